@@ -0,0 +1,205 @@
+use std::io::{ Read, Write };
+use std::path::{ Path, PathBuf };
+
+use crate::AdbError;
+
+pub const DEFAULT_SERVER_PORT: u16 = 5037;
+
+#[cfg(windows)]
+const ADB_BINARY: &str = "adb.exe";
+#[cfg(not(windows))]
+const ADB_BINARY: &str = "adb";
+
+/// Parsed `adb version` output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdbVersion {
+    /// e.g. "1.0.41", the protocol version shared by client and server
+    pub version: String,
+    /// e.g. "34.0.5-10900879", the platform-tools release
+    pub revision: Option<String>,
+    pub installed_as: Option<String>,
+}
+
+impl AdbVersion {
+    /// the last component of `version`, which is what the server reports
+    pub fn protocol(&self) -> Option<u32> {
+        self.version.rsplit('.').next()?.parse().ok()
+    }
+}
+
+/// Candidate adb binaries, most specific first: the SDK env vars,
+/// every `PATH` entry, the default SDK install locations and the bundled copy.
+pub fn discover_adb() -> Vec<PathBuf> {
+    let mut candidates = Vec::new();
+    for var in ["ANDROID_HOME", "ANDROID_SDK_ROOT"] {
+        if let Some(sdk) = std::env::var_os(var) {
+            candidates.push(PathBuf::from(sdk).join("platform-tools").join(ADB_BINARY));
+        }
+    }
+    if let Some(paths) = std::env::var_os("PATH") {
+        for dir in std::env::split_paths(&paths) {
+            candidates.push(dir.join(ADB_BINARY));
+        }
+    }
+    for sdk in default_sdk_dirs() {
+        candidates.push(sdk.join("platform-tools").join(ADB_BINARY));
+    }
+    candidates.push(PathBuf::from("platform-tools").join(ADB_BINARY));
+
+    let mut found: Vec<PathBuf> = Vec::new();
+    for candidate in candidates {
+        if candidate.is_file() && !found.contains(&candidate) {
+            found.push(candidate);
+        }
+    }
+    found
+}
+
+/// The first adb found, else `adb` for the system to look up in `PATH`.
+pub fn default_adb_path() -> String {
+    match discover_adb().first() {
+        Some(path) => path.to_string_lossy().to_string(),
+        None => ADB_BINARY.to_string(),
+    }
+}
+
+fn default_sdk_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(local) = std::env::var_os("LOCALAPPDATA") {
+        dirs.push(Path::new(&local).join("Android").join("Sdk"));
+    }
+    if let Some(home) = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")) {
+        let home = PathBuf::from(home);
+        dirs.push(home.join("Library").join("Android").join("sdk"));
+        dirs.push(home.join("Android").join("Sdk"));
+        dirs.push(home.join("AppData").join("Local").join("Android").join("Sdk"));
+    }
+    dirs.push(PathBuf::from("/usr/lib/android-sdk"));
+    dirs.push(PathBuf::from("/opt/android-sdk"));
+    dirs
+}
+
+pub fn parse_adb_version(output: &str) -> Option<AdbVersion> {
+    let mut version = None;
+    let mut revision = None;
+    let mut installed_as = None;
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(v) = line.strip_prefix("Android Debug Bridge version") {
+            version = Some(v.trim().to_string());
+        } else if let Some(v) = line.strip_prefix("Version") {
+            revision = Some(v.trim().to_string());
+        } else if let Some(v) = line.strip_prefix("Installed as") {
+            installed_as = Some(v.trim().to_string());
+        }
+    }
+    Some(AdbVersion {
+        version: version?,
+        revision,
+        installed_as,
+    })
+}
+
+pub fn adb_version(adb_path: &str) -> Result<AdbVersion, AdbError> {
+    let output = crate::run_adb(adb_path, None, &["version"])?;
+    let stdout = String::from_utf8_lossy(&output);
+    match parse_adb_version(&stdout) {
+        Some(version) => Ok(version),
+        None => {
+            let path = adb_path.trim().trim_matches('"');
+            Err(AdbError::Command(format!("{} is not adb: {}", path, stdout.trim())))
+        }
+    }
+}
+
+fn server_port() -> u16 {
+    std::env::var("ANDROID_ADB_SERVER_PORT")
+        .ok()
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(DEFAULT_SERVER_PORT)
+}
+
+/// The local adb server, on `ANDROID_ADB_SERVER_PORT` when it is set.
+pub fn server_address() -> std::net::SocketAddr {
+    std::net::SocketAddr::from(([127, 0, 0, 1], server_port()))
+}
+
+/// Asks a running server for its protocol version, None if no server is listening.
+pub fn server_version() -> Option<u32> {
+    let address = server_address();
+    let timeout = std::time::Duration::from_millis(500);
+    let mut stream = std::net::TcpStream::connect_timeout(&address, timeout).ok()?;
+    stream.set_read_timeout(Some(timeout)).ok()?;
+    let request = "host:version";
+    stream.write_all(format!("{:04x}{}", request.len(), request).as_bytes()).ok()?;
+    // OKAY + 4 hex digits of length + 4 hex digits of version
+    let mut response = [0u8; 12];
+    stream.read_exact(&mut response).ok()?;
+    if &response[..4] != b"OKAY" {
+        return None;
+    }
+    let version = std::str::from_utf8(&response[8..12]).ok()?;
+    u32::from_str_radix(version, 16).ok()
+}
+
+pub fn start_server(adb_path: &str) -> Result<(), AdbError> {
+    match crate::run_adb(adb_path, None, &["start-server"]) {
+        Ok(_) => Ok(()),
+        Err(AdbError::Command(message)) => Err(AdbError::ServerDown(message)),
+        Err(err) => Err(err),
+    }
+}
+
+/// Result of validating an adb binary against the running server.
+#[derive(Debug, Clone)]
+pub struct AdbStatus {
+    pub path: String,
+    pub version: AdbVersion,
+    pub server_version: Option<u32>,
+}
+
+impl AdbStatus {
+    pub fn describe(&self) -> String {
+        let revision = self.version.revision.as_deref().unwrap_or("?");
+        format!("adb {} ({})", self.version.version, revision)
+    }
+}
+
+/// Checks `adb version`, starts the server if it is down and
+/// reports a server left running by a different adb release.
+pub fn check_adb(adb_path: &str) -> Result<AdbStatus, AdbError> {
+    let version = adb_version(adb_path)?;
+    if server_version().is_none() {
+        start_server(adb_path)?;
+    }
+    let server_version = server_version();
+    let client = version.protocol();
+    if let (Some(server), Some(client)) = (server_version, client) {
+        if server != client {
+            return Err(AdbError::VersionMismatch { server, client });
+        }
+    }
+    Ok(AdbStatus {
+        path: adb_path.trim().trim_matches('"').to_string(),
+        version,
+        server_version,
+    })
+}
+
+/// Validates `preferred` and falls back to discovered binaries;
+/// on failure returns the error of the preferred path.
+pub fn find_working_adb(preferred: &str) -> Result<AdbStatus, AdbError> {
+    let preferred_error = match check_adb(preferred) {
+        Ok(status) => {
+            return Ok(status);
+        }
+        Err(err) => err,
+    };
+    for candidate in discover_adb() {
+        let candidate = candidate.to_string_lossy().to_string();
+        if let Ok(status) = check_adb(&candidate) {
+            return Ok(status);
+        }
+    }
+    Err(preferred_error)
+}
@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+
+use adbx::session::{ SessionBookmark, SessionLine };
+
+/// A marked log row with an optional note, e.g. "request sent here".
+#[derive(Clone)]
+pub struct Bookmark {
+    /// `ColorLog::index` of the row
+    pub index: usize,
+    /// the marked line, so the list still reads well without the logs
+    pub line: String,
+    pub note: String,
+}
+
+pub struct Bookmarks {
+    pub open: bool,
+    marks: BTreeMap<usize, Bookmark>,
+}
+
+impl Bookmarks {
+    pub fn new() -> Self {
+        Bookmarks {
+            open: false,
+            marks: BTreeMap::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.marks.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Bookmark> {
+        self.marks.get(&index)
+    }
+
+    pub fn toggle(&mut self, index: usize, line: &str) {
+        if self.marks.remove(&index).is_none() {
+            self.marks.insert(index, Bookmark {
+                index,
+                line: line.to_string(),
+                note: String::new(),
+            });
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.marks.clear();
+    }
+
+    pub fn to_session(&self) -> Vec<SessionBookmark> {
+        self.marks
+            .values()
+            .map(|b| SessionBookmark {
+                line: b.index,
+                note: b.note.clone(),
+            })
+            .collect()
+    }
+
+    pub fn load_session(&mut self, bookmarks: &[SessionBookmark], lines: &[SessionLine]) {
+        self.marks = bookmarks
+            .iter()
+            .filter_map(|b| {
+                let line = lines.get(b.line)?;
+                Some((b.line, Bookmark {
+                    index: b.line,
+                    line: line.raw.clone(),
+                    note: b.note.clone(),
+                }))
+            })
+            .collect();
+    }
+
+    /// The first bookmark after `current`, wrapping around.
+    pub fn next(&self, current: Option<usize>) -> Option<usize> {
+        let after = match current {
+            Some(current) => self.marks.range(current + 1..).next(),
+            None => None,
+        };
+        after.or_else(|| self.marks.iter().next()).map(|(i, _)| *i)
+    }
+
+    /// The last bookmark before `current`, wrapping around.
+    pub fn previous(&self, current: Option<usize>) -> Option<usize> {
+        let before = match current {
+            Some(current) => self.marks.range(..current).next_back(),
+            None => None,
+        };
+        before.or_else(|| self.marks.iter().next_back()).map(|(i, _)| *i)
+    }
+
+    /// Lists the bookmarks in a side panel, returns the one clicked.
+    /// `hint` names the current key bindings.
+    pub fn show(&mut self, ctx: &egui::Context, hint: &str) -> Option<usize> {
+        if !self.open {
+            return None;
+        }
+        let mut jump = None;
+        let mut removed = None;
+        egui::SidePanel
+            ::right("bookmarks")
+            .resizable(true)
+            .default_width(260.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.heading("Bookmarks");
+                    if !self.marks.is_empty() && ui.small_button("Clear").clicked() {
+                        self.marks.clear();
+                    }
+                });
+                ui.weak(hint);
+                ui.separator();
+                egui::ScrollArea
+                    ::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        for (index, bookmark) in self.marks.iter_mut() {
+                            ui.horizontal(|ui| {
+                                let text = format!("#{} {}", index, bookmark.line);
+                                let label = egui::Label
+                                    ::new(egui::RichText::new(text).monospace())
+                                    .truncate(true)
+                                    .sense(egui::Sense::click());
+                                if ui.add(label).on_hover_text(&bookmark.line).clicked() {
+                                    jump = Some(*index);
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                if ui.small_button("✖").clicked() {
+                                    removed = Some(*index);
+                                }
+                                ui.add(
+                                    egui::TextEdit
+                                        ::singleline(&mut bookmark.note)
+                                        .hint_text("note")
+                                        .desired_width(f32::INFINITY)
+                                );
+                            });
+                            ui.separator();
+                        }
+                    });
+            });
+        if let Some(index) = removed {
+            self.marks.remove(&index);
+        }
+        jump
+    }
+}
@@ -0,0 +1,240 @@
+use std::io::{ BufRead, BufReader };
+use std::process::Child;
+use std::sync::{ Arc, Mutex };
+use std::thread::JoinHandle;
+
+use crate::AdbError;
+
+const MARKER_PREFIX: &str = "--------- adbx ";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MarkerKind {
+    Screenshot,
+    RecordStart,
+    RecordStop,
+}
+
+impl MarkerKind {
+    const ALL: [MarkerKind; 3] = [
+        MarkerKind::Screenshot,
+        MarkerKind::RecordStart,
+        MarkerKind::RecordStop,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            MarkerKind::Screenshot => "screenshot",
+            MarkerKind::RecordStart => "screenrecord start",
+            MarkerKind::RecordStop => "screenrecord stop",
+        }
+    }
+}
+
+/// A line inserted into the log view, in the style of logcat's own
+/// `--------- beginning of main`, pointing at a file captured at that moment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marker {
+    pub kind: MarkerKind,
+    /// `session::format_utc` of the capture time
+    pub time: String,
+    pub path: String,
+}
+
+impl Marker {
+    pub fn new(kind: MarkerKind, millis: u64, path: &str) -> Self {
+        Marker {
+            kind,
+            time: crate::session::format_utc(millis),
+            path: path.to_string(),
+        }
+    }
+
+    /// e.g. `--------- adbx screenshot at 2024-01-02 03:04:05 UTC: shots/a.png`
+    pub fn line(&self) -> String {
+        format!("{}{} at {}: {}", MARKER_PREFIX, self.kind.name(), self.time, self.path)
+    }
+
+    pub fn parse(line: &str) -> Option<Marker> {
+        let rest = line.strip_prefix(MARKER_PREFIX)?;
+        let (name, rest) = rest.split_once(" at ")?;
+        let kind = MarkerKind::ALL.into_iter().find(|kind| kind.name() == name)?;
+        // the time ends with UTC, the path may hold anything
+        let (time, path) = rest.split_once(": ")?;
+        Some(Marker {
+            kind,
+            time: time.to_string(),
+            path: path.to_string(),
+        })
+    }
+}
+
+/// A decoded screenshot, 8 bit RGBA rows top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub rgba: Vec<u8>,
+}
+
+/// `adb exec-out screencap -p`, the PNG bytes of the current screen.
+pub fn capture_screenshot(adb_path: &str, device: &str) -> Result<Vec<u8>, AdbError> {
+    let png = crate::run_adb(adb_path, Some(device), &["exec-out", "screencap", "-p"])?;
+    if !png.starts_with(b"\x89PNG") {
+        // screencap prints its errors to stdout
+        let message = String::from_utf8_lossy(&png).trim().to_string();
+        return Err(AdbError::Command(format!("screencap failed: {}", message)));
+    }
+    Ok(png)
+}
+
+/// Decodes any PNG that screencap or a user may produce into RGBA.
+pub fn decode_png(bytes: &[u8]) -> std::io::Result<Image> {
+    let invalid = |err: png::DecodingError| {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string())
+    };
+    let mut decoder = png::Decoder::new(bytes);
+    // palettes, low bit depths and 16 bit channels all become 8 bit
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(invalid)?;
+    let mut buf = vec![0u8; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(invalid)?;
+    buf.truncate(info.buffer_size());
+    let rgba = match info.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => {
+            buf.chunks_exact(3)
+                .flat_map(|p| [p[0], p[1], p[2], 255])
+                .collect()
+        }
+        png::ColorType::Grayscale => {
+            buf.iter()
+                .flat_map(|&g| [g, g, g, 255])
+                .collect()
+        }
+        png::ColorType::GrayscaleAlpha => {
+            buf.chunks_exact(2)
+                .flat_map(|p| [p[0], p[0], p[0], p[1]])
+                .collect()
+        }
+        png::ColorType::Indexed => {
+            let message = "indexed PNG was not expanded".to_string();
+            return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, message));
+        }
+    };
+    Ok(Image {
+        width: info.width as usize,
+        height: info.height as usize,
+        rgba,
+    })
+}
+
+/// `adb shell screenrecord` writing an mp4 on the device until `stop`.
+pub struct ScreenRecording {
+    pub device: String,
+    /// the mp4 on the device
+    pub remote: String,
+    child: Child,
+    // pid of the shell that execs screenrecord and `date` right before it starts
+    started: Arc<Mutex<Option<(u32, String)>>>,
+    reader: Option<JoinHandle<()>>,
+}
+
+impl ScreenRecording {
+    /// Starts recording to `remote`, screenrecord stops on its own after
+    /// `time_limit` seconds, 180 at most.
+    pub fn start(
+        adb_path: &str,
+        device: &str,
+        remote: &str,
+        time_limit: u32
+    ) -> Result<Self, AdbError> {
+        let path = adb_path.trim().trim_matches('"');
+        // exec keeps the pid, so stop signals this screenrecord and no other
+        let command = format!(
+            "echo $$ $(date '+%m-%d %H:%M:%S'); exec screenrecord --time-limit {} {}",
+            time_limit.clamp(1, 180),
+            crate::files::shell_quote(remote)
+        );
+        let mut child = crate
+            ::adb_command(adb_path, Some(device))
+            .args(["shell", &command])
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .map_err(|err| AdbError::from_spawn(path, err))?;
+        let stdout = child.stdout.take().expect("!stdout");
+        let started = Arc::new(Mutex::new(None));
+        let shared = started.clone();
+        let reader = std::thread::spawn(move || {
+            let mut lines = BufReader::new(stdout).lines();
+            if let Some(Ok(line)) = lines.next() {
+                let parsed = line.trim().split_once(' ').and_then(|(pid, date)| {
+                    Some((pid.parse::<u32>().ok()?, date.to_string()))
+                });
+                *shared.lock().expect("!lock") = parsed;
+            }
+            // screenrecord says nothing unless it fails, keep the pipe drained
+            for line in lines.map_while(Result::ok) {
+                ::log::warn!("screenrecord: {}", line);
+            }
+        });
+        Ok(ScreenRecording {
+            device: device.to_string(),
+            remote: remote.to_string(),
+            child,
+            started,
+            reader: Some(reader),
+        })
+    }
+
+    /// `MM-DD HH:MM:SS` on the device when the recording began.
+    pub fn device_start(&self) -> Option<String> {
+        self.started
+            .lock()
+            .expect("!lock")
+            .as_ref()
+            .map(|(_, date)| date.clone())
+    }
+
+    /// False once screenrecord hit its time limit or failed.
+    pub fn is_running(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    /// Interrupts screenrecord, which finishes the mp4 before exiting.
+    /// Blocks until it did, call it off the UI thread.
+    pub fn stop(mut self, adb_path: &str) -> Result<(), AdbError> {
+        // the pid comes right after the start, wait for it a little when stopped at once
+        let mut pid = None;
+        for _ in 0..100 {
+            pid = self.started.lock().expect("!lock").as_ref().map(|(pid, _)| *pid);
+            if pid.is_some() || !self.is_running() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(20));
+        }
+        if self.is_running() {
+            let Some(pid) = pid else {
+                return Err(AdbError::Command("screenrecord did not report its pid".to_string()));
+            };
+            // killing adb would leave the mp4 without its index
+            let kill = format!("kill -INT {}", pid);
+            crate::run_adb(adb_path, Some(&self.device), &["shell", &kill])?;
+        }
+        self.child.wait()?;
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ScreenRecording {
+    fn drop(&mut self) {
+        if self.reader.is_some() {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+}
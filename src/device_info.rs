@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::AdbError;
+
+/// `dumpsys battery`, temperature in tenths of a degree as reported.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Battery {
+    pub level: Option<u32>,
+    pub scale: Option<u32>,
+    /// `Charging`, `Discharging`, `Not charging` or `Full`
+    pub status: Option<String>,
+    pub health: Option<String>,
+    /// `AC`, `USB` or `Wireless`, none on battery
+    pub plugged: Option<String>,
+    pub temperature: Option<i32>,
+    /// millivolts
+    pub voltage: Option<u32>,
+    pub technology: Option<String>,
+}
+
+impl Battery {
+    pub fn percent(&self) -> Option<u32> {
+        let level = self.level?;
+        match self.scale {
+            Some(scale) if scale > 0 => Some((level * 100) / scale),
+            _ => Some(level),
+        }
+    }
+}
+
+// BatteryManager codes, 0 is not used
+const BATTERY_STATUS: [&str; 6] = [
+    "",
+    "Unknown",
+    "Charging",
+    "Discharging",
+    "Not charging",
+    "Full",
+];
+const BATTERY_HEALTH: [&str; 8] = [
+    "",
+    "Unknown",
+    "Good",
+    "Overheat",
+    "Dead",
+    "Over voltage",
+    "Unspecified failure",
+    "Cold",
+];
+
+/// Parses `dumpsys battery`, the status and health codes are named.
+pub fn parse_battery(text: &str) -> Battery {
+    let mut battery = Battery::default();
+    let mut plugged = Vec::new();
+    for line in text.lines() {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "level" => {
+                battery.level = value.parse().ok();
+            }
+            "scale" => {
+                battery.scale = value.parse().ok();
+            }
+            "status" => {
+                let name = value.parse::<usize>().ok().and_then(|code| BATTERY_STATUS.get(code));
+                battery.status = name.filter(|name| !name.is_empty()).map(|name| name.to_string());
+            }
+            "health" => {
+                let name = value.parse::<usize>().ok().and_then(|code| BATTERY_HEALTH.get(code));
+                battery.health = name.filter(|name| !name.is_empty()).map(|name| name.to_string());
+            }
+            "AC powered" | "USB powered" | "Wireless powered" if value == "true" => {
+                plugged.push(key.trim_end_matches(" powered"));
+            }
+            "temperature" => {
+                battery.temperature = value.parse().ok();
+            }
+            "voltage" => {
+                battery.voltage = value.parse().ok();
+            }
+            "technology" => {
+                battery.technology = Some(value.to_string()).filter(|t| !t.is_empty());
+            }
+            _ => {}
+        }
+    }
+    if !plugged.is_empty() {
+        battery.plugged = Some(plugged.join(", "));
+    }
+    battery
+}
+
+/// `wm size` and `wm density`, the override is what apps see.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Display {
+    pub size: Option<(u32, u32)>,
+    pub override_size: Option<(u32, u32)>,
+    pub density: Option<u32>,
+    pub override_density: Option<u32>,
+}
+
+/// Reads `Physical size: 1080x2400` and `Override size: 720x1600`.
+pub fn parse_wm_size(text: &str, display: &mut Display) {
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let size = value.trim().split_once('x').and_then(|(width, height)| {
+            Some((width.parse().ok()?, height.parse().ok()?))
+        });
+        match key.trim() {
+            "Physical size" => {
+                display.size = size;
+            }
+            "Override size" => {
+                display.override_size = size;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Reads `Physical density: 420` and `Override density: 360`.
+pub fn parse_wm_density(text: &str, display: &mut Display) {
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        match key.trim() {
+            "Physical density" => {
+                display.density = value.trim().parse().ok();
+            }
+            "Override density" => {
+                display.override_density = value.trim().parse().ok();
+            }
+            _ => {}
+        }
+    }
+}
+
+/// A line of `df -k`, sizes in KiB.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Filesystem {
+    pub source: String,
+    pub size: u64,
+    pub used: u64,
+    pub available: u64,
+    pub mounted_on: String,
+}
+
+/// Parses `df -k`, the header and the lines of mounts that could not be read are skipped.
+pub fn parse_df(text: &str) -> Vec<Filesystem> {
+    text.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            // Filesystem 1K-blocks Used Available Use% Mounted on
+            let [source, size, used, available, _, mounted_on] = fields[..] else {
+                return None;
+            };
+            Some(Filesystem {
+                source: source.to_string(),
+                size: size.parse().ok()?,
+                used: used.parse().ok()?,
+                available: available.parse().ok()?,
+                mounted_on: mounted_on.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// `/proc/meminfo` in KiB.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Memory {
+    pub total: Option<u64>,
+    pub free: Option<u64>,
+    /// Linux 3.14 and up
+    pub available: Option<u64>,
+    pub cached: Option<u64>,
+    pub swap_total: Option<u64>,
+    pub swap_free: Option<u64>,
+}
+
+pub fn parse_meminfo(text: &str) -> Memory {
+    let values: HashMap<&str, u64> = text
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let value = value.trim().trim_end_matches("kB").trim();
+            Some((key.trim(), value.parse().ok()?))
+        })
+        .collect();
+    Memory {
+        total: values.get("MemTotal").copied(),
+        free: values.get("MemFree").copied(),
+        available: values.get("MemAvailable").copied(),
+        cached: values.get("Cached").copied(),
+        swap_total: values.get("SwapTotal").copied(),
+        swap_free: values.get("SwapFree").copied(),
+    }
+}
+
+/// A snapshot of the device, kept with the sessions it was taken for.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct DeviceInfo {
+    pub serial: String,
+    /// milliseconds since the unix epoch
+    pub captured_at: u64,
+    /// every `getprop` property
+    pub props: HashMap<String, String>,
+    pub battery: Battery,
+    pub display: Display,
+    pub storage: Vec<Filesystem>,
+    pub memory: Memory,
+}
+
+impl DeviceInfo {
+    pub fn prop(&self, key: &str) -> Option<&str> {
+        self.props.get(key).map(|value| value.as_str()).filter(|value| !value.is_empty())
+    }
+
+    /// `Google Pixel 7`
+    pub fn model(&self) -> Option<String> {
+        let model = self.prop("ro.product.model")?;
+        match self.prop("ro.product.manufacturer") {
+            Some(maker) if !model.to_lowercase().starts_with(&maker.to_lowercase()) => {
+                Some(format!("{} {}", maker, model))
+            }
+            _ => Some(model.to_string()),
+        }
+    }
+
+    pub fn sdk(&self) -> Option<u32> {
+        self.prop("ro.build.version.sdk")?.parse().ok()
+    }
+
+    /// All ABIs the device runs, the preferred one first.
+    pub fn abis(&self) -> Vec<&str> {
+        match self.prop("ro.product.cpu.abilist").or_else(|| self.prop("ro.product.cpu.abi")) {
+            Some(list) => list.split(',').map(|abi| abi.trim()).collect(),
+            None => Vec::new(),
+        }
+    }
+}
+
+// df exits 1 over the mounts the shell may not read, the rest of its output is good
+fn shell_lenient(adb_path: &str, device: &str, args: &[&str]) -> Result<String, AdbError> {
+    let output = crate::adb_command(adb_path, Some(device))
+        .args(args)
+        .output()
+        .map_err(|err| AdbError::from_spawn(adb_path.trim().trim_matches('"'), err))?;
+    if !output.status.success() && output.stdout.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AdbError::from_stderr(Some(device), &stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// Runs `getprop`, `dumpsys battery`, `wm size`, `wm density`, `df -k` and
+/// `cat /proc/meminfo` on the device.
+pub fn collect(adb_path: &str, device: &str) -> Result<DeviceInfo, AdbError> {
+    let run = |args: &[&str]| -> Result<String, AdbError> {
+        let output = crate::run_adb(adb_path, Some(device), args)?;
+        Ok(String::from_utf8_lossy(&output).to_string())
+    };
+    let mut display = Display::default();
+    parse_wm_size(&run(&["shell", "wm", "size"])?, &mut display);
+    parse_wm_density(&run(&["shell", "wm", "density"])?, &mut display);
+    Ok(DeviceInfo {
+        serial: device.to_string(),
+        captured_at: crate::session::unix_millis(std::time::SystemTime::now()),
+        props: crate::get_props(adb_path, device)?,
+        battery: parse_battery(&run(&["shell", "dumpsys", "battery"])?),
+        display,
+        storage: parse_df(&shell_lenient(adb_path, device, &["shell", "df", "-k"])?),
+        memory: parse_meminfo(&run(&["shell", "cat", "/proc/meminfo"])?),
+    })
+}
@@ -0,0 +1,277 @@
+use std::thread::JoinHandle;
+
+use adbx::device_info::{ self, DeviceInfo };
+use adbx::session;
+use adbx::AdbError;
+
+use crate::panel_status::PanelStatus;
+use crate::process_monitor::format_kib;
+
+/// Model, build, battery, display, storage and memory of the selected device,
+/// with every `getprop` property.
+pub struct DeviceInfoPanel {
+    pub open: bool,
+    info: Option<DeviceInfo>,
+    // device shown last, an error about another one does not hold the refresh back
+    device: Option<String>,
+    collect: Option<(String, JoinHandle<Result<DeviceInfo, AdbError>>)>,
+    // the snapshot saved with the replayed session instead of the device
+    show_session: bool,
+    search: String,
+    status: PanelStatus,
+}
+
+impl DeviceInfoPanel {
+    pub fn new() -> Self {
+        DeviceInfoPanel {
+            open: false,
+            info: None,
+            device: None,
+            collect: None,
+            show_session: true,
+            search: String::new(),
+            status: PanelStatus::default(),
+        }
+    }
+
+    fn refresh(&mut self, ctx: &egui::Context, adb_path: &str, device: &str) {
+        if self.collect.is_some() {
+            return;
+        }
+        let (adb_path, serial) = (adb_path.to_string(), device.to_string());
+        let ctx = ctx.clone();
+        let handle = std::thread::spawn(move || {
+            let info = device_info::collect(&adb_path, &serial);
+            ctx.request_repaint();
+            info
+        });
+        self.collect = Some((device.to_string(), handle));
+    }
+
+    fn poll(&mut self, adb_path: &str) {
+        let finished = self.collect.as_ref().is_some_and(|(_, handle)| handle.is_finished());
+        let Some((device, handle)) = finished.then(|| self.collect.take()).flatten() else {
+            return;
+        };
+        let command = adbx::command_line(adb_path, Some(&device), &["shell", "getprop"]);
+        match handle.join() {
+            Ok(Ok(info)) => {
+                self.info = Some(info);
+                self.status.clear_error();
+            }
+            Ok(Err(err)) => self.status.report(command, err.to_string()),
+            Err(_) => self.status.report(command, "device info thread panicked".to_string()),
+        }
+    }
+
+    /// `session` is the snapshot of the replayed session, if it has one.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        adb_path: &str,
+        device: Option<&str>,
+        session: Option<&DeviceInfo>
+    ) {
+        self.poll(adb_path);
+        if self.device.as_deref() != device {
+            self.device = device.map(|device| device.to_string());
+            self.status.clear_error();
+        }
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        egui::Window
+            ::new("Device Info")
+            .open(&mut open)
+            .default_size(egui::vec2(560.0, 600.0))
+            .resizable(true)
+            .show(ctx, |ui| {
+                let stale = self.info.as_ref().map(|info| info.serial.as_str()) != device;
+                if let Some(device) = device.filter(|_| stale && !self.status.has_error()) {
+                    self.refresh(ctx, adb_path, device);
+                }
+                ui.horizontal(|ui| {
+                    if let Some(device) = device {
+                        if ui.button("Refresh").clicked() {
+                            self.refresh(ctx, adb_path, device);
+                        }
+                    }
+                    if session.is_some() {
+                        ui.checkbox(&mut self.show_session, "Session snapshot");
+                    }
+                    if self.collect.is_some() {
+                        ui.spinner();
+                    }
+                });
+                self.status.show_error(ui);
+                let info = match session.filter(|_| self.show_session) {
+                    Some(info) => Some(info),
+                    None => self.info.as_ref().filter(|_| !stale),
+                };
+                let Some(info) = info else {
+                    if device.is_none() {
+                        ui.label("No device selected");
+                    }
+                    return;
+                };
+                ui.weak(format!("{} at {}", info.serial, session::format_utc(info.captured_at)));
+                ui.separator();
+                egui::ScrollArea
+                    ::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        show_summary(ui, info);
+                        show_storage(ui, info);
+                        show_props(ui, info, &mut self.search);
+                    });
+            });
+        self.open = open;
+    }
+}
+
+fn show_summary(ui: &mut egui::Ui, info: &DeviceInfo) {
+    let android = match (info.prop("ro.build.version.release"), info.sdk()) {
+        (Some(release), Some(sdk)) => format!("{} (SDK {})", release, sdk),
+        (Some(release), None) => release.to_string(),
+        (None, Some(sdk)) => format!("SDK {}", sdk),
+        (None, None) => String::new(),
+    };
+    let mut display = Vec::new();
+    if let Some((width, height)) = info.display.size {
+        display.push(format!("{}x{}", width, height));
+    }
+    if let Some(density) = info.display.density {
+        display.push(format!("{} dpi", density));
+    }
+    let overridden: Vec<String> = [
+        info.display.override_size.map(|(width, height)| format!("{}x{}", width, height)),
+        info.display.override_density.map(|density| format!("{} dpi", density)),
+    ]
+        .into_iter()
+        .flatten()
+        .collect();
+    let mut display = display.join(", ");
+    if !overridden.is_empty() {
+        display.push_str(&format!(" (override {})", overridden.join(", ")));
+    }
+    let battery = &info.battery;
+    let mut power = Vec::new();
+    if let Some(percent) = battery.percent() {
+        power.push(format!("{}%", percent));
+    }
+    match (battery.status.as_ref(), battery.plugged.as_ref()) {
+        (Some(status), Some(plugged)) => power.push(format!("{} ({})", status, plugged)),
+        (Some(status), None) => power.push(status.clone()),
+        _ => {}
+    }
+    if let Some(health) = battery.health.as_ref() {
+        power.push(health.clone());
+    }
+    if let Some(temperature) = battery.temperature {
+        power.push(format!("{:.1} °C", (temperature as f32) / 10.0));
+    }
+    if let Some(voltage) = battery.voltage {
+        power.push(format!("{:.2} V", (voltage as f32) / 1000.0));
+    }
+    let memory = &info.memory;
+    let mut ram = Vec::new();
+    if let Some(total) = memory.total {
+        ram.push(format!("{} total", format_kib(total)));
+    }
+    if let Some(available) = memory.available.or(memory.free) {
+        ram.push(format!("{} available", format_kib(available)));
+    }
+    if let Some(swap) = memory.swap_total.filter(|swap| *swap > 0) {
+        let free = memory.swap_free.unwrap_or(0);
+        ram.push(format!("swap {} free of {}", format_kib(free), format_kib(swap)));
+    }
+    let prop = |key: &str| info.prop(key).unwrap_or_default().to_string();
+    let rows = [
+        ("model", info.model().unwrap_or_default()),
+        ("android", android),
+        ("build", prop("ro.build.display.id")),
+        ("security patch", prop("ro.build.version.security_patch")),
+        ("fingerprint", prop("ro.build.fingerprint")),
+        ("abi", info.abis().join(", ")),
+        ("display", display),
+        ("battery", power.join(", ")),
+        ("memory", ram.join(", ")),
+    ];
+    egui::Grid
+        ::new("device_info_summary")
+        .num_columns(2)
+        .striped(true)
+        .show(ui, |ui| {
+            for (label, value) in rows {
+                ui.label(label);
+                ui.monospace(value);
+                ui.end_row();
+            }
+        });
+}
+
+fn show_storage(ui: &mut egui::Ui, info: &DeviceInfo) {
+    egui::CollapsingHeader
+        ::new("Storage")
+        .id_source("device_info_storage")
+        .default_open(true)
+        .show(ui, |ui| {
+            egui::Grid
+                ::new("device_info_storage_grid")
+                .num_columns(5)
+                .striped(true)
+                .show(ui, |ui| {
+                    for title in ["mounted on", "size", "used", "available", "use"] {
+                        ui.strong(title);
+                    }
+                    ui.end_row();
+                    for fs in info.storage.iter().filter(|fs| fs.size > 0) {
+                        ui.label(&fs.mounted_on).on_hover_text(&fs.source);
+                        ui.monospace(format_kib(fs.size));
+                        ui.monospace(format_kib(fs.used));
+                        ui.monospace(format_kib(fs.available));
+                        ui.add(
+                            egui::ProgressBar
+                                ::new((fs.used as f32) / (fs.size as f32))
+                                .desired_width(80.0)
+                                .show_percentage()
+                        );
+                        ui.end_row();
+                    }
+                });
+        });
+}
+
+fn show_props(ui: &mut egui::Ui, info: &DeviceInfo, search: &mut String) {
+    egui::CollapsingHeader
+        ::new(format!("Properties ({})", info.props.len()))
+        .id_source("device_info_props")
+        .show(ui, |ui| {
+            ui.add(
+                egui::TextEdit
+                    ::singleline(search)
+                    .hint_text("search keys and values")
+                    .desired_width(240.0)
+            );
+            let search = search.trim().to_lowercase();
+            let mut props: Vec<(&String, &String)> = info.props
+                .iter()
+                .filter(|(key, value)| {
+                    key.to_lowercase().contains(&search) || value.to_lowercase().contains(&search)
+                })
+                .collect();
+            props.sort();
+            egui::Grid
+                ::new("device_info_props_grid")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    for (key, value) in props {
+                        ui.monospace(key);
+                        ui.monospace(value);
+                        ui.end_row();
+                    }
+                });
+        });
+}
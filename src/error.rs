@@ -0,0 +1,121 @@
+use std::fmt;
+
+/// Failures of adb invocations, classified so the UI can say what to do about them.
+#[derive(Debug)]
+pub enum AdbError {
+    /// the adb binary does not exist at this path
+    NotFound(String),
+    /// the adb binary exists but cannot run on this machine
+    WrongArchitecture(String),
+    /// no adb server is running and it could not be started
+    ServerDown(String),
+    /// the running server belongs to a different adb release
+    VersionMismatch {
+        server: u32,
+        client: u32,
+    },
+    DeviceNotFound(String),
+    DeviceOffline(String),
+    /// the device has not accepted this computer's RSA key
+    Unauthorized(String),
+    /// adb ran but reported an error
+    Command(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for AdbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AdbError::NotFound(path) => write!(f, "adb not found at {}", path),
+            AdbError::WrongArchitecture(path) => {
+                write!(f, "{} was built for another architecture or OS", path)
+            }
+            AdbError::ServerDown(message) => write!(f, "adb server is not running: {}", message),
+            AdbError::VersionMismatch { server, client } => {
+                write!(f, "adb server version ({}) doesn't match this client ({})", server, client)
+            }
+            AdbError::DeviceNotFound(device) => write!(f, "device '{}' not found", device),
+            AdbError::DeviceOffline(device) => write!(f, "device '{}' is offline", device),
+            AdbError::Unauthorized(device) => {
+                write!(f, "device '{}' is unauthorized, accept the prompt on the device", device)
+            }
+            AdbError::Command(message) => write!(f, "{}", message),
+            AdbError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for AdbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            AdbError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for AdbError {
+    fn from(err: std::io::Error) -> Self {
+        AdbError::Io(err)
+    }
+}
+
+impl AdbError {
+    /// Classifies a failure to spawn the adb binary at `path`.
+    pub fn from_spawn(path: &str, err: std::io::Error) -> Self {
+        // ENOEXEC on unix, ERROR_BAD_EXE_FORMAT on windows
+        let wrong_format = if cfg!(windows) { 193 } else { 8 };
+        if err.raw_os_error() == Some(wrong_format) {
+            return AdbError::WrongArchitecture(path.to_string());
+        }
+        match err.kind() {
+            std::io::ErrorKind::NotFound => AdbError::NotFound(path.to_string()),
+            _ => AdbError::Io(err),
+        }
+    }
+
+    /// Classifies the stderr of an adb command that exited with an error.
+    pub fn from_stderr(device: Option<&str>, stderr: &str) -> Self {
+        let message = stderr.trim();
+        // remote commands write their own stderr, e.g. "sh: pidof: not found",
+        // only the messages of the adb client itself are classified
+        for line in message.lines().map(str::trim) {
+            let daemon = ["cannot connect to daemon", "failed to start daemon"];
+            if daemon.iter().any(|text| line.contains(text)) {
+                return AdbError::ServerDown(message.to_string());
+            }
+            let client = line.strip_prefix("adb: ").or_else(|| line.strip_prefix("error: "));
+            if let Some(err) = client.and_then(|client| Self::from_device_state(device, client)) {
+                return err;
+            }
+        }
+        if message.is_empty() {
+            AdbError::Command("adb exited with an error".to_string())
+        } else {
+            AdbError::Command(message.trim_start_matches("adb: ").to_string())
+        }
+    }
+
+    /// Classifies the FAIL message of the adb server, e.g. "device offline".
+    pub fn from_server(device: Option<&str>, message: &str) -> Self {
+        let message = message.trim();
+        Self::from_device_state(device, message)
+            .unwrap_or_else(|| AdbError::Command(message.to_string()))
+    }
+
+    fn from_device_state(device: Option<&str>, message: &str) -> Option<Self> {
+        let device = device.unwrap_or("").to_string();
+        if message.starts_with("device unauthorized") {
+            Some(AdbError::Unauthorized(device))
+        } else if message.starts_with("device offline") {
+            Some(AdbError::DeviceOffline(device))
+        } else if
+            (message.starts_with("device ") && message.ends_with("not found")) ||
+            message.starts_with("no devices")
+        {
+            Some(AdbError::DeviceNotFound(device))
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,457 @@
+use std::path::{ Path, PathBuf };
+
+use adbx::files::{ self, Direction, FileEntry, FileKind, Transfer };
+
+use crate::panel_status::PanelStatus;
+
+// what the buttons under the listing asked for
+enum EntryAction {
+    Pull,
+    // names in the listed directory, the old one as it was when Rename was clicked
+    Rename {
+        from: String,
+        to: String,
+    },
+    Delete,
+}
+
+/// Browses the files of the selected device, with `run-as` for app private data.
+pub struct FileExplorer {
+    pub open: bool,
+    path: String,
+    path_edit: String,
+    run_as: String,
+    // the device the listing belongs to
+    device: Option<String>,
+    entries: Vec<FileEntry>,
+    selected: Option<String>,
+    // old name and the new one being typed
+    renaming: Option<(String, String)>,
+    confirm_delete: Option<String>,
+    new_dir: String,
+    push_path: String,
+    transfers: Vec<Transfer>,
+    status: PanelStatus,
+}
+
+impl FileExplorer {
+    pub fn new() -> Self {
+        FileExplorer {
+            open: false,
+            path: "/sdcard".to_string(),
+            path_edit: "/sdcard".to_string(),
+            run_as: String::new(),
+            device: None,
+            entries: Vec::new(),
+            selected: None,
+            renaming: None,
+            confirm_delete: None,
+            new_dir: String::new(),
+            push_path: String::new(),
+            transfers: Vec::new(),
+            status: PanelStatus::default(),
+        }
+    }
+
+    fn run_as(&self) -> Option<&str> {
+        Some(self.run_as.trim()).filter(|package| !package.is_empty())
+    }
+
+    // `adb shell [run-as pkg] <command>` for error messages
+    fn command_line(&self, adb_path: &str, device: &str, command: &str) -> String {
+        let command = match self.run_as() {
+            Some(package) => format!("run-as {} {}", package, command),
+            None => command.to_string(),
+        };
+        adbx::command_line(adb_path, Some(device), &["shell", &command])
+    }
+
+    pub fn refresh(&mut self, adb_path: &str, device: &str) {
+        self.status.clear_error();
+        self.device = Some(device.to_string());
+        self.path_edit = self.path.clone();
+        match files::list_dir(adb_path, device, self.run_as(), &self.path) {
+            Ok(mut entries) => {
+                // directories first, then by name
+                entries.sort_by(|a, b| {
+                    b.is_dir().cmp(&a.is_dir()).then_with(|| a.name.cmp(&b.name))
+                });
+                self.entries = entries;
+            }
+            Err(err) => {
+                self.entries.clear();
+                let command = format!("ls -la {}", self.path);
+                self.status.report(self.command_line(adb_path, device, &command), err.to_string());
+            }
+        }
+    }
+
+    fn navigate(&mut self, adb_path: &str, device: &str, path: String) {
+        self.path = path;
+        self.selected = None;
+        self.renaming = None;
+        self.confirm_delete = None;
+        self.refresh(adb_path, device);
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        adb_path: &str,
+        device: Option<&str>,
+        local_dir: &mut String
+    ) {
+        if !self.open {
+            return;
+        }
+        self.poll_transfers(adb_path);
+        if !self.transfers.is_empty() {
+            ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        }
+        let mut open = self.open;
+        egui::Window
+            ::new("Files")
+            .open(&mut open)
+            .default_size(egui::vec2(720.0, 480.0))
+            .resizable(true)
+            .show(ctx, |ui| {
+                let Some(device) = device else {
+                    ui.label("No device selected");
+                    return;
+                };
+                if self.device.as_deref() != Some(device) {
+                    self.refresh(adb_path, device);
+                }
+                self.show_toolbar(ui, adb_path, device, local_dir);
+                self.status.show(ui);
+                self.show_transfers(ui);
+                ui.separator();
+                self.show_entries(ui, adb_path, device, local_dir);
+                // files dropped on the window go to the current directory
+                let dropped: Vec<PathBuf> = ctx.input(|i| {
+                    i.raw.dropped_files
+                        .iter()
+                        .filter_map(|file| file.path.clone())
+                        .collect()
+                });
+                for path in dropped {
+                    self.push(adb_path, device, &path);
+                }
+            });
+        self.open = open;
+    }
+
+    fn show_toolbar(
+        &mut self,
+        ui: &mut egui::Ui,
+        adb_path: &str,
+        device: &str,
+        local_dir: &mut String
+    ) {
+        ui.horizontal(|ui| {
+            if ui.button("⬆").on_hover_text("Parent directory").clicked() {
+                let parent = files::parent_remote(&self.path);
+                self.navigate(adb_path, device, parent);
+            }
+            let edit = ui.add(
+                egui::TextEdit::singleline(&mut self.path_edit).desired_width(320.0)
+            );
+            let enter = edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if enter || ui.button("Go").clicked() {
+                let path = self.path_edit.trim().to_string();
+                self.navigate(adb_path, device, path);
+            }
+            if ui.button("Refresh").clicked() {
+                self.refresh(adb_path, device);
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("run-as:");
+            let edit = ui
+                .add(egui::TextEdit::singleline(&mut self.run_as).desired_width(200.0))
+                .on_hover_text("Package of a debuggable app, to browse its private files");
+            if edit.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                // private files live under the app's data directory
+                let path = match self.run_as() {
+                    Some(package) => format!("/data/data/{}", package),
+                    None => "/sdcard".to_string(),
+                };
+                self.navigate(adb_path, device, path);
+            }
+            ui.label("pull to:");
+            ui.add(egui::TextEdit::singleline(local_dir).desired_width(200.0));
+        });
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit
+                    ::singleline(&mut self.new_dir)
+                    .hint_text("new directory")
+                    .desired_width(160.0)
+            );
+            if ui.button("Mkdir").clicked() && !self.new_dir.trim().is_empty() {
+                let path = files::join_remote(&self.path, self.new_dir.trim());
+                match files::make_dir(adb_path, device, self.run_as(), &path) {
+                    Ok(()) => {
+                        self.new_dir.clear();
+                        self.refresh(adb_path, device);
+                    }
+                    Err(err) => {
+                        let command = format!("mkdir -p {}", path);
+                        self.status.report(
+                            self.command_line(adb_path, device, &command),
+                            err.to_string()
+                        );
+                    }
+                }
+            }
+            ui.add(
+                egui::TextEdit
+                    ::singleline(&mut self.push_path)
+                    .hint_text("local file, or drop files here")
+                    .desired_width(240.0)
+            );
+            if ui.button("Push").clicked() && !self.push_path.trim().is_empty() {
+                let path = PathBuf::from(self.push_path.trim().trim_matches('"'));
+                self.push(adb_path, device, &path);
+            }
+        });
+    }
+
+    fn show_transfers(&mut self, ui: &mut egui::Ui) {
+        for transfer in self.transfers.iter() {
+            ui.horizontal(|ui| {
+                let name = match transfer.direction {
+                    Direction::Pull => format!("pull {}", transfer.remote),
+                    Direction::Push => format!("push {}", transfer.local.display()),
+                };
+                let text = format!("{} ({})", name, format_size(transfer.done()));
+                let bar = match transfer.fraction() {
+                    Some(fraction) => egui::ProgressBar::new(fraction).show_percentage(),
+                    None => egui::ProgressBar::new(0.0).animate(true),
+                };
+                ui.add(bar.text(text).desired_width(400.0));
+                if transfer.is_cancelled() {
+                    ui.weak("cancelling");
+                } else if ui.button("Cancel").clicked() {
+                    transfer.cancel();
+                }
+            });
+        }
+    }
+
+    fn show_entries(
+        &mut self,
+        ui: &mut egui::Ui,
+        adb_path: &str,
+        device: &str,
+        local_dir: &str
+    ) {
+        let mut open_dir = None;
+        let mut action = None;
+        egui::ScrollArea
+            ::vertical()
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                egui::Grid
+                    ::new("file_entries")
+                    .num_columns(5)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("name");
+                        ui.strong("size");
+                        ui.strong("modified");
+                        ui.strong("mode");
+                        ui.end_row();
+                        for entry in self.entries.iter() {
+                            let selected = self.selected.as_ref() == Some(&entry.name);
+                            let name = match &entry.kind {
+                                FileKind::Dir => format!("📁 {}", entry.name),
+                                FileKind::Link(target) => format!("{} → {}", entry.name, target),
+                                _ => entry.name.clone(),
+                            };
+                            let label = ui.selectable_label(selected, name);
+                            if label.clicked() && !selected {
+                                self.selected = Some(entry.name.clone());
+                                self.renaming = None;
+                                self.confirm_delete = None;
+                            }
+                            // links are followed too, `ls` of a file link lists the file
+                            let enters = matches!(entry.kind, FileKind::Dir | FileKind::Link(_));
+                            if label.double_clicked() && enters {
+                                open_dir = Some(files::join_remote(&self.path, &entry.name));
+                            }
+                            ui.label(entry.size.map(format_size).unwrap_or_default());
+                            ui.label(&entry.modified);
+                            ui.monospace(&entry.mode);
+                            ui.end_row();
+                        }
+                    });
+            });
+        if let Some(path) = open_dir {
+            self.navigate(adb_path, device, path);
+            return;
+        }
+
+        let Some(name) = self.selected.clone() else {
+            return;
+        };
+        let Some(entry) = self.entries.iter().find(|e| e.name == name).cloned() else {
+            return;
+        };
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label(&name);
+            if entry.kind == FileKind::File && ui.button("Pull").clicked() {
+                action = Some(EntryAction::Pull);
+            }
+            match self.renaming.as_mut() {
+                Some((old_name, new_name)) => {
+                    ui.text_edit_singleline(new_name);
+                    if ui.button("Apply").clicked() {
+                        action = Some(EntryAction::Rename {
+                            from: old_name.clone(),
+                            to: new_name.trim().to_string(),
+                        });
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.renaming = None;
+                    }
+                }
+                None => {
+                    if ui.button("Rename").clicked() {
+                        self.renaming = Some((name.clone(), name.clone()));
+                    }
+                }
+            }
+            if self.confirm_delete.as_ref() == Some(&name) {
+                let confirm = egui::Button
+                    ::new("Confirm delete")
+                    .fill(egui::Color32::from_rgb(160, 40, 40));
+                if ui.add(confirm).clicked() {
+                    action = Some(EntryAction::Delete);
+                }
+            } else if ui.button("Delete").clicked() {
+                self.confirm_delete = Some(name.clone());
+            }
+        });
+
+        let remote = files::join_remote(&self.path, &name);
+        match action {
+            Some(EntryAction::Pull) => {
+                let dir = Path::new(local_dir.trim().trim_matches('"'));
+                if let Err(err) = std::fs::create_dir_all(dir) {
+                    self.status.report(format!("mkdir {}", dir.display()), err.to_string());
+                    return;
+                }
+                let local = dir.join(&name);
+                let run_as = self.run_as();
+                match Transfer::pull(adb_path, device, run_as, &remote, &local, entry.size) {
+                    Ok(transfer) => self.transfers.push(transfer),
+                    Err(err) => {
+                        self.status.report(format!("create {}", local.display()), err.to_string());
+                    }
+                }
+            }
+            Some(EntryAction::Rename { from, to: new_name }) => {
+                self.renaming = None;
+                let from = files::join_remote(&self.path, &from);
+                let to = files::join_remote(&self.path, &new_name);
+                match files::rename(adb_path, device, self.run_as(), &from, &to) {
+                    Ok(()) => {
+                        self.selected = Some(new_name);
+                        self.refresh(adb_path, device);
+                    }
+                    Err(err) => {
+                        let command = format!("mv {} {}", from, to);
+                        self.status.report(
+                            self.command_line(adb_path, device, &command),
+                            err.to_string()
+                        );
+                    }
+                }
+            }
+            Some(EntryAction::Delete) => {
+                self.confirm_delete = None;
+                match files::remove(adb_path, device, self.run_as(), &remote) {
+                    Ok(()) => {
+                        self.selected = None;
+                        self.refresh(adb_path, device);
+                    }
+                    Err(err) => {
+                        let command = format!("rm -rf {}", remote);
+                        self.status.report(
+                            self.command_line(adb_path, device, &command),
+                            err.to_string()
+                        );
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn push(&mut self, adb_path: &str, device: &str, local: &Path) {
+        let Some(name) = local.file_name() else {
+            return;
+        };
+        if !local.is_file() {
+            self.status.report(format!("push {}", local.display()), "not a file".to_string());
+            return;
+        }
+        let remote = files::join_remote(&self.path, &name.to_string_lossy());
+        match Transfer::push(adb_path, device, self.run_as(), local, &remote) {
+            Ok(transfer) => self.transfers.push(transfer),
+            Err(err) => self.status.report(format!("open {}", local.display()), err.to_string()),
+        }
+    }
+
+    fn poll_transfers(&mut self, adb_path: &str) {
+        let mut pushed = false;
+        let mut i = 0;
+        while i < self.transfers.len() {
+            if !self.transfers[i].is_finished() {
+                i += 1;
+                continue;
+            }
+            let transfer = self.transfers.remove(i);
+            let cancelled = transfer.is_cancelled();
+            let (direction, remote) = (transfer.direction, transfer.remote.clone());
+            let local = transfer.local.clone();
+            let name = match direction {
+                Direction::Pull => format!("pull {} to {}", remote, local.display()),
+                Direction::Push => format!("push {} to {}", local.display(), remote),
+            };
+            match transfer.join() {
+                Ok(()) if cancelled => {
+                    self.status.set(format!("{} cancelled", name));
+                }
+                Ok(()) => {
+                    ::log::info!("{}", name);
+                    self.status.set(format!("{} done", name));
+                    pushed |= direction == Direction::Push;
+                }
+                Err(err) => self.status.report(name, err.to_string()),
+            }
+        }
+        if pushed {
+            if let Some(device) = self.device.clone() {
+                self.refresh(adb_path, &device);
+            }
+        }
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit + 1 < UNITS.len() {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
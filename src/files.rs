@@ -0,0 +1,568 @@
+use std::io::{ Read, Write };
+use std::net::{ SocketAddr, TcpStream };
+use std::path::{ Path, PathBuf };
+use std::process::{ Child, Stdio };
+use std::sync::atomic::{ AtomicBool, AtomicU64, Ordering };
+use std::sync::{ Arc, Mutex };
+use std::thread::JoinHandle;
+
+use crate::sync::{ SyncClient, S_IFREG };
+use crate::AdbError;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FileKind {
+    Dir,
+    File,
+    Link(String),
+    /// sockets, devices and pipes
+    Other,
+}
+
+/// One row of `ls -la` on the device.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FileEntry {
+    pub name: String,
+    pub kind: FileKind,
+    /// none where toolbox `ls` leaves it out, e.g. for directories
+    pub size: Option<u64>,
+    pub mode: String,
+    /// `2024-01-02 03:04` in the device's time zone
+    pub modified: String,
+}
+
+impl FileEntry {
+    pub fn is_dir(&self) -> bool {
+        self.kind == FileKind::Dir
+    }
+}
+
+/// Parses `ls -la` of toybox (Android 6+) and of the older toolbox, which has no
+/// link count and no size for directories. `.` and `..` are skipped.
+pub fn parse_ls(text: &str) -> Vec<FileEntry> {
+    text.lines().filter_map(parse_ls_line).collect()
+}
+
+fn parse_ls_line(line: &str) -> Option<FileEntry> {
+    let mode = line.split_whitespace().next()?;
+    if mode.len() < 10 || !mode.starts_with(['-', 'd', 'l', 'c', 'b', 's', 'p']) {
+        return None;
+    }
+    // the date is the first yyyy-mm-dd field, the time and the name follow it
+    let mut fields = Vec::new();
+    let mut rest = line;
+    loop {
+        let trimmed = rest.trim_start();
+        let end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+        if end == 0 {
+            return None;
+        }
+        fields.push(&trimmed[..end]);
+        rest = &trimmed[end..];
+        if is_date(&trimmed[..end]) {
+            break;
+        }
+    }
+    let rest = rest.trim_start();
+    let time_end = rest.find(char::is_whitespace)?;
+    let time = &rest[..time_end];
+    // a single separator, names may start with spaces
+    let name = rest[time_end..].strip_prefix(' ')?;
+    let date = fields.pop()?;
+    let size = fields
+        .last()
+        .filter(|_| fields.len() >= 4)
+        .and_then(|size| size.parse().ok());
+    let (name, kind) = match mode.as_bytes()[0] {
+        b'd' => (name, FileKind::Dir),
+        b'-' => (name, FileKind::File),
+        b'l' => {
+            let (name, target) = name.split_once(" -> ").unwrap_or((name, ""));
+            (name, FileKind::Link(target.to_string()))
+        }
+        _ => (name, FileKind::Other),
+    };
+    if name == "." || name == ".." {
+        return None;
+    }
+    Some(FileEntry {
+        name: name.to_string(),
+        kind,
+        size,
+        mode: mode.to_string(),
+        modified: format!("{} {}", date, time),
+    })
+}
+
+fn is_date(field: &str) -> bool {
+    let bytes = field.as_bytes();
+    bytes.len() == 10 &&
+        bytes[4] == b'-' &&
+        bytes[7] == b'-' &&
+        bytes
+            .iter()
+            .enumerate()
+            .all(|(i, b)| i == 4 || i == 7 || b.is_ascii_digit())
+}
+
+/// Quotes `text` for the device shell, `it's` becomes `'it'\''s'`.
+pub fn shell_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "'\\''"))
+}
+
+/// `parent/name` on the device.
+pub fn join_remote(parent: &str, name: &str) -> String {
+    if parent.ends_with('/') {
+        format!("{}{}", parent, name)
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+/// The directory above `path`, `/` at the top.
+pub fn parent_remote(path: &str) -> String {
+    let trimmed = path.trim_end_matches('/');
+    match trimmed.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(i) => trimmed[..i].to_string(),
+    }
+}
+
+// `run-as <package>` in front, so app private files are reachable on debuggable apps
+fn shell_command(run_as: Option<&str>, command: &str) -> String {
+    match run_as {
+        Some(package) => format!("run-as {} {}", shell_quote(package), command),
+        None => command.to_string(),
+    }
+}
+
+fn run_shell(
+    adb_path: &str,
+    device: &str,
+    run_as: Option<&str>,
+    command: &str
+) -> Result<Vec<u8>, AdbError> {
+    let command = shell_command(run_as, command);
+    crate::run_adb(adb_path, Some(device), &["shell", &command])
+}
+
+pub fn list_dir(
+    adb_path: &str,
+    device: &str,
+    run_as: Option<&str>,
+    path: &str
+) -> Result<Vec<FileEntry>, AdbError> {
+    // the trailing slash lists the target of a linked directory like /sdcard
+    let dir = join_remote(path, "");
+    let output = run_shell(adb_path, device, run_as, &format!("ls -la {}", shell_quote(&dir)))?;
+    Ok(parse_ls(&String::from_utf8_lossy(&output)))
+}
+
+pub fn remove(
+    adb_path: &str,
+    device: &str,
+    run_as: Option<&str>,
+    path: &str
+) -> Result<(), AdbError> {
+    run_shell(adb_path, device, run_as, &format!("rm -rf {}", shell_quote(path))).map(|_| ())
+}
+
+pub fn rename(
+    adb_path: &str,
+    device: &str,
+    run_as: Option<&str>,
+    from: &str,
+    to: &str
+) -> Result<(), AdbError> {
+    let command = format!("mv {} {}", shell_quote(from), shell_quote(to));
+    run_shell(adb_path, device, run_as, &command).map(|_| ())
+}
+
+pub fn make_dir(
+    adb_path: &str,
+    device: &str,
+    run_as: Option<&str>,
+    path: &str
+) -> Result<(), AdbError> {
+    run_shell(adb_path, device, run_as, &format!("mkdir -p {}", shell_quote(path))).map(|_| ())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Pull,
+    Push,
+}
+
+/// A file copied to or from the device on its own thread.
+///
+/// Bytes go through the `sync:` service like `adb pull` and `adb push`, so
+/// failures on the device come back as errors. App private files of `run-as`
+/// go through `adb shell` and its exit status instead.
+pub struct Transfer {
+    pub direction: Direction,
+    pub remote: String,
+    pub local: PathBuf,
+    // bytes to copy, 0 while unknown
+    total: Arc<AtomicU64>,
+    done: Arc<AtomicU64>,
+    cancel: Arc<Cancel>,
+    handle: Option<JoinHandle<Result<(), AdbError>>>,
+}
+
+// what a copy may be blocked on, closed to cancel it right away
+#[derive(Default)]
+struct Cancel {
+    cancelled: AtomicBool,
+    stream: Mutex<Option<TcpStream>>,
+    child: Mutex<Option<Child>>,
+}
+
+impl Cancel {
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        if let Some(stream) = self.stream.lock().expect("!lock").as_ref() {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+        if let Some(child) = self.child.lock().expect("!lock").as_mut() {
+            let _ = child.kill();
+        }
+    }
+}
+
+impl Transfer {
+    /// `total` is the size when known, it is read from the device otherwise.
+    pub fn pull(
+        adb_path: &str,
+        device: &str,
+        run_as: Option<&str>,
+        remote: &str,
+        local: &Path,
+        total: Option<u64>
+    ) -> Result<Transfer, AdbError> {
+        let server = crate::adb::server_address();
+        Transfer::pull_at(server, adb_path, device, run_as, remote, local, total)
+    }
+
+    /// Same as [`Transfer::pull`] through the adb server at `server`.
+    pub fn pull_at(
+        server: SocketAddr,
+        adb_path: &str,
+        device: &str,
+        run_as: Option<&str>,
+        remote: &str,
+        local: &Path,
+        total: Option<u64>
+    ) -> Result<Transfer, AdbError> {
+        // written next to the target and renamed once complete, a file already
+        // there is kept until then
+        let name = local.file_name().unwrap_or_default().to_string_lossy();
+        let part = local.with_file_name(format!(".{}.part", name));
+        let file = std::fs::File::create(&part)?;
+        let mut transfer = Transfer::new(Direction::Pull, remote, local, total);
+        let (done, cancel) = (transfer.done.clone(), transfer.cancel.clone());
+        let total = transfer.total.clone();
+        let (adb_path, device) = (adb_path.to_string(), device.to_string());
+        let run_as = run_as.map(str::to_string);
+        let (remote, local_path) = (remote.to_string(), local.to_path_buf());
+        let handle = std::thread::spawn(move || {
+            let mut file = Counted::new(file, &done, &cancel);
+            let result = match run_as {
+                Some(package) => {
+                    let command = format!("cat {}", shell_quote(&remote));
+                    let command = shell_command(Some(&package), &command);
+                    shell_copy(&adb_path, &device, &command, None, Some(&mut file), &cancel)
+                }
+                None => sync_pull(server, &device, &remote, &mut file, &total, &cancel),
+            };
+            drop(file);
+            if cancel.is_cancelled() {
+                let _ = std::fs::remove_file(&part);
+                return Ok(());
+            }
+            // a failed pull leaves no half file behind
+            let result = result.and_then(|_| Ok(std::fs::rename(&part, &local_path)?));
+            if result.is_err() {
+                let _ = std::fs::remove_file(&part);
+            }
+            result
+        });
+        transfer.handle = Some(handle);
+        Ok(transfer)
+    }
+
+    pub fn push(
+        adb_path: &str,
+        device: &str,
+        run_as: Option<&str>,
+        local: &Path,
+        remote: &str
+    ) -> Result<Transfer, AdbError> {
+        let server = crate::adb::server_address();
+        Transfer::push_at(server, adb_path, device, run_as, local, remote)
+    }
+
+    /// Same as [`Transfer::push`] through the adb server at `server`.
+    pub fn push_at(
+        server: SocketAddr,
+        adb_path: &str,
+        device: &str,
+        run_as: Option<&str>,
+        local: &Path,
+        remote: &str
+    ) -> Result<Transfer, AdbError> {
+        let file = std::fs::File::open(local)?;
+        let metadata = file.metadata()?;
+        let mut transfer = Transfer::new(Direction::Push, remote, local, Some(metadata.len()));
+        let (done, cancel) = (transfer.done.clone(), transfer.cancel.clone());
+        let (adb_path, device) = (adb_path.to_string(), device.to_string());
+        let run_as = run_as.map(str::to_string);
+        let remote = remote.to_string();
+        let handle = std::thread::spawn(move || {
+            let mut file = Counted::new(file, &done, &cancel);
+            let result = match run_as {
+                Some(package) => {
+                    let command = format!("cat > {}", shell_quote(&remote));
+                    let command = format!("sh -c {}", shell_quote(&command));
+                    let command = shell_command(Some(&package), &command);
+                    shell_copy(&adb_path, &device, &command, Some(&mut file), None, &cancel)
+                }
+                None => sync_push(server, &device, &remote, &mut file, &metadata, &cancel),
+            };
+            if cancel.is_cancelled() { Ok(()) } else { result }
+        });
+        transfer.handle = Some(handle);
+        Ok(transfer)
+    }
+
+    fn new(direction: Direction, remote: &str, local: &Path, total: Option<u64>) -> Transfer {
+        Transfer {
+            direction,
+            remote: remote.to_string(),
+            local: local.to_path_buf(),
+            total: Arc::new(AtomicU64::new(total.unwrap_or(0))),
+            done: Arc::new(AtomicU64::new(0)),
+            cancel: Arc::new(Cancel::default()),
+            handle: None,
+        }
+    }
+
+    /// Bytes copied so far.
+    pub fn done(&self) -> u64 {
+        self.done.load(Ordering::Relaxed)
+    }
+
+    /// Bytes to copy, none while unknown.
+    pub fn total(&self) -> Option<u64> {
+        Some(self.total.load(Ordering::Relaxed)).filter(|total| *total > 0)
+    }
+
+    /// From 0 to 1, none while the size is unknown.
+    pub fn fraction(&self) -> Option<f32> {
+        let total = self.total()?;
+        Some(((self.done() as f64) / (total as f64)).min(1.0) as f32)
+    }
+
+    /// Stops the copy, also when it waits on a stalled device.
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.handle.as_ref().is_none_or(|handle| handle.is_finished())
+    }
+
+    /// Waits for the copy to end. A cancelled transfer is `Ok`.
+    pub fn join(mut self) -> Result<(), AdbError> {
+        match self.handle.take() {
+            Some(handle) => {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(AdbError::Command("transfer panicked".to_string())))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Transfer {
+    // the thread cleans up after itself, nothing to wait for
+    fn drop(&mut self) {
+        if self.handle.take().is_some() {
+            self.cancel.cancel();
+        }
+    }
+}
+
+fn sync_client(
+    server: SocketAddr,
+    device: &str,
+    cancel: &Cancel
+) -> Result<SyncClient<TcpStream>, AdbError> {
+    let client = SyncClient::connect_at(server, device)?;
+    *cancel.stream.lock().expect("!lock") = Some(client.stream().try_clone()?);
+    // a cancel before the stream was there
+    if cancel.is_cancelled() {
+        cancel.cancel();
+    }
+    Ok(client)
+}
+
+fn sync_pull(
+    server: SocketAddr,
+    device: &str,
+    remote: &str,
+    out: &mut impl Write,
+    total: &AtomicU64,
+    cancel: &Cancel
+) -> Result<(), AdbError> {
+    let mut client = sync_client(server, device, cancel)?;
+    let stat = client.stat(remote)?;
+    if stat.mode == 0 {
+        return Err(AdbError::Command(format!("{}: No such file or directory", remote)));
+    }
+    if stat.is_dir() {
+        return Err(AdbError::Command(format!("{}: Is a directory", remote)));
+    }
+    // STAT does not follow a link, its size is the link's
+    if stat.is_file() && total.load(Ordering::Relaxed) == 0 {
+        total.store(stat.size, Ordering::Relaxed);
+    }
+    client.recv(remote, out)?;
+    client.quit()
+}
+
+fn sync_push(
+    server: SocketAddr,
+    device: &str,
+    remote: &str,
+    data: &mut impl Read,
+    metadata: &std::fs::Metadata,
+    cancel: &Cancel
+) -> Result<(), AdbError> {
+    let mut client = sync_client(server, device, cancel)?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|since| since.as_secs() as u32)
+        .unwrap_or(0);
+    client.send(data, remote, S_IFREG | 0o644, mtime)?;
+    client.quit()
+}
+
+// `adb shell` keeps stderr apart and exits with the status of the command
+fn shell_copy(
+    adb_path: &str,
+    device: &str,
+    command: &str,
+    stdin: Option<&mut dyn Read>,
+    stdout: Option<&mut dyn Write>,
+    cancel: &Cancel
+) -> Result<(), AdbError> {
+    let path = adb_path.trim().trim_matches('"');
+    let mut child = crate
+        ::adb_command(adb_path, Some(device))
+        .args(["shell", command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| AdbError::from_spawn(path, err))?;
+    let mut child_stdin = child.stdin.take().expect("!stdin");
+    let mut child_stdout = child.stdout.take().expect("!stdout");
+    let mut child_stderr = child.stderr.take().expect("!stderr");
+    *cancel.child.lock().expect("!lock") = Some(child);
+    if cancel.is_cancelled() {
+        cancel.cancel();
+    }
+    let stderr = std::thread::spawn(move || {
+        let mut message = String::new();
+        let _ = child_stderr.read_to_string(&mut message);
+        message
+    });
+    let copied = match (stdin, stdout) {
+        (Some(data), _) => {
+            // closing stdin ends cat on the device
+            let copied = std::io::copy(data, &mut child_stdin);
+            drop(child_stdin);
+            copied
+        }
+        (None, Some(out)) => {
+            drop(child_stdin);
+            std::io::copy(&mut child_stdout, out)
+        }
+        (None, None) => Ok(0),
+    };
+    let status = wait_child(cancel)?;
+    let message = stderr.join().unwrap_or_default();
+    if !status.success() {
+        return Err(AdbError::from_stderr(Some(device), &message));
+    }
+    copied?;
+    Ok(())
+}
+
+// polls so that a cancel can still kill the child meanwhile
+fn wait_child(cancel: &Cancel) -> Result<std::process::ExitStatus, AdbError> {
+    loop {
+        let mut child = cancel.child.lock().expect("!lock");
+        let Some(running) = child.as_mut() else {
+            return Err(AdbError::Command("adb shell is gone".to_string()));
+        };
+        if let Some(status) = running.try_wait()? {
+            child.take();
+            return Ok(status);
+        }
+        drop(child);
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+}
+
+// counts the bytes going through for the progress and stops at a cancel
+struct Counted<'a, T> {
+    inner: T,
+    done: &'a AtomicU64,
+    cancel: &'a Cancel,
+}
+
+impl<'a, T> Counted<'a, T> {
+    fn new(inner: T, done: &'a AtomicU64, cancel: &'a Cancel) -> Self {
+        Counted { inner, done, cancel }
+    }
+
+    fn check(&self) -> std::io::Result<()> {
+        if self.cancel.is_cancelled() {
+            return Err(std::io::Error::other("cancelled"));
+        }
+        Ok(())
+    }
+}
+
+impl<T: Read> Read for Counted<'_, T> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.check()?;
+        let got = self.inner.read(buf)?;
+        self.done.fetch_add(got as u64, Ordering::Relaxed);
+        Ok(got)
+    }
+}
+
+impl<T: Write> Write for Counted<'_, T> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.check()?;
+        let written = self.inner.write(buf)?;
+        self.done.fetch_add(written as u64, Ordering::Relaxed);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
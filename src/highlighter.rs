@@ -1,36 +1,77 @@
+use adbx::tag_color::TagPalette;
+
 use crate::log::*;
-pub struct MyHighlighter {}
+use crate::log_table::Column;
+use crate::theme::LogTheme;
+
+pub struct MyHighlighter {
+    pub font_size: f32,
+    pub theme: LogTheme,
+    tag_palette: TagPalette,
+}
 
 impl MyHighlighter {
     pub fn new() -> Self {
-        MyHighlighter {}
+        MyHighlighter {
+            font_size: 14.0,
+            theme: LogTheme::dark(),
+            tag_palette: LogTheme::dark().tag_palette(egui::Color32::from_gray(27)),
+        }
     }
-    pub fn highlighter(&mut self, string: &str) -> (egui::text::LayoutJob, RawLog) {
-        let mut layout_job = egui::text::LayoutJob::default();
+
+    /// `background` is what the log rows are painted on, tag colours are picked to contrast with it
+    pub fn set_theme(&mut self, theme: LogTheme, background: egui::Color32) {
+        self.tag_palette = theme.tag_palette(background);
+        self.theme = theme;
+    }
+
+    pub fn font_id(&self) -> egui::FontId {
+        egui::FontId::new(self.font_size, egui::FontFamily::Monospace)
+    }
+
+    /// Splits a threadtime line into one layout job per `Column::ALL` entry,
+    /// or a single job for lines that do not parse, with the jobs to paint bold.
+    pub fn highlighter(
+        &mut self,
+        string: &str
+    ) -> (Vec<egui::text::LayoutJob>, Vec<bool>, RawLog) {
         let mut log = RawLog {
             origin: string.to_string(),
             info: None,
+            retraced: None,
         };
         //println!("string len: {}", string.len());
         let line = string;
 
+        let plain = |log: &RawLog| {
+            let mut formats = [
+                egui::TextFormat {
+                    font_id: self.font_id(),
+                    color: self.theme.plain,
+                    ..Default::default()
+                },
+            ];
+            let mut bold = [false];
+            self.theme.apply_rules(log, &mut formats, &mut bold);
+            let [format] = formats;
+            (egui::text::LayoutJob::single_section(log.origin.clone(), format), bold.to_vec())
+        };
+
         // if first word is not number, then it is not a time stamp
-        if line.len() < 18 || !line.starts_with(|c: char| c.is_digit(10)) {
-            layout_job.append(line, 0.0, egui::TextFormat {
-                font_id: egui::FontId::new(14.0, egui::FontFamily::Monospace),
-                color: egui::Color32::GRAY,
-                ..Default::default()
-            });
-            //layout_job.append("\n", 0.0, egui::TextFormat::default());
-            return (layout_job, log);
+        if
+            line.len() < 18 ||
+            !line.starts_with(|c: char| c.is_ascii_digit()) ||
+            !line.is_char_boundary(6)
+        {
+            let (job, bold) = plain(&log);
+            return (vec![job], bold, log);
         }
 
-        let l_date = &line[0..5];
         // split line by space or double space for 6 parts
         let others = &line[6..];
         let mut split_indexes = Vec::with_capacity(11);
         let mut last_is_space = true;
-        for (i, c) in others.chars().enumerate() {
+        for (i, c) in others.char_indices() {
             if c != ' ' {
                 if last_is_space {
                     split_indexes.push(i);
@@ -47,120 +88,96 @@ impl MyHighlighter {
             }
         }
         if split_indexes.len() < 11 {
-            layout_job.append(line, 0.0, egui::TextFormat {
-                font_id: egui::FontId::new(14.0, egui::FontFamily::Monospace),
-                color: egui::Color32::GRAY,
-                ..Default::default()
-            });
-            //layout_job.append("\n", 0.0, egui::TextFormat::default());
-            return (layout_job, log);
+            let (job, bold) = plain(&log);
+            return (vec![job], bold, log);
         }
 
-        log.info = Some(FormatedItem {
+        // ranges are byte offsets into the whole line
+        let offset = 6;
+        let tag_end = if others[..split_indexes[9]].ends_with(':') {
+            split_indexes[9] - 1
+        } else {
+            split_indexes[9]
+        };
+        let info = FormatedItem {
             date: 0..5,
-            time: split_indexes[0]..split_indexes[1],
-            pid: split_indexes[2]..split_indexes[3],
-            tid: split_indexes[4]..split_indexes[5],
-            level: split_indexes[6]..split_indexes[7],
-            tag: split_indexes[8]..split_indexes[9],
-            message: split_indexes[10]..line.len(),
-        });
-
-        let l_time = &others[split_indexes[0]..split_indexes[1]];
-        let l_pid = &others[split_indexes[2]..split_indexes[3]];
-        // ensure pid length is 5
-        let l_pid = format!("{: <width$}", l_pid, width = 5);
-        let l_pid = l_pid.as_str();
-        let l_tid = &others[split_indexes[4]..split_indexes[5]];
-        // ensure tid length is 5
-        let l_tid = format!("{: <width$}", l_tid, width = 5);
-        let l_tid = l_tid.as_str();
-
-        let l_level = &others[split_indexes[6]..split_indexes[7]];
-        let l_tag = &others[split_indexes[8]..split_indexes[9]].trim_end_matches(":");
-        // limit tag length and extend it with spaces
-        let limit = 20;
-        let l_tag = format!("{: <width$}", l_tag, width = limit);
-        let l_tag = l_tag.as_str();
-
-        let l_message = &others[split_indexes[10]..];
-
-        let data_color = egui::Color32::from_rgb(0x66, 0x99, 0x99);
-        let time_color = egui::Color32::from_rgb(0x33, 0x99, 0x99);
-        let pid_color = egui::Color32::from_rgb(0xcc, 0xcc, 0xcc);
-        let tid_color = egui::Color32::from_rgb(0x99, 0xcc, 0x99);
-
-        let tag_color = get_color_from_string(l_tag);
-        let color = match l_level.chars().nth(0).unwrap() {
-            'V' => egui::Color32::LIGHT_GRAY,
-            'D' => egui::Color32::LIGHT_BLUE,
-            'I' => egui::Color32::WHITE,
-            'W' => egui::Color32::YELLOW,
-            'E' => egui::Color32::LIGHT_RED,
-            _ => egui::Color32::LIGHT_GRAY,
+            time: offset + split_indexes[0]..offset + split_indexes[1],
+            pid: offset + split_indexes[2]..offset + split_indexes[3],
+            tid: offset + split_indexes[4]..offset + split_indexes[5],
+            level: offset + split_indexes[6]..offset + split_indexes[7],
+            tag: offset + split_indexes[8]..offset + tag_end,
+            message: offset + split_indexes[10]..line.len(),
         };
 
-        layout_job.append(l_date, 0.0, egui::TextFormat {
-            font_id: egui::FontId::new(14.0, egui::FontFamily::Monospace),
-            color: data_color,
-            ..Default::default()
-        });
-        layout_job.append("  ", 0.0, egui::TextFormat::default());
-
-        layout_job.append(l_time, 0.0, egui::TextFormat {
-            font_id: egui::FontId::new(14.0, egui::FontFamily::Monospace),
-            color: time_color,
-            ..Default::default()
-        });
-        layout_job.append("  ", 0.0, egui::TextFormat::default());
-
-        layout_job.append(l_pid, 0.0, egui::TextFormat {
-            font_id: egui::FontId::new(14.0, egui::FontFamily::Monospace),
-            color: pid_color,
-            ..Default::default()
-        });
-        layout_job.append("  ", 0.0, egui::TextFormat::default());
+        // the date ends at a fixed offset, which may fall inside a multibyte char
+        let cells: Option<Vec<&str>> = Column::ALL
+            .iter()
+            .map(|column| line.get(column.range(&info)))
+            .collect();
+        let Some(cells) = cells else {
+            let (job, bold) = plain(&log);
+            return (vec![job], bold, log);
+        };
+        let l_level = cells[Column::Level.index()];
+        let l_tag = cells[Column::Tag.index()];
 
-        layout_job.append(l_tid, 0.0, egui::TextFormat {
-            font_id: egui::FontId::new(14.0, egui::FontFamily::Monospace),
-            color: tid_color,
-            ..Default::default()
-        });
-        layout_job.append("  ", 0.0, egui::TextFormat::default());
+        let [r, g, b] = self.tag_palette.color(l_tag);
+        let tag_color = egui::Color32::from_rgb(r, g, b);
+        let color = self.theme.level_color(l_level.chars().next().unwrap());
 
-        layout_job.append(l_tag, 0.0, egui::TextFormat {
-            font_id: egui::FontId::new(14.0, egui::FontFamily::Monospace),
-            italics: true,
-            color: tag_color,
-            ..Default::default()
-        });
-        layout_job.append("  ", 0.0, egui::TextFormat::default());
-
-        layout_job.append(l_message, 0.0, egui::TextFormat {
-            font_id: egui::FontId::new(14.0, egui::FontFamily::Monospace),
+        let format = |color: egui::Color32| egui::TextFormat {
+            font_id: self.font_id(),
             color,
             ..Default::default()
-        });
-
-        //layout_job.append("\n", 0.0, egui::TextFormat::default());
-        (layout_job, log)
+        };
+        let mut formats = [
+            format(self.theme.date),
+            format(self.theme.time),
+            format(self.theme.pid),
+            format(self.theme.tid),
+            format(color),
+            egui::TextFormat {
+                italics: true,
+                ..format(tag_color)
+            },
+            format(color),
+        ];
+        log.info = Some(info);
+        let mut bold = [false; Column::ALL.len()];
+        self.theme.apply_rules(&log, &mut formats, &mut bold);
+
+        let jobs = cells
+            .into_iter()
+            .zip(formats)
+            .map(|(cell, format)| egui::text::LayoutJob::single_section(cell.to_string(), format))
+            .collect();
+        (jobs, bold.to_vec(), log)
     }
 }
 
-fn get_color_from_string(string: &str) -> egui::Color32 {
-    let len = string.len();
-    let point13 = len / 3;
-    let point23 = point13 * 2;
-    let mut chars = [0u8, 0u8, 0u8];
-    // let char1 equels the average of the first third of the string
-    chars[0] = (string[..point13].chars().fold(0, |acc, x| acc + (x as u32)) /
-        (point13 as u32)) as u8;
-    // let char2 equels the average of the second third of the string
-    chars[1] = (string[point13..point23].chars().fold(0, |acc, x| acc + (x as u32)) /
-        (point13 as u32)) as u8;
-    // let char3 equels the average of the third third of the string
-    chars[2] = (string[point23..].chars().fold(0, |acc, x| acc + (x as u32)) /
-        ((len - point23) as u32)) as u8;
-
-    egui::Color32::from_rgb(chars[0] + chars[0], chars[1] + chars[1], chars[2] + chars[2])
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_threadtime_lines() {
+        let line = "01-02 12:00:00.000  1234  5678 I ActivityManager: Start proc";
+        let (jobs, bold, log) = MyHighlighter::new().highlighter(line);
+        assert_eq!(jobs.len(), Column::ALL.len());
+        assert_eq!(bold.len(), Column::ALL.len());
+        assert_eq!(jobs[Column::Date.index()].text, "01-02");
+        assert_eq!(log.tag(), Some("ActivityManager"));
+        assert_eq!(jobs[Column::Message.index()].text, "Start proc");
+    }
+
+    #[test]
+    fn keeps_lines_with_a_multibyte_date_plain() {
+        // 'é' spans bytes 4 and 5, the date would end inside it
+        let line = "0123é 12:00:00.000  1234  5678 I ActivityManager: Start proc";
+        let (jobs, bold, log) = MyHighlighter::new().highlighter(line);
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(bold.len(), 1);
+        assert_eq!(jobs[0].text, line);
+        assert!(log.info.is_none());
+    }
 }
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::thread::JoinHandle;
+
+use adbx::payload::{ self, Node };
+use adbx::AdbError;
+
+use crate::log::ColorLog;
+use crate::log_table::Column;
+
+// adb may be slow to answer, the name is read on a thread
+struct Lookup {
+    pid: u32,
+    handle: JoinHandle<Result<Option<String>, AdbError>>,
+}
+
+/// Details of the focused row: its fields, the process and a tree of its payload.
+pub struct Inspector {
+    pub open: bool,
+    // process names by pid, none when it could not be read
+    processes: HashMap<u32, Option<String>>,
+    // the pid being looked up
+    lookup: Option<Lookup>,
+    // the parsed message of the last row shown
+    tree: Option<(usize, Option<Node>)>,
+}
+
+impl Inspector {
+    pub fn new() -> Self {
+        Inspector {
+            open: false,
+            processes: HashMap::new(),
+            lookup: None,
+            tree: None,
+        }
+    }
+
+    /// Pids are reused, forget the names when the logcat restarts.
+    pub fn clear(&mut self) {
+        self.processes.clear();
+        self.lookup = None;
+        self.tree = None;
+    }
+
+    fn poll_lookup(&mut self) {
+        let finished = self.lookup.as_ref().is_some_and(|lookup| lookup.handle.is_finished());
+        let Some(Lookup { pid, handle }) = finished.then(|| self.lookup.take()).flatten() else {
+            return;
+        };
+        let name = handle
+            .join()
+            .unwrap_or_else(|_| Err(AdbError::Command("ps thread panicked".to_string())));
+        let name = name.unwrap_or_else(|err| {
+            ::log::warn!("process name of {}: {}", pid, err);
+            None
+        });
+        self.processes.insert(pid, name);
+    }
+
+    // looked up once per pid, one at a time
+    fn process_name(
+        &mut self,
+        ctx: &egui::Context,
+        adb_path: &str,
+        device: &str,
+        pid: u32
+    ) -> Option<&str> {
+        if !self.processes.contains_key(&pid) && self.lookup.is_none() {
+            let ctx = ctx.clone();
+            let adb_path = adb_path.to_string();
+            let device = device.to_string();
+            let handle = std::thread::spawn(move || {
+                let name = adbx::get_process_name(&adb_path, &device, pid);
+                ctx.request_repaint();
+                name
+            });
+            self.lookup = Some(Lookup { pid, handle });
+        }
+        self.processes.get(&pid)?.as_deref()
+    }
+
+    /// `device` is none for a replayed session, whose pids are gone.
+    /// Returns a filter to apply, e.g. `tag:ActivityManager`.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        log: Option<&ColorLog>,
+        adb_path: &str,
+        device: Option<&str>
+    ) -> Option<String> {
+        self.poll_lookup();
+        if !self.open {
+            return None;
+        }
+        let mut filter = None;
+        egui::SidePanel
+            ::right("inspector")
+            .resizable(true)
+            .default_width(360.0)
+            .show(ctx, |ui| {
+                ui.heading("Inspector");
+                ui.separator();
+                let Some(log) = log else {
+                    ui.weak("Select a row to inspect it");
+                    return;
+                };
+                egui::ScrollArea
+                    ::vertical()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        filter = self.show_log(ui, log, adb_path, device);
+                    });
+            });
+        filter
+    }
+
+    fn show_log(
+        &mut self,
+        ui: &mut egui::Ui,
+        log: &ColorLog,
+        adb_path: &str,
+        device: Option<&str>
+    ) -> Option<String> {
+        let raw = &log.raw;
+        let mut filter = None;
+        let pid = raw.pid().zip(device);
+        let looking_up = pid.is_some_and(|(pid, _)| {
+            self.lookup.as_ref().is_some_and(|lookup| lookup.pid == pid)
+        });
+        let process = pid
+            .and_then(|(pid, device)| self.process_name(ui.ctx(), adb_path, device, pid))
+            .map(str::to_string);
+        egui::Grid
+            ::new("inspector_fields")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label("line");
+                ui.label(format!("#{}", log.index));
+                ui.end_row();
+                if let Some(info) = raw.info.as_ref() {
+                    for column in Column::ALL {
+                        if column == Column::Message {
+                            continue;
+                        }
+                        ui.label(column.name());
+                        ui.monospace(raw.origin.get(column.range(info)).unwrap_or(""));
+                        ui.end_row();
+                    }
+                }
+                ui.label("process");
+                match process.as_deref() {
+                    Some(process) => ui.monospace(process),
+                    None if looking_up => ui.spinner(),
+                    None => ui.monospace("unknown"),
+                };
+                ui.end_row();
+            });
+        ui.horizontal(|ui| {
+            let tag = raw.tag().map(|tag| format!("tag:{}", tag.trim()));
+            let pid = raw.pid().map(|pid| format!("pid:{}", pid));
+            let tid = raw.tid().map(|tid| format!("tid:{}", tid.trim()));
+            let buttons = [("Filter Tag", tag), ("Filter Pid", pid), ("Filter Thread", tid)];
+            for (text, value) in buttons {
+                if let Some(value) = value {
+                    if ui.button(text).on_hover_text(&value).clicked() {
+                        filter = Some(value);
+                    }
+                }
+            }
+        });
+
+        ui.separator();
+        ui.label("message");
+        let mut message = raw.message();
+        ui.add(egui::TextEdit::multiline(&mut message).code_editor().desired_rows(1));
+        if let Some(retraced) = raw.retraced.as_ref() {
+            ui.label("retraced");
+            let mut retraced = retraced.as_str();
+            ui.add(egui::TextEdit::multiline(&mut retraced).code_editor().desired_rows(1));
+        }
+        ui.label("raw line");
+        let mut origin = raw.origin.as_str();
+        ui.add(egui::TextEdit::multiline(&mut origin).code_editor().desired_rows(1));
+
+        // parse once per row, messages can be long
+        if self.tree.as_ref().map(|(index, _)| *index) != Some(log.index) {
+            self.tree = Some((log.index, payload::parse_message(raw.message())));
+        }
+        if let Some((_, Some(node))) = self.tree.as_ref() {
+            ui.separator();
+            ui.label("payload");
+            let id = egui::Id::new("inspector_payload").with(log.index);
+            show_node(ui, id, "payload", node);
+        }
+        filter
+    }
+}
+
+// collapsed state is kept per path from the root
+fn show_node(ui: &mut egui::Ui, id: egui::Id, name: &str, node: &Node) {
+    match node {
+        Node::Value(value) => {
+            ui.horizontal_wrapped(|ui| {
+                ui.strong(name);
+                ui.monospace(value);
+            });
+        }
+        Node::List(items) => {
+            let header = format!("{} [{}]", name, items.len());
+            egui::CollapsingHeader
+                ::new(header)
+                .id_source(id)
+                .default_open(true)
+                .show(ui, |ui| {
+                    for (i, item) in items.iter().enumerate() {
+                        show_node(ui, id.with(i), &i.to_string(), item);
+                    }
+                });
+        }
+        Node::Map(entries) => {
+            let header = format!("{} {{{}}}", name, entries.len());
+            egui::CollapsingHeader
+                ::new(header)
+                .id_source(id)
+                .default_open(true)
+                .show(ui, |ui| {
+                    for (key, value) in entries {
+                        show_node(ui, id.with(key), key, value);
+                    }
+                });
+        }
+    }
+}
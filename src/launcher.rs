@@ -0,0 +1,324 @@
+use crate::files::shell_quote;
+use crate::AdbError;
+
+/// What `am` does with an intent.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LaunchKind {
+    Activity,
+    Broadcast,
+    Service,
+}
+
+impl LaunchKind {
+    pub const ALL: [LaunchKind; 3] = [
+        LaunchKind::Activity,
+        LaunchKind::Broadcast,
+        LaunchKind::Service,
+    ];
+
+    /// The `am` subcommand, activities wait for the launch to report its timing.
+    pub fn command(&self) -> &'static str {
+        match self {
+            LaunchKind::Activity => "start -W",
+            LaunchKind::Broadcast => "broadcast",
+            LaunchKind::Service => "startservice",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LaunchKind::Activity => "Start Activity",
+            LaunchKind::Broadcast => "Broadcast",
+            LaunchKind::Service => "Start Service",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExtraKind {
+    String,
+    Int,
+    Long,
+    Float,
+    Bool,
+    Uri,
+}
+
+impl ExtraKind {
+    pub const ALL: [ExtraKind; 6] = [
+        ExtraKind::String,
+        ExtraKind::Int,
+        ExtraKind::Long,
+        ExtraKind::Float,
+        ExtraKind::Bool,
+        ExtraKind::Uri,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExtraKind::String => "string",
+            ExtraKind::Int => "int",
+            ExtraKind::Long => "long",
+            ExtraKind::Float => "float",
+            ExtraKind::Bool => "bool",
+            ExtraKind::Uri => "uri",
+        }
+    }
+
+    fn option(&self) -> &'static str {
+        match self {
+            ExtraKind::String => "--es",
+            ExtraKind::Int => "--ei",
+            ExtraKind::Long => "--el",
+            ExtraKind::Float => "--ef",
+            ExtraKind::Bool => "--ez",
+            ExtraKind::Uri => "--eu",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Extra {
+    pub kind: ExtraKind,
+    pub key: String,
+    pub value: String,
+}
+
+/// An intent as `am` takes it, empty fields are left out.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Intent {
+    pub action: String,
+    pub data: String,
+    pub category: String,
+    /// `com.example.app/.MainActivity`
+    pub component: String,
+    pub package: String,
+    pub extras: Vec<Extra>,
+    /// names or numbers, e.g. `ACTIVITY_NEW_TASK | 0x8000`
+    pub flags: String,
+}
+
+// the flags worth typing by name, see android.content.Intent
+const FLAGS: &[(&str, u32)] = &[
+    ("ACTIVITY_NO_HISTORY", 0x4000_0000),
+    ("ACTIVITY_SINGLE_TOP", 0x2000_0000),
+    ("ACTIVITY_NEW_TASK", 0x1000_0000),
+    ("ACTIVITY_MULTIPLE_TASK", 0x0800_0000),
+    ("ACTIVITY_CLEAR_TOP", 0x0400_0000),
+    ("ACTIVITY_EXCLUDE_FROM_RECENTS", 0x0080_0000),
+    ("ACTIVITY_REORDER_TO_FRONT", 0x0002_0000),
+    ("ACTIVITY_NO_ANIMATION", 0x0001_0000),
+    ("ACTIVITY_CLEAR_TASK", 0x0000_8000),
+    ("RECEIVER_FOREGROUND", 0x1000_0000),
+    ("INCLUDE_STOPPED_PACKAGES", 0x0000_0020),
+];
+
+/// Ors flags separated by `|`, `,` or spaces. A flag is a number, decimal or
+/// `0x` hex, or a name with or without the `FLAG_` prefix.
+pub fn parse_flags(text: &str) -> Result<u32, AdbError> {
+    let mut flags = 0;
+    for flag in text.split(['|', ',', ' ']).map(|flag| flag.trim()).filter(|f| !f.is_empty()) {
+        let name = flag.strip_prefix("FLAG_").unwrap_or(flag);
+        let value = match flag.strip_prefix("0x").or_else(|| flag.strip_prefix("0X")) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => flag.parse().ok(),
+        };
+        let value = value.or_else(|| {
+            FLAGS.iter().find(|(known, _)| known.eq_ignore_ascii_case(name)).map(|(_, v)| *v)
+        });
+        match value {
+            Some(value) => flags |= value,
+            None => return Err(AdbError::Command(format!("unknown intent flag {}", flag))),
+        }
+    }
+    Ok(flags)
+}
+
+impl Intent {
+    /// The launcher activity of a package, or one of its activities.
+    pub fn launch(package: &str, activity: Option<&str>) -> Intent {
+        match activity {
+            Some(activity) => Intent {
+                component: activity.to_string(),
+                ..Intent::default()
+            },
+            None => Intent {
+                action: "android.intent.action.MAIN".to_string(),
+                category: "android.intent.category.LAUNCHER".to_string(),
+                package: package.to_string(),
+                ..Intent::default()
+            },
+        }
+    }
+
+    /// The package the intent goes to, if it names one.
+    pub fn target_package(&self) -> Option<&str> {
+        let package = match self.component.split_once('/') {
+            Some((package, _)) => package,
+            None => self.package.as_str(),
+        };
+        let package = package.trim();
+        if package.is_empty() { None } else { Some(package) }
+    }
+
+    /// The intent arguments of `am`, unquoted.
+    pub fn args(&self) -> Result<Vec<String>, AdbError> {
+        let mut args = Vec::new();
+        let options = [
+            ("-a", &self.action),
+            ("-d", &self.data),
+            ("-c", &self.category),
+            ("-n", &self.component),
+            ("-p", &self.package),
+        ];
+        for (option, value) in options {
+            if !value.trim().is_empty() {
+                args.push(option.to_string());
+                args.push(value.trim().to_string());
+            }
+        }
+        for extra in self.extras.iter().filter(|extra| !extra.key.trim().is_empty()) {
+            args.push(extra.kind.option().to_string());
+            args.push(extra.key.trim().to_string());
+            args.push(extra.value.clone());
+        }
+        let flags = parse_flags(&self.flags)?;
+        if flags != 0 {
+            args.push("-f".to_string());
+            args.push(format!("0x{:08x}", flags));
+        }
+        Ok(args)
+    }
+}
+
+/// The `am` command line run by `adb shell`.
+pub fn am_command(kind: LaunchKind, intent: &Intent) -> Result<String, AdbError> {
+    let mut command = format!("am {}", kind.command());
+    for arg in intent.args()? {
+        command.push(' ');
+        // options and plain names read better unquoted
+        let plain = !arg.is_empty() &&
+            arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=,@%+".contains(c));
+        if plain {
+            command.push_str(&arg);
+        } else {
+            command.push_str(&shell_quote(&arg));
+        }
+    }
+    Ok(command)
+}
+
+/// What `am` printed, with the timing of `am start -W`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LaunchResult {
+    /// `ok`, or the result of a broadcast
+    pub status: Option<String>,
+    /// `COLD`, `WARM` or `HOT`, Android 10 and up
+    pub launch_state: Option<String>,
+    pub activity: Option<String>,
+    pub this_time: Option<u64>,
+    pub total_time: Option<u64>,
+    pub wait_time: Option<u64>,
+    /// e.g. the activity was only brought to the front
+    pub warning: Option<String>,
+    pub output: String,
+}
+
+/// Parses the output of `am start -W`, `am broadcast` or `am startservice`,
+/// `am` exits 0 on most failures and prints them instead.
+pub fn parse_am_output(text: &str) -> Result<LaunchResult, AdbError> {
+    let lines: Vec<&str> = text.lines().map(|line| line.trim()).collect();
+    // `Error type 3` comes before the line telling what is wrong
+    let error = lines
+        .iter()
+        .find(|line| line.starts_with("Error:"))
+        .or_else(|| {
+            lines.iter().find(|line| {
+                line.starts_with("Error") ||
+                    line.starts_with("Exception") ||
+                    line.starts_with("java.lang.")
+            })
+        });
+    if let Some(error) = error {
+        return Err(AdbError::Command(error.to_string()));
+    }
+    let mut result = LaunchResult {
+        output: text.trim_end().to_string(),
+        ..LaunchResult::default()
+    };
+    for line in lines {
+        let Some((key, value)) = line.split_once(": ") else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "Status" => {
+                result.status = Some(value.to_string());
+            }
+            "Broadcast completed" => {
+                result.status = Some(value.to_string());
+            }
+            "LaunchState" => {
+                result.launch_state = Some(value.to_string());
+            }
+            "Activity" => {
+                result.activity = Some(value.to_string());
+            }
+            "ThisTime" => {
+                result.this_time = value.parse().ok();
+            }
+            "TotalTime" => {
+                result.total_time = value.parse().ok();
+            }
+            "WaitTime" => {
+                result.wait_time = value.parse().ok();
+            }
+            "Warning" => {
+                result.warning = Some(value.to_string());
+            }
+            _ => {}
+        }
+    }
+    Ok(result)
+}
+
+pub fn launch(
+    adb_path: &str,
+    device: &str,
+    kind: LaunchKind,
+    intent: &Intent
+) -> Result<LaunchResult, AdbError> {
+    let command = am_command(kind, intent)?;
+    let output = crate::run_adb(adb_path, Some(device), &["shell", &command])?;
+    parse_am_output(&String::from_utf8_lossy(&output))
+}
+
+/// Activities of a package with an intent filter, from the `Activity Resolver Table:`
+/// of `dumpsys package`. Others cannot be started from the shell anyway.
+pub fn parse_activities(dumpsys: &str, package: &str) -> Vec<String> {
+    let prefix = format!("{}/", package);
+    let mut activities: Vec<String> = Vec::new();
+    let mut in_table = false;
+    for line in dumpsys.lines() {
+        if !line.starts_with(' ') {
+            in_table = line.starts_with("Activity Resolver Table:");
+            continue;
+        }
+        if !in_table {
+            continue;
+        }
+        // `        1a2b3c com.example.app/.MainActivity filter 4d5e6f`
+        let component = line.split_whitespace().nth(1).filter(|c| c.starts_with(&prefix));
+        if let Some(component) = component {
+            if !activities.iter().any(|a| a == component) {
+                activities.push(component.to_string());
+            }
+        }
+    }
+    activities
+}
+
+pub fn activities(adb_path: &str, device: &str, package: &str) -> Result<Vec<String>, AdbError> {
+    let output = crate::run_adb(adb_path, Some(device), &["shell", "dumpsys", "package", package])?;
+    Ok(parse_activities(&String::from_utf8_lossy(&output), package))
+}
@@ -0,0 +1,480 @@
+use std::thread::JoinHandle;
+
+use serde::{ Deserialize, Serialize };
+
+use adbx::launcher::{ self, Extra, ExtraKind, Intent, LaunchKind, LaunchResult };
+use adbx::packages;
+use adbx::AdbError;
+
+use crate::panel_status::PanelStatus;
+
+/// Kept in the settings, the same app is launched over and over.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LaunchOptions {
+    pub package: String,
+    /// `logcat -c` before launching
+    pub clear_log: bool,
+    /// filter the log to the pids of the launched package
+    pub filter_package: bool,
+}
+
+impl Default for LaunchOptions {
+    fn default() -> Self {
+        LaunchOptions {
+            package: String::new(),
+            clear_log: true,
+            filter_package: true,
+        }
+    }
+}
+
+pub enum LauncherEvent {
+    /// clear the log if asked, then call `Launcher::launch`
+    Launch {
+        clear_log: bool,
+    },
+    /// a pid filter on the launched package
+    Filter(String),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    App,
+    Intent,
+}
+
+type Outcome = (Result<LaunchResult, AdbError>, Option<Result<Vec<u32>, AdbError>>);
+
+struct Running {
+    command: String,
+    package: Option<String>,
+    handle: JoinHandle<Outcome>,
+}
+
+// `pm list packages` for the Pick menu
+struct Listing {
+    command: String,
+    device: String,
+    handle: JoinHandle<Result<Vec<String>, AdbError>>,
+}
+
+/// Starts an app, or any activity, broadcast or service intent, through `am`.
+pub struct Launcher {
+    pub open: bool,
+    mode: Mode,
+    packages: Vec<String>,
+    // device the packages were listed on
+    packages_of: String,
+    listing: Option<Listing>,
+    // package the activities were read for
+    activities_of: String,
+    activities: Vec<String>,
+    // none launches the launcher activity
+    activity: Option<String>,
+    intent: Intent,
+    kind: LaunchKind,
+    pending: Option<(LaunchKind, Intent)>,
+    running: Option<Running>,
+    result: Option<LaunchResult>,
+    status: PanelStatus,
+}
+
+impl Launcher {
+    pub fn new() -> Self {
+        Launcher {
+            open: false,
+            mode: Mode::App,
+            packages: Vec::new(),
+            packages_of: String::new(),
+            listing: None,
+            activities_of: String::new(),
+            activities: Vec::new(),
+            activity: None,
+            intent: Intent::default(),
+            kind: LaunchKind::Activity,
+            pending: None,
+            running: None,
+            result: None,
+            status: PanelStatus::default(),
+        }
+    }
+
+    /// Runs the launch asked for by `LauncherEvent::Launch`.
+    pub fn launch(&mut self, ctx: &egui::Context, adb_path: &str, device: &str, filter: bool) {
+        let Some((kind, intent)) = self.pending.take() else {
+            return;
+        };
+        let command = match launcher::am_command(kind, &intent) {
+            Ok(command) => adbx::command_line(adb_path, Some(device), &["shell", &command]),
+            Err(err) => {
+                self.status.report(format!("am {}", kind.command()), err.to_string());
+                return;
+            }
+        };
+        ::log::info!("> {}", command);
+        self.status.clear();
+        self.result = None;
+        let package = intent.target_package().filter(|_| filter).map(|p| p.to_string());
+        let (adb_path, device) = (adb_path.to_string(), device.to_string());
+        let ctx = ctx.clone();
+        let thread_package = package.clone();
+        let handle = std::thread::spawn(move || {
+            let result = launcher::launch(&adb_path, &device, kind, &intent);
+            // -W returns once the activity is drawn, its process is up by then
+            let pids = thread_package
+                .filter(|_| result.is_ok())
+                .map(|package| adbx::get_pids(&adb_path, &device, &package));
+            ctx.request_repaint();
+            (result, pids)
+        });
+        self.running = Some(Running {
+            command,
+            package,
+            handle,
+        });
+    }
+
+    fn poll(&mut self) -> Option<String> {
+        if !self.running.as_ref()?.handle.is_finished() {
+            return None;
+        }
+        let running = self.running.take()?;
+        let (result, pids) = match running.handle.join() {
+            Ok(outcome) => outcome,
+            Err(_) => {
+                self.status.report(running.command, "launch thread panicked".to_string());
+                return None;
+            }
+        };
+        match result {
+            Ok(result) => {
+                if let Some(time) = result.total_time {
+                    ::log::info!("launched in {} ms", time);
+                }
+                self.result = Some(result);
+            }
+            Err(err) => {
+                self.status.report(running.command, err.to_string());
+                return None;
+            }
+        }
+        let package = running.package?;
+        match pids? {
+            Ok(pids) if pids.is_empty() => {
+                self.status.set(format!("{} is not running, the log is not filtered", package));
+                None
+            }
+            Ok(pids) => {
+                let pids: Vec<String> = pids.iter().map(|pid| pid.to_string()).collect();
+                Some(format!("pid:{}", pids.join(",")))
+            }
+            Err(err) => {
+                self.status.report(format!("pidof {}", package), err.to_string());
+                None
+            }
+        }
+    }
+
+    fn refresh_packages(&mut self, ctx: &egui::Context, adb_path: &str, device: &str) {
+        let args = ["shell", "pm list packages"];
+        let command = adbx::command_line(adb_path, Some(device), &args);
+        let (adb_path, thread_device) = (adb_path.to_string(), device.to_string());
+        let ctx = ctx.clone();
+        let handle = std::thread::spawn(move || {
+            let packages = packages::list_packages(&adb_path, &thread_device);
+            ctx.request_repaint();
+            packages.map(|packages| packages.into_iter().map(|package| package.name).collect())
+        });
+        self.listing = Some(Listing {
+            command,
+            device: device.to_string(),
+            handle,
+        });
+    }
+
+    fn poll_packages(&mut self) {
+        let finished = self.listing.as_ref().is_some_and(|listing| listing.handle.is_finished());
+        let Some(listing) = finished.then(|| self.listing.take()).flatten() else {
+            return;
+        };
+        let packages = listing.handle
+            .join()
+            .unwrap_or_else(|_| Err(AdbError::Command("list thread panicked".to_string())));
+        match packages {
+            Ok(packages) => {
+                self.packages = packages;
+                self.packages_of = listing.device;
+            }
+            Err(err) => self.status.report(listing.command, err.to_string()),
+        }
+    }
+
+    fn load_activities(&mut self, adb_path: &str, device: &str, package: &str) {
+        self.activities_of = package.to_string();
+        self.activity = None;
+        match launcher::activities(adb_path, device, package) {
+            Ok(activities) => {
+                self.activities = activities;
+            }
+            Err(err) => {
+                self.activities.clear();
+                let args = ["shell", "dumpsys", "package", package];
+                self.status.report(
+                    adbx::command_line(adb_path, Some(device), &args),
+                    err.to_string()
+                );
+            }
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        adb_path: &str,
+        device: Option<&str>,
+        options: &mut LaunchOptions
+    ) -> Option<LauncherEvent> {
+        let mut event = self.poll().map(LauncherEvent::Filter);
+        self.poll_packages();
+        if !self.open {
+            return event;
+        }
+        let mut open = self.open;
+        egui::Window
+            ::new("Launcher")
+            .open(&mut open)
+            .default_size(egui::vec2(520.0, 480.0))
+            .resizable(true)
+            .show(ctx, |ui| {
+                let Some(device) = device else {
+                    ui.label("No device selected");
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    ui.selectable_value(&mut self.mode, Mode::App, "App");
+                    ui.selectable_value(&mut self.mode, Mode::Intent, "Intent");
+                });
+                ui.separator();
+                let launch = match self.mode {
+                    Mode::App => self.show_app(ui, adb_path, device, options),
+                    Mode::Intent => self.show_intent(ui),
+                };
+                ui.separator();
+                ui.checkbox(&mut options.clear_log, "Clear the log before launching");
+                ui.checkbox(&mut options.filter_package, "Filter the log to the package");
+                if let Some((kind, intent)) = launch.as_ref() {
+                    match launcher::am_command(*kind, intent) {
+                        Ok(command) => ui.weak(egui::RichText::new(command).monospace()),
+                        Err(err) => ui.colored_label(egui::Color32::LIGHT_RED, err.to_string()),
+                    };
+                }
+                ui.horizontal(|ui| {
+                    let text = match launch.as_ref() {
+                        Some((kind, _)) if self.mode == Mode::Intent => kind.name(),
+                        _ => "Launch",
+                    };
+                    let enabled = self.running.is_none() && launch.is_some();
+                    if ui.add_enabled(enabled, egui::Button::new(text)).clicked() {
+                        self.pending = launch;
+                        event = Some(LauncherEvent::Launch {
+                            clear_log: options.clear_log,
+                        });
+                    }
+                    if self.running.is_some() {
+                        ui.spinner();
+                    }
+                });
+                self.status.show(ui);
+                if let Some(result) = self.result.as_ref() {
+                    show_result(ui, result);
+                }
+            });
+        self.open = open;
+        event
+    }
+
+    // the intent to launch, none until a package is typed
+    fn show_app(
+        &mut self,
+        ui: &mut egui::Ui,
+        adb_path: &str,
+        device: &str,
+        options: &mut LaunchOptions
+    ) -> Option<(LaunchKind, Intent)> {
+        ui.horizontal(|ui| {
+            ui.label("package:");
+            ui.add(
+                egui::TextEdit
+                    ::singleline(&mut options.package)
+                    .hint_text("com.example.app")
+                    .desired_width(240.0)
+            );
+            let pick = ui.menu_button("Pick", |ui| {
+                ui.horizontal(|ui| {
+                    let refresh = egui::Button::new("Refresh");
+                    if ui.add_enabled(self.listing.is_none(), refresh).clicked() {
+                        self.refresh_packages(ui.ctx(), adb_path, device);
+                    }
+                    if self.listing.is_some() {
+                        ui.spinner();
+                    }
+                });
+                let search = options.package.trim().to_lowercase();
+                egui::ScrollArea
+                    ::vertical()
+                    .max_height(320.0)
+                    .show(ui, |ui| {
+                        // the list of another device stays hidden until this one is read
+                        let matching = self.packages
+                            .iter()
+                            .filter(|_| self.packages_of == device)
+                            .filter(|name| name.to_lowercase().contains(&search));
+                        for name in matching {
+                            if ui.button(name).clicked() {
+                                options.package = name.clone();
+                                ui.close_menu();
+                            }
+                        }
+                    });
+            });
+            // listed once per device, Refresh for apps installed since
+            let listed = self.packages_of == device && !self.packages.is_empty();
+            if pick.response.clicked() && !listed && self.listing.is_none() {
+                self.refresh_packages(ui.ctx(), adb_path, device);
+            }
+        });
+        let package = options.package.trim().to_string();
+        if package.is_empty() {
+            return None;
+        }
+        if self.activities_of != package {
+            self.activities_of.clear();
+            self.activities.clear();
+            self.activity = None;
+        }
+        ui.horizontal(|ui| {
+            ui.label("activity:");
+            let selected = self.activity.as_deref().unwrap_or("launcher activity");
+            egui::ComboBox
+                ::from_id_source("launch_activity")
+                .selected_text(selected)
+                .width(300.0)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.activity, None, "launcher activity");
+                    for activity in self.activities.iter() {
+                        ui.selectable_value(&mut self.activity, Some(activity.clone()), activity);
+                    }
+                });
+            if ui.button("Load").on_hover_text("Read the activities of the package").clicked() {
+                self.load_activities(adb_path, device, &package);
+            }
+        });
+        Some((LaunchKind::Activity, Intent::launch(&package, self.activity.as_deref())))
+    }
+
+    fn show_intent(&mut self, ui: &mut egui::Ui) -> Option<(LaunchKind, Intent)> {
+        ui.horizontal(|ui| {
+            for kind in LaunchKind::ALL {
+                ui.radio_value(&mut self.kind, kind, kind.name());
+            }
+        });
+        let intent = &mut self.intent;
+        egui::Grid
+            ::new("launch_intent")
+            .num_columns(2)
+            .show(ui, |ui| {
+                let fields = [
+                    ("action", &mut intent.action, "android.intent.action.VIEW"),
+                    ("data", &mut intent.data, "https://example.com"),
+                    ("category", &mut intent.category, "android.intent.category.DEFAULT"),
+                    ("component", &mut intent.component, "com.example.app/.MainActivity"),
+                    ("package", &mut intent.package, "com.example.app"),
+                    ("flags", &mut intent.flags, "ACTIVITY_CLEAR_TOP | 0x10000000"),
+                ];
+                for (label, value, hint) in fields {
+                    ui.label(label);
+                    ui.add(egui::TextEdit::singleline(value).hint_text(hint).desired_width(320.0));
+                    ui.end_row();
+                }
+            });
+        ui.label("extras");
+        let mut remove = None;
+        egui::Grid
+            ::new("launch_extras")
+            .num_columns(4)
+            .show(ui, |ui| {
+                for (i, extra) in intent.extras.iter_mut().enumerate() {
+                    egui::ComboBox
+                        ::from_id_source(("launch_extra_kind", i))
+                        .selected_text(extra.kind.name())
+                        .width(70.0)
+                        .show_ui(ui, |ui| {
+                            for kind in ExtraKind::ALL {
+                                ui.selectable_value(&mut extra.kind, kind, kind.name());
+                            }
+                        });
+                    ui.add(
+                        egui::TextEdit
+                            ::singleline(&mut extra.key)
+                            .hint_text("key")
+                            .desired_width(140.0)
+                    );
+                    ui.add(
+                        egui::TextEdit
+                            ::singleline(&mut extra.value)
+                            .hint_text("value")
+                            .desired_width(140.0)
+                    );
+                    if ui.button("✖").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        if let Some(i) = remove {
+            intent.extras.remove(i);
+        }
+        if ui.button("Add Extra").clicked() {
+            intent.extras.push(Extra {
+                kind: ExtraKind::String,
+                key: String::new(),
+                value: String::new(),
+            });
+        }
+        Some((self.kind, self.intent.clone()))
+    }
+}
+
+fn show_result(ui: &mut egui::Ui, result: &LaunchResult) {
+    ui.separator();
+    egui::Grid
+        ::new("launch_result")
+        .num_columns(2)
+        .show(ui, |ui| {
+            let rows = [
+                ("status", result.status.clone()),
+                ("launch state", result.launch_state.clone()),
+                ("activity", result.activity.clone()),
+                ("total time", result.total_time.map(|t| format!("{} ms", t))),
+                ("wait time", result.wait_time.map(|t| format!("{} ms", t))),
+                ("this time", result.this_time.map(|t| format!("{} ms", t))),
+            ];
+            for (label, value) in rows {
+                if let Some(value) = value {
+                    ui.label(label);
+                    ui.monospace(value);
+                    ui.end_row();
+                }
+            }
+        });
+    if let Some(warning) = result.warning.as_ref() {
+        ui.colored_label(egui::Color32::YELLOW, warning);
+    }
+    egui::CollapsingHeader
+        ::new("Output")
+        .id_source("launch_output")
+        .show(ui, |ui| {
+            ui.monospace(&result.output);
+        });
+}
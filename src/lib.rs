@@ -1,30 +1,163 @@
+pub mod adb;
+pub mod capture;
+pub mod device_info;
+pub mod error;
+pub mod files;
+pub mod launcher;
+pub mod packages;
+pub mod payload;
+pub mod processes;
+pub mod recorder;
+pub mod retrace;
+pub mod selection;
+pub mod session;
+pub mod sync;
+pub mod tag_color;
+pub mod terminal;
+pub mod tombstone;
+pub mod wireless;
 
-pub fn get_adb_devices(adb_path: &str) -> Vec<String> {
+use std::collections::HashMap;
+
+pub use error::AdbError;
+
+/// `adb [-s device]` with the path cleaned up the way users paste it.
+pub fn adb_command(adb_path: &str, device: Option<&str>) -> std::process::Command {
     let path = adb_path.trim().trim_matches('"');
-    let output = std::process::Command::new(path).arg("devices").output();
-    if output.is_err() {
-        println!("adb devices error: {}, {}", path, output.err().unwrap());
-        return Vec::new();
+    let mut command = std::process::Command::new(path);
+    if let Some(device) = device {
+        command.arg("-s").arg(device);
     }
-    let output = output.unwrap();
-    // convert output to string
-    let output = String::from_utf8_lossy(&output.stdout);
-    // split output by new line
-    let output = output.split('\n').collect::<Vec<&str>>();
-    // add devices to adb_devices
-    let mut adb_devices = Vec::new();
-    for line in output {
-        if line.starts_with("List of") {
-            continue;
-        }
-        if line.len() < 1 {
-            continue;
-        }
-        let split = line.split('\t').collect::<Vec<&str>>();
-        if split.len() < 2 {
-            continue;
+    command
+}
+
+/// The command line as shown to the user, e.g. `adb -s emulator-5554 logcat -c`.
+pub fn command_line(adb_path: &str, device: Option<&str>, args: &[&str]) -> String {
+    let mut line = adb_path.trim().trim_matches('"').to_string();
+    if let Some(device) = device {
+        line.push_str(" -s ");
+        line.push_str(device);
+    }
+    for arg in args {
+        line.push(' ');
+        line.push_str(arg);
+    }
+    line
+}
+
+/// Runs adb to completion and returns its stdout, classifying failures.
+pub fn run_adb(adb_path: &str, device: Option<&str>, args: &[&str]) -> Result<Vec<u8>, AdbError> {
+    let path = adb_path.trim().trim_matches('"');
+    let output = adb_command(adb_path, device)
+        .args(args)
+        .output()
+        .map_err(|err| AdbError::from_spawn(path, err))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AdbError::from_stderr(device, &stderr));
+    }
+    Ok(output.stdout)
+}
+
+/// A line of `adb devices`, e.g. `emulator-5554\tdevice`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AdbDevice {
+    pub serial: String,
+    /// `device`, `offline`, `unauthorized`, `recovery`, `no permissions (...)`, ...
+    pub state: String,
+}
+
+impl AdbDevice {
+    /// Why adb cannot talk to the device in its state, if it cannot.
+    pub fn check(&self) -> Result<(), AdbError> {
+        match self.state.as_str() {
+            "device" | "recovery" => Ok(()),
+            "unauthorized" => Err(AdbError::Unauthorized(self.serial.clone())),
+            "offline" => Err(AdbError::DeviceOffline(self.serial.clone())),
+            state => Err(AdbError::Command(format!("device '{}' is {}", self.serial, state))),
         }
-        adb_devices.push(split[0].to_string());
     }
-    adb_devices
+}
+
+/// Parses the output of `adb devices`.
+pub fn parse_devices(text: &str) -> Vec<AdbDevice> {
+    text.lines()
+        .filter(|line| !line.starts_with("List of") && !line.starts_with('*'))
+        .filter_map(|line| {
+            let (serial, state) = line.split_once('\t')?;
+            Some(AdbDevice {
+                serial: serial.trim().to_string(),
+                state: state.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn get_adb_devices(adb_path: &str) -> Result<Vec<AdbDevice>, AdbError> {
+    let output = run_adb(adb_path, None, &["devices"])?;
+    Ok(parse_devices(&String::from_utf8_lossy(&output)))
+}
+
+pub fn get_pids(adb_path: &str, device: &str, package: &str) -> Result<Vec<u32>, AdbError> {
+    // pidof exits with 1 when no process matches
+    let output = adb_command(adb_path, Some(device))
+        .args(["shell", "pidof", package])
+        .output()
+        .map_err(|err| AdbError::from_spawn(adb_path, err))?;
+    if !output.status.success() && !output.stderr.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AdbError::from_stderr(Some(device), &stderr));
+    }
+    let output = String::from_utf8_lossy(&output.stdout);
+    Ok(
+        output
+            .split_whitespace()
+            .filter_map(|pid| pid.parse().ok())
+            .collect()
+    )
+}
+
+/// Name of a running process, e.g. `com.example.app:remote`, `None` once it is gone.
+pub fn get_process_name(
+    adb_path: &str,
+    device: &str,
+    pid: u32
+) -> Result<Option<String>, AdbError> {
+    // ps exits with 1 when no process matches
+    let output = adb_command(adb_path, Some(device))
+        .args(["shell", "ps", "-p", &pid.to_string(), "-o", "NAME"])
+        .output()
+        .map_err(|err| AdbError::from_spawn(adb_path, err))?;
+    if !output.status.success() && !output.stderr.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(AdbError::from_stderr(Some(device), &stderr));
+    }
+    Ok(parse_process_name(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses `ps -o NAME` output, a `NAME` header and the name of the process.
+pub fn parse_process_name(text: &str) -> Option<String> {
+    text.lines()
+        .skip(1)
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+/// Reads all system properties of the device.
+pub fn get_props(adb_path: &str, device: &str) -> Result<HashMap<String, String>, AdbError> {
+    let output = run_adb(adb_path, Some(device), &["shell", "getprop"])?;
+    Ok(parse_getprop(&String::from_utf8_lossy(&output)))
+}
+
+/// Parses `getprop` output, one `[ro.product.model]: [Pixel 7]` per line.
+pub fn parse_getprop(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once("]: [")?;
+            let key = key.trim().strip_prefix('[')?;
+            let value = value.trim_end().strip_suffix(']')?;
+            Some((key.to_string(), value.to_string()))
+        })
+        .collect()
 }
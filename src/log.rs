@@ -1,16 +1,30 @@
 use std::sync::Arc;
 
+use adbx::session::LineFields;
+
 pub struct RawLog {
     pub origin: String,
 
     pub info: Option<FormatedItem>,
+
+    // origin with obfuscated frames and class names mapped back, if any
+    pub retraced: Option<String>,
 }
 pub struct ColorLog {
     pub raw: RawLog,
 
-    pub gallery: Arc<egui::Galley>,
+    // one galley per column, or a single one spanning the row for unparsed lines
+    pub cells: Vec<Arc<egui::Galley>>,
+    /// cells of bold highlight rules, one per cell
+    pub bold: Vec<bool>,
+
+    // laid out next to the original message
+    pub retraced_gallery: Option<Arc<egui::Galley>>,
 
+    pub height: f32,
     pub bottom: f32,
+    /// position among all received lines, unaffected by the filter
+    pub index: usize,
 }
 
 pub struct FormatedItem {
@@ -22,3 +36,60 @@ pub struct FormatedItem {
     pub tag: std::ops::Range<usize>,
     pub message: std::ops::Range<usize>,
 }
+
+impl RawLog {
+    pub fn pid(&self) -> Option<u32> {
+        self.field(|info| info.pid.clone())?.trim().parse().ok()
+    }
+
+    fn field(&self, range: impl Fn(&FormatedItem) -> std::ops::Range<usize>) -> Option<&str> {
+        let info = self.info.as_ref()?;
+        self.origin.get(range(info))
+    }
+
+    pub fn tag(&self) -> Option<&str> {
+        self.field(|info| info.tag.clone())
+    }
+
+    pub fn tid(&self) -> Option<&str> {
+        self.field(|info| info.tid.clone())
+    }
+
+    /// `tag:`, `pid:` and `tid:` filters match that field exactly, or any of a
+    /// comma separated list like `pid:123,456`. Anything else is a part of the line.
+    pub fn matches(&self, filter: &str) -> bool {
+        let field = match filter.split_once(':') {
+            Some(("tag", tag)) => Some((self.tag(), tag)),
+            Some(("pid", pid)) => Some((self.field(|info| info.pid.clone()), pid)),
+            Some(("tid", tid)) => Some((self.tid(), tid)),
+            _ => None,
+        };
+        match field {
+            Some((value, wanted)) => {
+                value.is_some_and(|value| wanted.split(',').any(|w| w.trim() == value.trim()))
+            }
+            None => self.origin.contains(filter),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self.info.as_ref() {
+            Some(info) => self.origin.get(info.message.clone()).unwrap_or(""),
+            None => self.origin.as_str(),
+        }
+    }
+}
+
+impl From<&FormatedItem> for LineFields {
+    fn from(info: &FormatedItem) -> Self {
+        LineFields {
+            date: info.date.clone(),
+            time: info.time.clone(),
+            pid: info.pid.clone(),
+            tid: info.tid.clone(),
+            level: info.level.clone(),
+            tag: info.tag.clone(),
+            message: info.message.clone(),
+        }
+    }
+}
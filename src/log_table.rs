@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::log::{ ColorLog, FormatedItem };
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Column {
+    Date,
+    Time,
+    Pid,
+    Tid,
+    Level,
+    Tag,
+    Message,
+}
+
+impl Column {
+    /// the order of `ColorLog::cells` for parsed lines
+    pub const ALL: [Column; 7] = [
+        Column::Date,
+        Column::Time,
+        Column::Pid,
+        Column::Tid,
+        Column::Level,
+        Column::Tag,
+        Column::Message,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Column::Date => "date",
+            Column::Time => "time",
+            Column::Pid => "pid",
+            Column::Tid => "tid",
+            Column::Level => "level",
+            Column::Tag => "tag",
+            Column::Message => "message",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Column> {
+        Column::ALL.iter().copied().find(|c| c.name() == name)
+    }
+
+    pub fn index(&self) -> usize {
+        Column::ALL
+            .iter()
+            .position(|c| c == self)
+            .unwrap()
+    }
+
+    // in characters of the monospace log font
+    fn default_chars(&self) -> f32 {
+        match self {
+            Column::Date => 5.0,
+            Column::Time => 12.0,
+            Column::Pid => 5.0,
+            Column::Tid => 5.0,
+            Column::Level => 1.0,
+            Column::Tag => 20.0,
+            Column::Message => 0.0,
+        }
+    }
+
+    pub fn range(&self, info: &FormatedItem) -> std::ops::Range<usize> {
+        match self {
+            Column::Date => info.date.clone(),
+            Column::Time => info.time.clone(),
+            Column::Pid => info.pid.clone(),
+            Column::Tid => info.tid.clone(),
+            Column::Level => info.level.clone(),
+            Column::Tag => info.tag.clone(),
+            Column::Message => info.message.clone(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct ColumnState {
+    pub column: Column,
+    pub visible: bool,
+    /// ignored for the message column, which takes the remaining width
+    pub width: f32,
+}
+
+pub const CELL_SPACING: f32 = 10.0;
+const MIN_WIDTH: f32 = 12.0;
+
+/// Display order, visibility and widths of the log table columns.
+#[derive(Clone, PartialEq)]
+pub struct ColumnLayout {
+    pub columns: Vec<ColumnState>,
+}
+
+pub enum HeaderChange {
+    None,
+    /// the header is being dragged, rows keep their layout until it is released
+    Preview,
+    Commit,
+}
+
+impl ColumnLayout {
+    pub fn new(font_size: f32) -> Self {
+        // a monospace glyph is a little over half as wide as the font is tall
+        let char_width = font_size * 0.6;
+        ColumnLayout {
+            columns: Column::ALL
+                .iter()
+                .map(|&column| ColumnState {
+                    column,
+                    visible: true,
+                    width: (column.default_chars() * char_width).max(MIN_WIDTH),
+                })
+                .collect(),
+        }
+    }
+
+    /// Restores the layout saved in the settings; unknown names are skipped
+    /// and columns missing from `order` are appended.
+    pub fn from_settings(
+        font_size: f32,
+        order: &[String],
+        hidden: &[String],
+        widths: &HashMap<String, f32>
+    ) -> Self {
+        let mut layout = ColumnLayout::new(font_size);
+        let mut columns: Vec<ColumnState> = order
+            .iter()
+            .filter_map(|name| Column::from_name(name))
+            .filter_map(|column| layout.columns.iter().find(|c| c.column == column).cloned())
+            .collect();
+        for state in layout.columns.iter() {
+            if !columns.iter().any(|c| c.column == state.column) {
+                columns.push(state.clone());
+            }
+        }
+        for state in columns.iter_mut() {
+            state.visible = !hidden.iter().any(|h| h == state.column.name());
+            if let Some(width) = widths.get(state.column.name()) {
+                state.width = width.max(MIN_WIDTH);
+            }
+        }
+        layout.columns = columns;
+        layout
+    }
+
+    pub fn order_names(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .map(|c| c.column.name().to_string())
+            .collect()
+    }
+
+    pub fn hidden_names(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .filter(|c| !c.visible)
+            .map(|c| c.column.name().to_string())
+            .collect()
+    }
+
+    pub fn widths(&self) -> HashMap<String, f32> {
+        self.columns
+            .iter()
+            .filter(|c| c.column != Column::Message)
+            .map(|c| (c.column.name().to_string(), c.width))
+            .collect()
+    }
+
+    pub fn width(&self, column: Column) -> f32 {
+        self.columns
+            .iter()
+            .find(|c| c.column == column)
+            .map(|c| c.width)
+            .unwrap_or(MIN_WIDTH)
+    }
+
+    pub fn is_visible(&self, column: Column) -> bool {
+        self.columns.iter().any(|c| c.column == column && c.visible)
+    }
+
+    pub fn message_width(&self, total_width: f32) -> f32 {
+        let fixed: f32 = self.columns
+            .iter()
+            .filter(|c| c.visible && c.column != Column::Message)
+            .map(|c| c.width + CELL_SPACING)
+            .sum();
+        (total_width - fixed).max(MIN_WIDTH * 4.0)
+    }
+
+    /// x ranges of the visible columns, left to right
+    pub fn cell_ranges(&self, left: f32, total_width: f32) -> Vec<(Column, egui::Rangef)> {
+        let mut x = left;
+        let mut ranges = Vec::new();
+        for state in self.columns.iter().filter(|c| c.visible) {
+            let width = if state.column == Column::Message {
+                self.message_width(total_width)
+            } else {
+                state.width
+            };
+            ranges.push((state.column, egui::Rangef::new(x, x + width)));
+            x += width + CELL_SPACING;
+        }
+        ranges
+    }
+
+    /// Column titles with resize handles on their right edge.
+    /// Drag a title to move the column, right click to hide or show columns.
+    pub fn header_ui(&mut self, ui: &mut egui::Ui, font_size: f32) -> HeaderChange {
+        let mut change = HeaderChange::None;
+        let height = ui.text_style_height(&egui::TextStyle::Body) + 4.0;
+        let (rect, response) = ui.allocate_exact_size(
+            egui::vec2(ui.available_width(), height),
+            egui::Sense::click()
+        );
+        let ranges = self.cell_ranges(rect.left(), rect.width());
+
+        let drag_id = ui.id().with("column_drag");
+        let mut dragged: Option<Column> = ui.data(|d| d.get_temp(drag_id));
+        for (column, range) in ranges.iter() {
+            let cell = egui::Rect::from_x_y_ranges(*range, rect.y_range());
+            let title = ui.interact(cell, ui.id().with(column.name()), egui::Sense::drag());
+            if title.drag_started() {
+                dragged = Some(*column);
+            }
+            ui.painter().text(
+                cell.left_center(),
+                egui::Align2::LEFT_CENTER,
+                column.name(),
+                egui::TextStyle::Body.resolve(ui.style()),
+                ui.visuals().strong_text_color()
+            );
+
+            if *column == Column::Message {
+                continue;
+            }
+            let handle = egui::Rect::from_x_y_ranges(
+                range.max..=range.max + CELL_SPACING,
+                rect.y_range()
+            );
+            let handle_id = ui.id().with((column.name(), "resize"));
+            let resize = ui.interact(handle, handle_id, egui::Sense::drag());
+            if resize.hovered() || resize.dragged() {
+                ui.ctx().set_cursor_icon(egui::CursorIcon::ResizeHorizontal);
+            }
+            if resize.dragged() {
+                if let Some(state) = self.columns.iter_mut().find(|c| c.column == *column) {
+                    state.width = (state.width + resize.drag_delta().x).max(MIN_WIDTH);
+                }
+                change = HeaderChange::Preview;
+            }
+            if resize.drag_released() {
+                change = HeaderChange::Commit;
+            }
+            ui.painter().vline(
+                handle.center().x,
+                rect.y_range(),
+                ui.visuals().widgets.noninteractive.bg_stroke
+            );
+        }
+
+        if let Some(column) = dragged {
+            if ui.input(|i| i.pointer.any_released()) {
+                let target = ui
+                    .input(|i| i.pointer.interact_pos())
+                    .and_then(|pos| ranges.iter().find(|(_, r)| r.contains(pos.x)))
+                    .map(|(c, _)| *c);
+                if let Some(target) = target.filter(|t| *t != column) {
+                    self.move_column(column, target);
+                    change = HeaderChange::Commit;
+                }
+                dragged = None;
+            } else {
+                ui.ctx().set_cursor_icon(egui::CursorIcon::Grabbing);
+            }
+        }
+        ui.data_mut(|d| {
+            match dragged {
+                Some(column) => d.insert_temp(drag_id, column),
+                None => d.remove::<Column>(drag_id),
+            }
+        });
+
+        response.context_menu(|ui| {
+            for state in self.columns.iter_mut() {
+                if ui.checkbox(&mut state.visible, state.column.name()).changed() {
+                    change = HeaderChange::Commit;
+                }
+            }
+            ui.separator();
+            if ui.button("Reset").clicked() {
+                *self = ColumnLayout::new(font_size);
+                change = HeaderChange::Commit;
+                ui.close_menu();
+            }
+        });
+        change
+    }
+
+    fn move_column(&mut self, column: Column, target: Column) {
+        let from = self.columns.iter().position(|c| c.column == column);
+        let to = self.columns.iter().position(|c| c.column == target);
+        if let (Some(from), Some(to)) = (from, to) {
+            let state = self.columns.remove(from);
+            self.columns.insert(to, state);
+        }
+    }
+
+    /// Paints one row; lines that did not parse span the whole row.
+    pub fn paint_row(&self, ui: &egui::Ui, rect: egui::Rect, log: &ColorLog) {
+        let painter = ui.painter();
+        // egui fonts have no bold face, bold cells are painted twice a pixel apart
+        let pixel = egui::vec2(1.0 / ui.ctx().pixels_per_point(), 0.0);
+        let paint = |pos: egui::Pos2, i: usize| {
+            let galley = &log.cells[i];
+            painter.galley(pos, galley.clone());
+            if log.bold.get(i).copied().unwrap_or(false) {
+                painter.galley(pos + pixel, galley.clone());
+            }
+        };
+        if log.cells.len() != Column::ALL.len() {
+            if !log.cells.is_empty() {
+                paint(rect.left_top(), 0);
+            }
+            return;
+        }
+        for (column, range) in self.cell_ranges(rect.left(), rect.width()) {
+            let galley: &Arc<egui::Galley> = &log.cells[column.index()];
+            paint(egui::pos2(range.min, rect.top()), column.index());
+            if column == Column::Message {
+                if let Some(retraced) = log.retraced_gallery.as_ref() {
+                    let x = range.min + galley.rect.width().max(range.span() / 2.0);
+                    painter.galley(egui::pos2(x + CELL_SPACING, rect.top()), retraced.clone());
+                }
+            }
+        }
+    }
+
+    /// The full text of an elided cell under `pos`, for a tooltip.
+    pub fn elided_text_at<'a>(
+        &self,
+        rect: egui::Rect,
+        log: &'a ColorLog,
+        pos: egui::Pos2
+    ) -> Option<&'a str> {
+        let info = log.raw.info.as_ref()?;
+        let column = self.column_at(rect, pos)?;
+        if !log.cells.get(column.index())?.elided {
+            return None;
+        }
+        log.raw.origin.get(column.range(info))
+    }
+
+    /// The column of a row painted in `rect` under `pos`.
+    pub fn column_at(&self, rect: egui::Rect, pos: egui::Pos2) -> Option<Column> {
+        self.cell_ranges(rect.left(), rect.width())
+            .into_iter()
+            .find(|(_, range)| range.contains(pos.x))
+            .map(|(column, _)| column)
+    }
+}
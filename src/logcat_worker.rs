@@ -2,19 +2,43 @@ use std::sync::{ Arc, Mutex };
 use std::thread::JoinHandle;
 use std::io::Read;
 
+use adbx::device_info::DeviceInfo;
+use adbx::recorder::{ RecordOptions, Recorder };
+use adbx::retrace::Retracer;
+use adbx::session::{ self, DeviceProps, Session, SessionBookmark, SessionLine, SessionRecording };
+use adbx::AdbError;
+
 use crate::highlighter::MyHighlighter;
 use crate::log::ColorLog;
+use crate::log_table::{ Column, ColumnLayout, CELL_SPACING };
+use crate::theme::LogTheme;
+
+const LAYOUT_LINES_PER_FRAME: usize = 5000;
 
 pub struct LogcatWorker {
     device_name: String,
     logcat_sender: Option<std::process::Child>,
     logcat_receiver: Option<JoinHandle<()>>,
     logcat_buffer: Option<Arc<Mutex<Vec<u8>>>>,
+    // written by the reader thread, so recording ignores the filter
+    recorder: Arc<Mutex<Option<Recorder>>>,
     pending_strings: Vec<String>,
     highlighter: MyHighlighter,
     filter: Option<String>,
+    retracer: Arc<Retracer>,
+    columns: ColumnLayout,
     //logs: Vec<Arc<egui::Galley>>,
     logs: Vec<ColorLog>,
+    /// number of lines shown while paused, the rest keeps arriving behind it
+    frozen_len: Option<usize>,
+    started_at: std::time::SystemTime,
+    ended_at: Option<std::time::SystemTime>,
+    /// set when replaying a saved session instead of a device
+    session_device: Option<DeviceProps>,
+    /// screen recordings made while this logcat ran
+    recordings: Vec<SessionRecording>,
+    /// the snapshot saved with a replayed session
+    device_info: Option<DeviceInfo>,
 }
 
 impl LogcatWorker {
@@ -26,68 +50,129 @@ impl LogcatWorker {
             logcat_sender: None,
             logcat_receiver: None,
             logcat_buffer: None,
+            recorder: Arc::new(Mutex::new(None)),
             pending_strings: Vec::new(),
             highlighter: MyHighlighter::new(),
             filter: None,
+            retracer: Arc::new(Retracer::default()),
+            columns: ColumnLayout::new(14.0),
             logs: Vec::new(),
+            frozen_len: None,
+            started_at: std::time::SystemTime::now(),
+            ended_at: None,
+            session_device: None,
+            recordings: Vec::new(),
+            device_info: None,
         }
     }
 
-    pub fn close(&mut self) {
-        println!("close logcat {}", self.device_name);
-        if self.logcat_sender.is_some() {
-            let ret = self.logcat_sender.take().unwrap().kill();
-            match ret {
-                Ok(_) => {}
-                Err(err) => {
-                    println!("kill logcat {} error: {}", self.device_name, err);
-                }
-            }
-            self.logcat_sender = None;
-        }
-        if self.logcat_receiver.is_some() {
-            let ret = self.logcat_receiver.take().unwrap().join();
-            match ret {
-                Ok(_) => {}
-                Err(_) => {
-                    println!("join logcat {} error", self.device_name);
+    /// A worker showing the lines of a saved session, without a running logcat
+    /// and so without a recorder.
+    pub fn from_session(session: Session) -> Self {
+        let mut worker = LogcatWorker::new(&session.device.serial);
+        let time = |millis| std::time::UNIX_EPOCH + std::time::Duration::from_millis(millis);
+        worker.started_at = time(session.started_at);
+        worker.ended_at = Some(time(session.ended_at));
+        worker.filter = session.filter;
+        worker.pending_strings = session.lines
+            .into_iter()
+            .map(|line| line.raw)
+            .collect();
+        worker.session_device = Some(session.device);
+        worker.recordings = session.recordings;
+        worker.device_info = session.device_info;
+        worker
+    }
+
+    pub fn device_name(&self) -> &str {
+        &self.device_name
+    }
+
+    pub fn session_device(&self) -> Option<&DeviceProps> {
+        self.session_device.as_ref()
+    }
+
+    pub fn session_device_info(&self) -> Option<&DeviceInfo> {
+        self.device_info.as_ref()
+    }
+
+    /// Start and end of the capture in unix milliseconds, the end is now for live logcat.
+    pub fn capture_time(&self) -> (u64, u64) {
+        let ended_at = self.ended_at.unwrap_or_else(std::time::SystemTime::now);
+        (session::unix_millis(self.started_at), session::unix_millis(ended_at))
+    }
+
+    /// Everything received so far, in the order of `ColorLog::index`.
+    pub fn to_session(&self, device: DeviceProps, bookmarks: Vec<SessionBookmark>) -> Session {
+        let laid_out = self.logs.iter().map(|log| SessionLine {
+            raw: log.raw.origin.clone(),
+            fields: log.raw.info.as_ref().map(|info| info.into()),
+        });
+        let pending = self.pending_strings.iter().map(|line| SessionLine {
+            raw: line.clone(),
+            fields: None,
+        });
+        let (started_at, ended_at) = self.capture_time();
+        Session {
+            version: session::SESSION_VERSION,
+            device,
+            started_at,
+            ended_at,
+            filter: self.filter.clone(),
+            lines: laid_out.chain(pending).collect(),
+            bookmarks,
+            recordings: self.recordings.clone(),
+            device_info: self.device_info.clone(),
+        }
+    }
+
+    pub fn close(&mut self) -> Result<(), AdbError> {
+        ::log::info!("close logcat {}", self.device_name);
+        let mut result = Ok(());
+        if let Some(mut sender) = self.logcat_sender.take() {
+            // the process may already be gone when the device was unplugged
+            if let Err(err) = sender.kill() {
+                if err.kind() != std::io::ErrorKind::InvalidInput {
+                    result = Err(AdbError::Io(err));
                 }
             }
-            self.logcat_receiver = None;
+            let _ = sender.wait();
         }
-        if self.logcat_buffer.is_some() {
-            self.logcat_buffer = None;
+        if let Some(receiver) = self.logcat_receiver.take() {
+            if receiver.join().is_err() {
+                ::log::error!("join logcat {} error", self.device_name);
+            }
         }
+        self.logcat_buffer = None;
+        result
     }
 
-    pub fn connect(&mut self, adb_path: &str) {
+    pub fn connect(&mut self, adb_path: &str) -> Result<(), AdbError> {
         let path = adb_path.trim().trim_matches('"');
-        let output = std::process::Command
-            ::new(path)
-            .arg("-s")
-            .arg(&self.device_name)
+        let mut sender = adbx
+            ::adb_command(adb_path, Some(&self.device_name))
             .arg("logcat")
             .stdout(std::process::Stdio::piped())
-            .spawn();
-        if output.is_err() {
-            println!("adb logcat error: {}, {}", path, output.err().unwrap());
-            return;
-        }
-
-        let mut sender = output.unwrap();
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|err| AdbError::from_spawn(path, err))?;
 
         let mut stdout = sender.stdout.take().expect("!stdout");
+        let stderr = sender.stderr.take();
 
         let buffer = Arc::new(Mutex::new(Vec::with_capacity(512)));
         self.logcat_buffer = Some(buffer.clone());
+        self.started_at = std::time::SystemTime::now();
 
+        let device_name = self.device_name.clone();
+        let recorder = self.recorder.clone();
         let receiver = std::thread::spawn(move || {
             let mut line_buf = Vec::with_capacity(512);
             let mut byte = [0u8; 1];
             loop {
                 match stdout.read(&mut byte) {
                     Err(err) => {
-                        println!("{}] Error reading from stream: {}", line!(), err);
+                        ::log::error!("logcat {}: error reading from stream: {}", device_name, err);
                         break;
                     }
                     Ok(got) => {
@@ -96,6 +181,7 @@ impl LogcatWorker {
                         }
                         line_buf.push(byte[0]);
                         if byte[0] == b'\n' {
+                            record(&recorder, &line_buf);
                             let mut vec = buffer.lock().expect("!lock");
                             vec.append(&mut line_buf);
                             line_buf.clear();
@@ -103,91 +189,265 @@ impl LogcatWorker {
                     }
                 }
             }
+            // keep the unterminated tail and close the file, e.g. finish the gzip stream
+            if !line_buf.is_empty() {
+                record(&recorder, &line_buf);
+            }
+            if let Some(recorder) = recorder.lock().expect("!lock").take() {
+                if let Err(err) = recorder.finish() {
+                    ::log::error!("logcat {}: finish recording: {}", device_name, err);
+                }
+            }
+            // logcat only ends on its own when adb failed, e.g. the device went offline
+            let mut message = String::new();
+            if let Some(mut stderr) = stderr {
+                let _ = stderr.read_to_string(&mut message);
+            }
+            if !message.trim().is_empty() {
+                let err = AdbError::from_stderr(Some(&device_name), &message);
+                ::log::error!("logcat {} stopped: {}", device_name, err);
+            }
         });
         self.logcat_sender = Some(sender);
         self.logcat_receiver = Some(receiver);
+        Ok(())
     }
 
-    pub fn clear(&mut self, adb_path: &str) {
-        let path = adb_path.trim().trim_matches('"');
-        let output = std::process::Command
-            ::new(path)
-            .arg("-s")
-            .arg(&self.device_name)
-            .arg("logcat")
-            .arg("-c")
-            .output();
-        if output.is_err() {
-            println!("adb logcat error: {}, {}", path, output.err().unwrap());
-            return;
-        }
+    pub fn clear(&mut self, adb_path: &str) -> Result<(), AdbError> {
+        adbx::run_adb(adb_path, Some(&self.device_name), &["logcat", "-c"])?;
         self.logs.clear();
         self.pending_strings.clear();
+        // their marker rows are gone, the indices start over
+        self.recordings.clear();
+        if self.frozen_len.is_some() {
+            self.frozen_len = Some(0);
+        }
+        Ok(())
+    }
+
+    pub fn start_recording(&mut self, options: RecordOptions) -> std::io::Result<()> {
+        let recorder = Recorder::start(options)?;
+        let previous = self.recorder.lock().expect("!lock").replace(recorder);
+        match previous {
+            Some(previous) => previous.finish(),
+            None => Ok(()),
+        }
+    }
+
+    pub fn stop_recording(&mut self) -> std::io::Result<()> {
+        let recorder = self.recorder.lock().expect("!lock").take();
+        match recorder {
+            Some(recorder) => recorder.finish(),
+            None => Ok(()),
+        }
+    }
+
+    /// Freeze the lines shown, new ones are still received and laid out.
+    pub fn pause(&mut self) {
+        if self.frozen_len.is_none() {
+            self.frozen_len = Some(self.logs.len());
+        }
+    }
+
+    pub fn resume(&mut self) {
+        self.frozen_len = None;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.frozen_len.is_some()
+    }
+
+    /// Lines received since the pause, 0 when not paused.
+    pub fn new_lines(&self) -> usize {
+        match self.frozen_len {
+            Some(len) => (self.logs.len() + self.pending_strings.len()).saturating_sub(len),
+            None => 0,
+        }
+    }
+
+    pub fn recordings(&self) -> &[SessionRecording] {
+        &self.recordings
+    }
+
+    pub fn add_recording(&mut self, recording: SessionRecording) {
+        self.recordings.push(recording);
+    }
+
+    /// The line `offset_millis` into a recording, see `SessionRecording::line_at`.
+    pub fn recording_line(&self, recording: usize, offset_millis: u64) -> Option<usize> {
+        let lines = self.logs
+            .iter()
+            .map(|log| log.raw.origin.as_str())
+            .chain(self.pending_strings.iter().map(|line| line.as_str()));
+        self.recordings.get(recording)?.line_at(lines, offset_millis)
+    }
+
+    /// A line by `ColorLog::index`, shown or not.
+    pub fn log(&self, index: usize) -> Option<&ColorLog> {
+        self.logs.get(index)
     }
 
     pub fn set_fliter(&mut self, filter: Option<String>) {
         self.filter = filter;
     }
 
-    pub fn update(&mut self, ui: &mut egui::Ui) {
-        if self.logcat_buffer.is_none() {
+    pub fn set_retracer(&mut self, retracer: Arc<Retracer>) {
+        self.retracer = retracer;
+    }
+
+    pub fn set_font_size(&mut self, font_size: f32) {
+        if self.highlighter.font_size == font_size {
             return;
         }
-        let mut buffer = self.logcat_buffer.as_ref().unwrap().lock().expect("!lock");
-        if buffer.len() > 0 {
-            let mut vec = Vec::new();
-            std::mem::swap(&mut vec, &mut buffer);
-            let string = unsafe { String::from_utf8_unchecked(vec) };
-            for line in string.lines() {
-                self.pending_strings.push(line.to_string());
+        self.highlighter.font_size = font_size;
+        self.relayout();
+    }
+
+    pub fn set_theme(&mut self, theme: LogTheme, background: egui::Color32) {
+        self.highlighter.set_theme(theme, background);
+        self.relayout();
+    }
+
+    pub fn set_columns(&mut self, columns: &ColumnLayout) {
+        if &self.columns == columns {
+            return;
+        }
+        self.columns = columns.clone();
+        self.relayout();
+    }
+
+    // lay out every received line again on the next update
+    pub fn relayout(&mut self) {
+        let mut strings: Vec<String> = self.logs
+            .drain(..)
+            .map(|log| log.raw.origin)
+            .collect();
+        strings.append(&mut self.pending_strings);
+        self.pending_strings = strings;
+    }
+
+    // move what the reader thread received to the pending lines
+    fn take_received(&mut self) {
+        // a replayed session has no buffer, its lines are pending from the start
+        if let Some(buffer) = self.logcat_buffer.as_ref() {
+            let mut buffer = buffer.lock().expect("!lock");
+            if !buffer.is_empty() {
+                let mut vec = Vec::new();
+                std::mem::swap(&mut vec, &mut buffer);
+                let string = unsafe { String::from_utf8_unchecked(vec) };
+                for line in string.lines() {
+                    self.pending_strings.push(line.to_string());
+                }
             }
         }
-        if self.pending_strings.len() == 0 {
+    }
+
+    /// Adds a line of our own after everything received so far, e.g. a screenshot
+    /// marker, and returns the `ColorLog::index` it will get.
+    pub fn insert_marker(&mut self, line: &str) -> usize {
+        self.take_received();
+        record(&self.recorder, format!("{}\n", line).as_bytes());
+        let index = self.logs.len() + self.pending_strings.len();
+        self.pending_strings.push(line.to_string());
+        index
+    }
+
+    pub fn update(&mut self, ui: &mut egui::Ui) {
+        self.take_received();
+        if self.pending_strings.is_empty() {
             return;
         }
 
         let mut layouter = |ui: &egui::Ui, string: &str, wrap_width: f32| {
-            let (mut layout_job, log) = self.highlighter.highlighter(string);
-            layout_job.wrap.max_width = wrap_width;
+            let (jobs, bold, mut log) = self.highlighter.highlighter(string);
+            if !self.retracer.is_empty() {
+                log.retraced = self.retracer.retrace(log.pid(), log.message());
+            }
+
+            let mut message_width = if log.info.is_some() {
+                self.columns.message_width(wrap_width)
+            } else {
+                wrap_width
+            };
+
+            // show the retraced text side by side with the original
+            let mut retraced_gallery = None;
+            if let Some(retraced) = log.retraced.as_ref() {
+                message_width = (message_width - CELL_SPACING) / 2.0;
+                let mut retraced_job = egui::text::LayoutJob::single_section(
+                    retraced.clone(),
+                    egui::TextFormat {
+                        font_id: self.highlighter.font_id(),
+                        color: self.highlighter.theme.retraced,
+                        ..Default::default()
+                    }
+                );
+                retraced_job.wrap.max_width = message_width;
+                retraced_gallery = Some(ui.fonts(|f| f.layout_job(retraced_job)));
+            }
 
-            let g = ui.fonts(|f| f.layout_job(layout_job));
+            let parsed = log.info.is_some();
+            let mut height: f32 = retraced_gallery
+                .as_ref()
+                .map(|r| r.rect.height())
+                .unwrap_or(0.0);
+            let mut cells = Vec::with_capacity(jobs.len());
+            for (i, mut job) in jobs.into_iter().enumerate() {
+                let column = if parsed { Column::ALL[i] } else { Column::Message };
+                if column == Column::Message {
+                    job.wrap.max_width = message_width;
+                } else {
+                    // fixed width cells stay on one row and end with an ellipsis
+                    job.wrap.max_width = self.columns.width(column);
+                    job.wrap.max_rows = 1;
+                    job.wrap.break_anywhere = true;
+                }
+                let g = ui.fonts(|f| f.layout_job(job));
+                if !parsed || self.columns.is_visible(column) {
+                    height = height.max(g.rect.height());
+                }
+                cells.push(g);
+            }
 
-            let color_log = ColorLog {
+            //println!("g rows{:?}", g.rows.len());
+            ColorLog {
                 raw: log,
-                gallery: g.clone(),
+                cells,
+                bold,
+                retraced_gallery,
+                height,
                 bottom: 0.0,
-            };
-            //println!("g rows{:?}", g.rows.len());
-            return color_log;
+                index: 0,
+            }
         };
 
-        let mut start_index = 0;
-        if self.pending_strings.len() < 30 {
-            start_index = self.pending_strings.len() - self.pending_strings.len() / 10 - 1;
-        }
-
-        for line in self.pending_strings[start_index..].iter() {
+        // lay out in arrival order so a line keeps its index, a few thousand per frame
+        let count = self.pending_strings.len().min(LAYOUT_LINES_PER_FRAME);
+        for line in self.pending_strings[..count].iter() {
             let mut log = layouter(ui, line, ui.available_width());
-            let height = log.gallery.rect.height() + ui.style().spacing.item_spacing.y;
+            let height = log.height + ui.style().spacing.item_spacing.y;
             let last_bottom = self.logs
                 .last()
                 .map(|l| l.bottom)
                 .unwrap_or(0.0);
             log.bottom = last_bottom + height;
+            log.index = self.logs.len();
             self.logs.push(log);
         }
         // remove used pending strings
-        self.pending_strings.drain(start_index..);
+        self.pending_strings.drain(..count);
+        if !self.pending_strings.is_empty() {
+            ui.ctx().request_repaint();
+        }
         //println!("left {}", self.pending_strings.len());
     }
 
     pub fn get_logs(&mut self) -> Vec<&ColorLog> {
         let mut logs_show = Vec::new();
-        for log in self.logs.iter() {
-            if self.filter.is_some() {
-                let filter = self.filter.as_ref().unwrap();
-                let text = log.raw.origin.as_str();
-                if !text.contains(filter) {
+        let shown = self.frozen_len.unwrap_or(self.logs.len()).min(self.logs.len());
+        for log in self.logs[..shown].iter() {
+            if let Some(filter) = self.filter.as_ref() {
+                if !log.raw.matches(filter) {
                     continue;
                 }
             }
@@ -206,8 +466,21 @@ impl LogcatWorker {
     }
 }
 
+fn record(recorder: &Mutex<Option<Recorder>>, line: &[u8]) {
+    let mut recorder = recorder.lock().expect("!lock");
+    if let Some(writer) = recorder.as_mut() {
+        if let Err(err) = writer.write_line(line) {
+            // stop instead of failing on every line, e.g. when the disk is full
+            ::log::error!("recording stopped: {}", err);
+            *recorder = None;
+        }
+    }
+}
+
 impl Drop for LogcatWorker {
     fn drop(&mut self) {
-        self.close();
+        if let Err(err) = self.close() {
+            ::log::error!("close logcat {} error: {}", self.device_name, err);
+        }
     }
 }
@@ -1,15 +1,66 @@
 mod log;
+mod bookmarks;
+mod device_info_panel;
+mod file_explorer;
 mod highlighter;
+mod inspector;
+mod launcher_panel;
 mod logcat_worker;
+mod log_table;
+mod packages_panel;
+mod panel_status;
+mod process_monitor;
+mod retrace_panel;
+mod screen_recorder;
+mod screenshot_viewer;
+mod settings;
+mod shell_panel;
+mod shortcuts;
+mod theme;
+mod theme_editor;
+mod tombstone_viewer;
+mod wireless_panel;
 
+use std::path::{ Path, PathBuf };
+use std::sync::Arc;
+use std::thread::JoinHandle;
 use std::time::SystemTime;
 
 use eframe::egui;
 use eframe::App;
 
+use adbx::adb::AdbStatus;
+use adbx::capture::{ Marker, MarkerKind };
+use adbx::device_info;
+use adbx::recorder::RecordOptions;
+use adbx::retrace::Retracer;
+use adbx::selection::Selection;
+use adbx::session::{ self, DeviceProps, Session };
+use adbx::{ AdbDevice, AdbError };
+use bookmarks::Bookmarks;
+use device_info_panel::DeviceInfoPanel;
+use file_explorer::FileExplorer;
+use inspector::Inspector;
+use launcher_panel::{ Launcher, LauncherEvent };
+use packages_panel::PackagesPanel;
+use process_monitor::ProcessMonitor;
 use logcat_worker::LogcatWorker;
+use log_table::{ Column, ColumnLayout, HeaderChange };
+use retrace_panel::RetracePanel;
+use screen_recorder::ScreenRecorder;
+use screenshot_viewer::ScreenshotViewer;
+use settings::{ Settings, SettingsChange };
+use shell_panel::ShellPanel;
+use shortcuts::{ Action, ShortcutEditor };
+use theme_editor::ThemeEditor;
+use tombstone_viewer::TombstoneViewer;
+use wireless_panel::WirelessPanel;
 
 fn main() {
+    // collect messages for the in-app log panel
+    if let Err(err) = egui_logger::init() {
+        println!("Error: {}", err);
+    }
     let mut native_options = eframe::NativeOptions::default();
     native_options.viewport.inner_size = Some(egui::Vec2::new(1280.0, 720.0));
     native_options.follow_system_theme = false;
@@ -26,9 +77,74 @@ fn main() {
     }
 }
 
+const BOOKMARK_COLOR: egui::Color32 = egui::Color32::from_rgb(0x33, 0x99, 0xff);
+const FIND_ID: &str = "logcat_find";
+
+// the selected lines that pass the filter, with their retraced stacks
+fn copy_text(logs: &[&log::ColorLog], selection: &Selection) -> String {
+    let mut text = String::new();
+    for log in logs.iter().filter(|log| selection.contains(log.index)) {
+        text.push_str(log.raw.origin.as_str());
+        text.push('\n');
+        if let Some(retraced) = log.raw.retraced.as_ref() {
+            text.push_str(retraced);
+            text.push('\n');
+        }
+    }
+    text
+}
+
+// `name (pid)` for a pid cell, the thread name for a tid cell once its threads were listed
+fn process_tooltip(
+    monitor: &ProcessMonitor,
+    device: &str,
+    log: &log::ColorLog,
+    column: Column
+) -> Option<String> {
+    let info = log.raw.info.as_ref()?;
+    let id: u32 = log.raw.origin.get(column.range(info))?.trim().parse().ok()?;
+    let name = match column {
+        Column::Pid => monitor.process_name(device, id),
+        // the main thread has the id of its process
+        Column::Tid => monitor.thread_name(device, id).or_else(|| monitor.process_name(device, id)),
+        _ => None,
+    }?;
+    Some(format!("{} ({})", name, id))
+}
+
+fn column_layout(settings: &Settings) -> ColumnLayout {
+    ColumnLayout::from_settings(
+        settings.font_size,
+        &settings.column_order,
+        &settings.hidden_columns,
+        &settings.column_widths
+    )
+}
+
+struct StatusMessage {
+    command: Option<String>,
+    message: String,
+    is_error: bool,
+}
+
+// a session written on its own thread, the device snapshot takes a while
+struct SavingSession {
+    path: String,
+    handle: JoinHandle<std::io::Result<usize>>,
+}
+
+// Check or Detect on its own thread, adb may take its whole timeout to answer
+struct AdbCheck {
+    // Detect may find another binary, which replaces the path
+    detect: bool,
+    handle: JoinHandle<Result<AdbStatus, AdbError>>,
+}
+
 struct MyEguiApp {
     adb_path: String,
-    adb_devices: Vec<String>,
+    adb_status: Result<AdbStatus, AdbError>,
+    adb_check: Option<AdbCheck>,
+    adb_devices: Vec<AdbDevice>,
     selected_device: usize,
     time_point: SystemTime,
     frame_count: usize,
@@ -37,83 +153,636 @@ struct MyEguiApp {
 
     adb_logcat_worker: Option<LogcatWorker>,
     filter_buffer: String,
-    selected_indexes: Vec<usize>,
-    clear_flag: bool,
-    lock_flag: bool,
+    columns: ColumnLayout,
+    /// by `ColorLog::index`
+    selection: Selection,
+    /// rows in view, for page up and down
+    page_rows: usize,
+    /// `ColorLog::index` to bring into view on the next frame
+    scroll_to: Option<usize>,
+    /// follow new lines, off while reading older ones
+    autoscroll: bool,
+    /// scroll offset of the last frame, to tell when the user scrolled
+    scroll_offset: f32,
+    scroll_to_bottom: bool,
+    find_buffer: String,
+    recording: bool,
+
+    tombstone_viewer: TombstoneViewer,
+    retrace_panel: RetracePanel,
+    shell_panel: ShellPanel,
+    file_explorer: FileExplorer,
+    packages_panel: PackagesPanel,
+    launcher: Launcher,
+    process_monitor: ProcessMonitor,
+    device_info_panel: DeviceInfoPanel,
+    wireless_panel: WirelessPanel,
+    screenshot_viewer: ScreenshotViewer,
+    screen_recorder: ScreenRecorder,
+    retracer: Arc<Retracer>,
+
+    settings: Settings,
+    settings_open: bool,
+    theme_editor: ThemeEditor,
+    shortcut_editor: ShortcutEditor,
+    bookmarks: Bookmarks,
+    inspector: Inspector,
+    /// `ColorLog::index` to scroll to on the next frame
+    jump_to: Option<usize>,
+
+    status: Option<StatusMessage>,
+    log_open: bool,
+    saving: Option<SavingSession>,
 
+    #[allow(dead_code)]
     demo: egui_demo_lib::DemoWindows,
 }
 
 impl MyEguiApp {
-    fn new(_cc: &eframe::CreationContext<'_>) -> Self {
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
         // Customize egui here with cc.egui_ctx.set_fonts and cc.egui_ctx.set_visuals.
-        // Restore app state using cc.storage (requires the "persistence" feature).
         // Use the cc.gl (a glow::Context) to create graphics shaders and buffers that you can use
         // for e.g. egui::PaintCallback.
 
-        // set adb path
-        let buildin_adb_path = "platform-tools/adb.exe";
-        // convert adb path to absolute path
-        // let adb_path = match std::fs::canonicalize(buildin_adb_path) {
-        //     Ok(path) => path.to_string_lossy().to_string(),
-        //     Err(_) => buildin_adb_path.to_string(),
-        // };
-        let adb_path = buildin_adb_path.to_string();
+        // restore app state saved by the last session
+        let settings = Settings::load(cc.storage);
+        settings.apply_theme(&cc.egui_ctx);
+
+        // fall back to an adb from the SDK or PATH if the saved one does not work
+        let adb_status = adbx::adb::find_working_adb(&settings.adb_path);
+        let adb_path = match adb_status.as_ref() {
+            Ok(status) => status.path.clone(),
+            Err(_) => settings.adb_path.clone(),
+        };
         // get adb devices
-        let adb_devices = adbx::get_adb_devices(adb_path.as_str());
+        let mut status = None;
+        let adb_devices = match adbx::get_adb_devices(adb_path.as_str()) {
+            Ok(devices) => devices,
+            Err(err) => {
+                ::log::error!("{} devices: {}", adb_path, err);
+                status = Some(StatusMessage {
+                    command: Some(adbx::command_line(&adb_path, None, &["devices"])),
+                    message: err.to_string(),
+                    is_error: true,
+                });
+                Vec::new()
+            }
+        };
+        let selected_device = settings.last_device
+            .as_ref()
+            .and_then(|last| adb_devices.iter().position(|d| &d.serial == last))
+            .unwrap_or(0);
+
+        let mut tombstone_viewer = TombstoneViewer::new();
+        tombstone_viewer.open = settings.is_tab_open("tombstones");
+        let mut retrace_panel = RetracePanel::new();
+        retrace_panel.open = settings.is_tab_open("retrace");
+        let mut shell_panel = ShellPanel::new();
+        shell_panel.open = settings.is_tab_open("shell");
+        let mut file_explorer = FileExplorer::new();
+        file_explorer.open = settings.is_tab_open("files");
+        let mut packages_panel = PackagesPanel::new();
+        packages_panel.open = settings.is_tab_open("packages");
+        let mut launcher = Launcher::new();
+        launcher.open = settings.is_tab_open("launcher");
+        let mut process_monitor = ProcessMonitor::new();
+        process_monitor.open = settings.is_tab_open("processes");
+        let mut device_info_panel = DeviceInfoPanel::new();
+        device_info_panel.open = settings.is_tab_open("device_info");
+        let mut wireless_panel = WirelessPanel::new();
+        wireless_panel.open = settings.is_tab_open("wireless");
+        let mut screenshot_viewer = ScreenshotViewer::new();
+        screenshot_viewer.open = settings.is_tab_open("screenshot");
+        let mut screen_recorder = ScreenRecorder::new();
+        screen_recorder.open = settings.is_tab_open("screenrecord");
+        let mut bookmarks = Bookmarks::new();
+        bookmarks.open = settings.is_tab_open("bookmarks");
+        let mut inspector = Inspector::new();
+        inspector.open = settings.is_tab_open("inspector");
+        let mut theme_editor = ThemeEditor::new();
+        theme_editor.open = settings.is_tab_open("themes");
+        let mut shortcut_editor = ShortcutEditor::new();
+        shortcut_editor.open = settings.is_tab_open("shortcuts");
 
         MyEguiApp {
             adb_path,
+            adb_status,
+            adb_check: None,
             adb_devices,
-            selected_device: 0,
+            selected_device,
             time_point: SystemTime::now(),
             frame_count: 0,
             last_fps: 0,
             frame_limit: 60,
             adb_logcat_worker: None,
-            filter_buffer: String::new(),
-            selected_indexes: Vec::new(),
-            clear_flag: false,
-            lock_flag: false,
+            filter_buffer: settings.filter.clone(),
+            columns: column_layout(&settings),
+            selection: Selection::new(),
+            page_rows: 1,
+            scroll_to: None,
+            autoscroll: true,
+            scroll_offset: 0.0,
+            scroll_to_bottom: false,
+            find_buffer: String::new(),
+            recording: false,
+
+            tombstone_viewer,
+            retrace_panel,
+            shell_panel,
+            file_explorer,
+            packages_panel,
+            launcher,
+            process_monitor,
+            device_info_panel,
+            wireless_panel,
+            screenshot_viewer,
+            screen_recorder,
+            retracer: Arc::new(Retracer::default()),
+
+            settings_open: settings.is_tab_open("settings"),
+            theme_editor,
+            shortcut_editor,
+            bookmarks,
+            inspector,
+            jump_to: None,
+            settings,
+
+            status,
+            log_open: false,
+            saving: None,
 
             demo: egui_demo_lib::DemoWindows::default(),
         }
     }
 
-    pub fn check_adb_devices(&mut self) -> bool {
-        let last_device = self.adb_devices
-            .get(self.selected_device)
-            .unwrap_or(&String::new())
-            .clone();
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        // the editor is waiting for the keys of a new binding
+        if self.shortcut_editor.is_capturing() {
+            return;
+        }
+        for (action, extend) in self.settings.shortcuts.pressed(ctx) {
+            match action {
+                Action::Find => ctx.memory_mut(|m| m.request_focus(egui::Id::new(FIND_ID))),
+                Action::Clear => self.clear_logcat(),
+                Action::ToggleAutoscroll => self.toggle_autoscroll(),
+                _ => self.handle_row_action(ctx, action, extend),
+            }
+        }
+    }
 
-        self.adb_devices = adbx::get_adb_devices(&self.adb_path);
+    // actions on the rows that pass the filter
+    fn handle_row_action(&mut self, ctx: &egui::Context, action: Action, extend: bool) {
+        let Some(worker) = self.adb_logcat_worker.as_mut() else {
+            return;
+        };
+        let logs = worker.get_logs();
+        let Some(last) = logs.len().checked_sub(1) else {
+            return;
+        };
+        // a cursor hidden by the filter continues from the next row shown
+        let cursor = self.selection
+            .cursor()
+            .map(|id| logs.partition_point(|l| l.index < id).min(last));
+        let page = self.page_rows.max(1);
+        let mut not_found = false;
+        let target = match action {
+            Action::Up => Some(cursor.map_or(last, |row| row.saturating_sub(1))),
+            Action::Down => Some(cursor.map_or(0, |row| (row + 1).min(last))),
+            Action::PageUp => Some(cursor.map_or(last, |row| row.saturating_sub(page))),
+            Action::PageDown => Some(cursor.map_or(0, |row| (row + page).min(last))),
+            Action::Home => Some(0),
+            Action::End => Some(last),
+            Action::FindNext | Action::FindPrevious => {
+                let needle = self.find_buffer.to_lowercase();
+                if needle.is_empty() {
+                    return;
+                }
+                let matches = |row: &usize| {
+                    logs[*row].raw.origin.to_lowercase().contains(&needle)
+                };
+                // search from the cursor and wrap around
+                let found = if action == Action::FindNext {
+                    let start = cursor.map_or(0, |row| row + 1);
+                    (start..=last).chain(0..start.min(last + 1)).find(matches)
+                } else {
+                    let end = cursor.unwrap_or(last + 1);
+                    (0..end).rev().chain((end..=last).rev()).find(matches)
+                };
+                not_found = found.is_none();
+                found
+            }
+            Action::Copy => {
+                if !self.selection.is_empty() {
+                    ctx.copy_text(copy_text(&logs, &self.selection));
+                }
+                None
+            }
+            Action::SelectAll => {
+                self.selection.select_all(logs.iter().map(|l| l.index));
+                None
+            }
+            Action::ToggleBookmark => {
+                if let Some(log) = cursor.map(|row| logs[row]) {
+                    self.bookmarks.toggle(log.index, &log.raw.origin);
+                }
+                None
+            }
+            Action::NextBookmark | Action::PreviousBookmark if !self.bookmarks.is_empty() => {
+                let current = self.selection.cursor();
+                self.jump_to = if action == Action::NextBookmark {
+                    self.bookmarks.next(current)
+                } else {
+                    self.bookmarks.previous(current)
+                };
+                None
+            }
+            _ => None,
+        };
+        if let Some(id) = target.map(|row| logs[row].index) {
+            if extend {
+                self.selection.extend_to(id);
+            } else {
+                self.selection.select(id);
+            }
+            self.scroll_to = Some(id);
+        }
+        if not_found {
+            self.report_info(&format!("{} not found", self.find_buffer));
+        }
+    }
 
-        let new_device = self.adb_devices.get(self.selected_device);
+    fn toggle_autoscroll(&mut self) {
+        self.autoscroll = !self.autoscroll;
+        // following starts from the newest line
+        self.scroll_to_bottom |= self.autoscroll;
+    }
 
-        if last_device.is_empty() && new_device.is_none() {
-            println!("device not found");
-            return false;
+    fn toggle_pause(&mut self) {
+        let Some(worker) = self.adb_logcat_worker.as_mut() else {
+            return;
+        };
+        if worker.is_paused() {
+            worker.resume();
+            self.scroll_to_bottom |= self.autoscroll;
+        } else {
+            worker.pause();
+        }
+    }
+
+    // logcat -c, only for a running logcat
+    fn clear_logcat(&mut self) {
+        let live = self.adb_logcat_worker
+            .as_ref()
+            .is_some_and(|w| w.session_device().is_none());
+        if !live || !self.check_adb_devices() {
+            return;
+        }
+        let command = adbx::command_line(
+            &self.adb_path,
+            Some(&self.adb_devices[self.selected_device].serial),
+            &["logcat", "-c"]
+        );
+        ::log::info!("> {}", command);
+        let Some(worker) = self.adb_logcat_worker.as_mut() else {
+            return;
+        };
+        match worker.clear(&self.adb_path) {
+            Ok(_) => {
+                self.bookmarks.clear();
+                self.selection.clear();
+                self.forget_marker_lines();
+            }
+            Err(err) => self.report_error(command, err),
+        }
+    }
+
+    fn prepare_worker(&self, worker: &mut LogcatWorker, ctx: &egui::Context) {
+        worker.set_retracer(self.retracer.clone());
+        worker.set_font_size(self.settings.font_size);
+        worker.set_columns(&self.columns);
+        let background = ctx.style().visuals.panel_fill;
+        worker.set_theme(self.settings.current_log_theme(), background);
+    }
+
+    // tee the running logcat to rotating files, it starts with the next logcat otherwise
+    fn start_recording(&mut self) {
+        let Some(worker) = self.adb_logcat_worker.as_mut() else {
+            return;
+        };
+        if worker.session_device().is_some() {
+            return;
+        }
+        let started = session::file_stamp(session::unix_millis(SystemTime::now()));
+        let device: String = worker
+            .device_name()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        let options = RecordOptions {
+            dir: PathBuf::from(self.settings.record_dir.trim().trim_matches('"')),
+            prefix: format!("{}-{}", device, started),
+            max_file_bytes: (self.settings.record_max_file_mb as u64) * 1024 * 1024,
+            max_files: self.settings.record_max_files,
+            gzip: self.settings.record_gzip,
+        };
+        let dir = options.dir.display().to_string();
+        match worker.start_recording(options) {
+            Ok(_) => self.report_info(&format!("recording to {}", dir)),
+            Err(err) => {
+                self.recording = false;
+                self.report_error(format!("record to {}", dir), AdbError::Io(err));
+            }
+        }
+    }
+
+    fn stop_recording(&mut self) {
+        if let Some(worker) = self.adb_logcat_worker.as_mut() {
+            if let Err(err) = worker.stop_recording() {
+                self.report_error("stop recording".to_string(), AdbError::Io(err));
+            }
+        }
+    }
+
+    fn session_path(&self) -> String {
+        let path = self.settings.session_path.trim().trim_matches('"');
+        if Path::new(path).extension().is_some() {
+            path.to_string()
+        } else {
+            format!("{}.{}", path, session::SESSION_EXTENSION)
+        }
+    }
+
+    fn save_session(&mut self, ctx: &egui::Context) {
+        if self.saving.is_some() {
+            self.report_info("the last session is still being saved");
+            return;
+        }
+        let path = self.session_path();
+        let Some(worker) = self.adb_logcat_worker.as_ref() else {
+            self.report_info("no logs to save");
+            return;
+        };
+        let live = worker.session_device().is_none();
+        let device = worker.session_device().cloned().unwrap_or_else(|| DeviceProps {
+            serial: worker.device_name().to_string(),
+            ..Default::default()
+        });
+        let mut session = worker.to_session(device, self.bookmarks.to_session());
+        // a live session takes a snapshot of the device along
+        let (adb_path, thread_path) = (self.adb_path.clone(), path.clone());
+        let ctx = ctx.clone();
+        let handle = std::thread::spawn(move || {
+            if live {
+                let serial = session.device.serial.clone();
+                match device_info::collect(&adb_path, &serial) {
+                    Ok(info) => {
+                        session.device = DeviceProps::from_getprop(&serial, &info.props);
+                        session.device_info = Some(info);
+                    }
+                    Err(err) => ::log::warn!("device info {}: {}", serial, err),
+                }
+            }
+            let saved = session.save(&thread_path).map(|_| session.lines.len());
+            ctx.request_repaint();
+            saved
+        });
+        self.saving = Some(SavingSession { path, handle });
+    }
+
+    fn start_adb_check(
+        &mut self,
+        ctx: &egui::Context,
+        detect: bool,
+        check: impl FnOnce() -> Result<AdbStatus, AdbError> + Send + 'static
+    ) {
+        let ctx = ctx.clone();
+        let handle = std::thread::spawn(move || {
+            let status = check();
+            ctx.request_repaint();
+            status
+        });
+        self.adb_check = Some(AdbCheck { detect, handle });
+    }
+
+    fn poll_adb_check(&mut self) {
+        let finished = self.adb_check.as_ref().is_some_and(|check| check.handle.is_finished());
+        let Some(check) = finished.then(|| self.adb_check.take()).flatten() else {
+            return;
+        };
+        self.adb_status = check.handle
+            .join()
+            .unwrap_or_else(|_| Err(AdbError::Command("adb check thread panicked".to_string())));
+        if let (true, Ok(status)) = (check.detect, self.adb_status.as_ref()) {
+            self.adb_path = status.path.clone();
+        }
+    }
+
+    fn poll_saving(&mut self) {
+        let finished = self.saving.as_ref().is_some_and(|saving| saving.handle.is_finished());
+        let Some(saving) = finished.then(|| self.saving.take()).flatten() else {
+            return;
+        };
+        let path = saving.path;
+        let saved = saving.handle
+            .join()
+            .unwrap_or_else(|_| Err(std::io::Error::other("save thread panicked")));
+        match saved {
+            Ok(lines) => {
+                let message = format!("saved {} lines to {}", lines, path);
+                self.report_info(&message);
+            }
+            Err(err) => self.report_error(format!("save {}", path), AdbError::Io(err)),
+        }
+    }
+
+    fn open_session(&mut self, ctx: &egui::Context) {
+        let path = self.session_path();
+        match Session::load(&path) {
+            Ok(session) => {
+                ::log::info!("open session {}: {} lines", path, session.lines.len());
+                self.bookmarks.load_session(&session.bookmarks, &session.lines);
+                self.filter_buffer = session.filter.clone().unwrap_or_default();
+                self.selection.clear();
+                // the live logcat being recorded is replaced
+                if self.recording {
+                    self.stop_recording();
+                    self.recording = false;
+                }
+                let mut worker = LogcatWorker::from_session(session);
+                self.prepare_worker(&mut worker, ctx);
+                self.adb_logcat_worker = Some(worker);
+                self.forget_marker_lines();
+            }
+            Err(err) => self.report_error(format!("open {}", path), AdbError::Io(err)),
+        }
+    }
+
+    fn apply_log_theme(&mut self, ctx: &egui::Context) {
+        if let Some(worker) = self.adb_logcat_worker.as_mut() {
+            let background = ctx.style().visuals.panel_fill;
+            worker.set_theme(self.settings.current_log_theme(), background);
+        }
+    }
+
+    fn apply_filter(&mut self) {
+        let fliter = self.current_filter();
+        if let Some(worker) = self.adb_logcat_worker.as_mut() {
+            worker.set_fliter(fliter);
+        }
+        self.settings.push_recent_filter(&self.filter_buffer);
+        ::log::info!("set fliter: {}", self.filter_buffer);
+    }
+
+    // option string if filter is empty
+    fn current_filter(&self) -> Option<String> {
+        if self.filter_buffer.is_empty() {
+            None
+        } else {
+            Some(self.filter_buffer.clone())
         }
-        if &last_device != new_device.unwrap() {
-            println!("device changed");
-            self.selected_device = 0;
+    }
+
+    fn report_error(&mut self, command: String, err: AdbError) {
+        ::log::error!("{}: {}", command, err);
+        self.status = Some(StatusMessage {
+            command: Some(command),
+            message: err.to_string(),
+            is_error: true,
+        });
+    }
+
+    fn report_info(&mut self, message: &str) {
+        ::log::info!("{}", message);
+        self.status = Some(StatusMessage {
+            command: None,
+            message: message.to_string(),
+            is_error: false,
+        });
+    }
+
+    fn selected_serial(&self) -> Option<String> {
+        self.adb_devices.get(self.selected_device).map(|d| d.serial.clone())
+    }
+
+    // a replayed session does not need the device, it stays
+    fn stop_live_logcat(&mut self) {
+        let live = self.adb_logcat_worker
+            .as_ref()
+            .is_some_and(|w| w.session_device().is_none());
+        if live {
             self.adb_logcat_worker = None;
+            self.forget_marker_lines();
+        }
+    }
+
+    // rows linked from the screenshot and the recordings went with the log
+    fn forget_marker_lines(&mut self) {
+        self.screenshot_viewer.forget_line();
+        self.screen_recorder.forget_lines();
+    }
+
+    pub fn check_adb_devices(&mut self) -> bool {
+        let last_device = self.selected_serial();
+
+        match adbx::get_adb_devices(&self.adb_path) {
+            Ok(devices) => {
+                self.adb_devices = devices;
+            }
+            Err(err) => {
+                self.adb_devices.clear();
+                self.stop_live_logcat();
+                self.report_error(adbx::command_line(&self.adb_path, None, &["devices"]), err);
+                return false;
+            }
+        }
+
+        // devices come and go around the selected one, it is kept by serial
+        let position = last_device
+            .as_ref()
+            .map(|serial| self.adb_devices.iter().position(|d| &d.serial == serial));
+        match position {
+            Some(Some(i)) => {
+                self.selected_device = i;
+            }
+            Some(None) => {
+                self.report_info("device not found");
+                self.selected_device = 0;
+                self.stop_live_logcat();
+                return false;
+            }
+            None => {
+                self.selected_device = 0;
+            }
+        }
+
+        let Some(device) = self.adb_devices.get(self.selected_device) else {
+            self.report_info("device not found");
+            self.stop_live_logcat();
             return false;
+        };
+        // listed, but unauthorized or offline
+        match device.check() {
+            Ok(()) => true,
+            Err(err) => {
+                let command = adbx::command_line(&self.adb_path, None, &["devices"]);
+                self.report_error(command, err);
+                false
+            }
         }
-        true
     }
 
     pub fn show_logcat(&mut self, ui: &mut egui::Ui, scoll_to_bottom: bool) {
+        let scoll_to_bottom = scoll_to_bottom || std::mem::take(&mut self.scroll_to_bottom);
+        let mut theme_changed = false;
         if let Some(worker) = self.adb_logcat_worker.as_mut() {
+            if let Some(device) = worker.session_device() {
+                let (started_at, ended_at) = worker.capture_time();
+                let model = device.model.as_deref().unwrap_or("unknown model");
+                let sdk = device.sdk.map(|sdk| format!(", SDK {}", sdk)).unwrap_or_default();
+                ui.label(
+                    format!(
+                        "Session {} ({}{}), {} to {}",
+                        device.serial,
+                        model,
+                        sdk,
+                        session::format_utc(started_at),
+                        session::format_utc(ended_at)
+                    )
+                ).on_hover_text(device.fingerprint.as_deref().unwrap_or(""));
+            }
+            match self.columns.header_ui(ui, self.settings.font_size) {
+                HeaderChange::Commit => {
+                    self.settings.column_order = self.columns.order_names();
+                    self.settings.hidden_columns = self.columns.hidden_names();
+                    self.settings.column_widths = self.columns.widths();
+                    worker.set_columns(&self.columns);
+                }
+                HeaderChange::Preview | HeaderChange::None => {}
+            }
+            // pids of a replayed session belong to processes long gone
+            let live_device = worker
+                .session_device()
+                .is_none()
+                .then(|| worker.device_name().to_string());
             worker.update(ui);
             let logs = worker.get_logs();
-            if logs.len() <= 0 {
+            if logs.is_empty() {
                 return;
             }
-            egui::ScrollArea
+            let jump_to = self.jump_to.take();
+            let jump_row = jump_to.and_then(|index| logs.iter().position(|l| l.index == index));
+            if let Some(index) = jump_row.and(jump_to) {
+                self.selection.select(index);
+            } else if let Some(index) = jump_to {
+                ::log::info!("bookmark #{} is hidden by the filter", index);
+            }
+            let scroll_row = self.scroll_to
+                .take()
+                .and_then(|index| logs.binary_search_by_key(&index, |l| l.index).ok());
+            let output = egui::ScrollArea
                 ::vertical()
                 .auto_shrink([false, false])
-                .stick_to_bottom(true)
+                .stick_to_bottom(self.autoscroll)
                 .show_viewport(ui, |ui, viewport| {
                     let top = viewport.top();
                     let bottom = viewport.bottom();
@@ -146,6 +815,7 @@ impl MyEguiApp {
                         }
                     }
                     to = to.min(logs.len() - 1);
+                    self.page_rows = to - from;
 
                     let mut actual_top = 0.0;
                     if from != 0 {
@@ -183,96 +853,170 @@ impl MyEguiApp {
 
                         // get hovered rect
                         let pos = s_click.hover_pos();
-                        if s_click.clicked_by(egui::PointerButton::Primary) {
-                            self.selected_indexes.clear();
-                        }
-                        if self.clear_flag && s_click.drag_delta().y.abs() > 1.0 {
-                            self.selected_indexes.clear();
-                            self.clear_flag = false;
-                        }
-                        if s_click.drag_released() {
-                            self.clear_flag = true;
-                        }
-
-                        let mut seleced_rect: Option<egui::Rect> = None;
+                        let (primary_pressed, secondary_pressed, modifiers) = ui.input(|i| {
+                            let pointer = &i.pointer;
+                            (pointer.primary_pressed(), pointer.secondary_pressed(), i.modifiers)
+                        });
 
                         //ui.skip_ahead_auto_ids(from);
-                        for (ri, log) in logs[from..=to].iter().enumerate() {
-                            let wt = egui::WidgetText::from(log.gallery.clone());
-                            let label = egui::Label::new(wt);
-                            let res = ui.add(label);
-                            let index = from + ri;
-
+                        for log in logs[from..=to].iter() {
+                            let (row_rect, res) = ui.allocate_exact_size(
+                                egui::vec2(ui.available_width(), log.height),
+                                egui::Sense::hover()
+                            );
+                            self.columns.paint_row(ui, row_rect, log);
+                            // the full text of a truncated cell, e.g. a long tag
+                            let elided = pos
+                                .filter(|p| row_rect.contains(*p))
+                                .and_then(|p| self.columns.elided_text_at(row_rect, log, p));
+                            let bookmark = self.bookmarks.get(log.index);
+                            if bookmark.is_some() {
+                                let marker = egui::Rect::from_x_y_ranges(
+                                    rect.left()..=rect.left() + 3.0,
+                                    row_rect.y_range()
+                                );
+                                ui.painter().rect_filled(marker, 1.0, BOOKMARK_COLOR);
+                            }
+                            let note = bookmark
+                                .map(|b| b.note.as_str())
+                                .filter(|note| !note.is_empty())
+                                .filter(|_| pos.is_some_and(|p| row_rect.contains(p)));
+                            let process = pos
+                                .filter(|p| row_rect.contains(*p))
+                                .and_then(|p| self.columns.column_at(row_rect, p))
+                                .zip(live_device.as_deref())
+                                .and_then(|(column, device)| {
+                                    process_tooltip(&self.process_monitor, device, log, column)
+                                });
+                            let text = elided.map(str::to_string).or(process);
+                            if let Some(text) = text.or(note.map(str::to_string)) {
+                                egui::show_tooltip_at_pointer(
+                                    ui.ctx(),
+                                    egui::Id::new("logcat_cell_tooltip"),
+                                    |ui| {
+                                        ui.label(text);
+                                    }
+                                );
+                            }
                             let log_rect = egui::Rect::from_x_y_ranges(
                                 rect.x_range(),
                                 res.rect.y_range()
                             );
 
-                            if pos.is_some() {
-                                if log_rect.contains(pos.unwrap()) {
-                                    if s_click.drag_delta().y.abs() > 1.0 {
-                                        if let Some(&last) = self.selected_indexes.last() {
-                                            if last != index {
-                                                let should_push =
-                                                    self.selected_indexes.len() <= 1 ||
-                                                    (self.selected_indexes[0] <
-                                                        self.selected_indexes[1]) ==
-                                                        (last < index);
-                                                if should_push {
-                                                    self.selected_indexes.push(index);
-                                                } else {
-                                                    self.selected_indexes.pop();
-                                                }
-                                            }
-                                        } else {
-                                            self.selected_indexes.push(index);
-                                        }
+                            if pos.is_some_and(|p| log_rect.contains(p)) {
+                                ui.painter().rect_filled(
+                                    log_rect,
+                                    3.0,
+                                    egui::Color32::from_rgba_unmultiplied(80, 80, 80, 30)
+                                );
+                                if primary_pressed {
+                                    if modifiers.command {
+                                        self.selection.toggle(log.index);
+                                    } else if modifiers.shift {
+                                        self.selection.extend_to(log.index);
                                     } else {
-                                        ui.painter().rect_filled(
-                                            log_rect,
-                                            3.0,
-                                            egui::Color32::from_rgba_unmultiplied(80, 80, 80, 30)
-                                        );
-                                        if !self.lock_flag && self.selected_indexes.len() <= 1 {
-                                            self.selected_indexes.clear();
-                                            self.selected_indexes.push(index);
-                                        }
+                                        self.selection.select(log.index);
                                     }
+                                } else if s_click.dragged_by(egui::PointerButton::Primary) {
+                                    if self.selection.cursor() != Some(log.index) {
+                                        self.selection.extend_to(log.index);
+                                    }
+                                } else if secondary_pressed && !self.selection.contains(log.index) {
+                                    // the menu acts on the row under the pointer
+                                    self.selection.select(log.index);
                                 }
                             }
 
-                            if self.selected_indexes.contains(&index) {
-                                if seleced_rect.is_none() {
-                                    seleced_rect = Some(log_rect);
-                                } else {
-                                    seleced_rect = Some(seleced_rect.unwrap().union(log_rect));
-                                }
+                            if self.selection.contains(log.index) {
+                                ui.painter().rect_filled(
+                                    log_rect,
+                                    0.0,
+                                    egui::Color32::from_rgba_unmultiplied(80, 80, 80, 30)
+                                );
                             }
                         }
 
-                        if seleced_rect.is_some() {
-                            ui.painter().rect_filled(
-                                seleced_rect.unwrap(),
-                                3.0,
-                                egui::Color32::from_rgba_unmultiplied(80, 80, 80, 30)
-                            );
-                        }
-                        if self.selected_indexes.len() > 0 {
-                            self.lock_flag = false;
+                        if !self.selection.is_empty() {
                             s_click.context_menu(|ui| {
-                                self.lock_flag = true;
                                 // add a button to copy
                                 if ui.button("Copy").clicked() {
-                                    let mut text = String::new();
-                                    for index in self.selected_indexes.iter() {
-                                        let log = logs.get(*index).unwrap();
-                                        text.push_str(log.raw.origin.as_str());
-                                        text.push_str("\n");
-                                    }
-                                    ui.ctx().copy_text(text);
+                                    ui.ctx().copy_text(copy_text(&logs, &self.selection));
                                     // close the menu
                                     ui.close_menu();
                                 }
+                                let first = logs
+                                    .iter()
+                                    .find(|log| self.selection.contains(log.index));
+                                if let Some(log) = first {
+                                    if ui.button("Inspect").clicked() {
+                                        self.selection.select(log.index);
+                                        self.inspector.open = true;
+                                        ui.close_menu();
+                                    }
+                                    let marker = Marker::parse(&log.raw.origin)
+                                        .filter(|m| m.kind == MarkerKind::Screenshot);
+                                    if let Some(marker) = marker {
+                                        if ui.button("Show Screenshot").clicked() {
+                                            self.screenshot_viewer.open_file(
+                                                ui.ctx(),
+                                                &marker.path,
+                                                Some(log.index)
+                                            );
+                                            ui.close_menu();
+                                        }
+                                    }
+                                    let text = if self.bookmarks.get(log.index).is_some() {
+                                        "Remove Bookmark"
+                                    } else {
+                                        "Bookmark"
+                                    };
+                                    if ui.button(text).clicked() {
+                                        self.bookmarks.toggle(log.index, &log.raw.origin);
+                                        ui.close_menu();
+                                    }
+                                }
+                                let tag = first.and_then(|log| {
+                                    let info = log.raw.info.as_ref()?;
+                                    log.raw.origin.get(info.tag.clone())
+                                });
+                                if let Some(tag) = tag {
+                                    if ui.button(format!("Pin Colour of {}", tag)).clicked() {
+                                        let background = ui.visuals().panel_fill;
+                                        let theme = self.settings.log_themes
+                                            .iter_mut()
+                                            .find(|t| t.name == self.settings.log_theme);
+                                        if let Some(theme) = theme {
+                                            // start from the hashed colour, tune it in Themes
+                                            let palette = theme.tag_palette(background);
+                                            let [r, g, b] = palette.color(tag);
+                                            let color = egui::Color32::from_rgb(r, g, b);
+                                            theme.pinned_tags.insert(tag.to_string(), color);
+                                            self.theme_editor.open = true;
+                                            theme_changed = true;
+                                        }
+                                        ui.close_menu();
+                                    }
+                                }
+                                // a native crash announces where debuggerd wrote its tombstone
+                                let tombstone_line = logs
+                                    .iter()
+                                    .filter(|log| self.selection.contains(log.index))
+                                    .map(|log| log.raw.origin.as_str())
+                                    .find(|line| line.contains(adbx::tombstone::TOMBSTONE_DIR));
+                                if let Some(line) = tombstone_line {
+                                    if ui.button("Open Tombstone").clicked() {
+                                        let selected = self.adb_devices.get(self.selected_device);
+                                        if let Some(device) = selected.map(|d| &d.serial) {
+                                            self.tombstone_viewer.open_from_log(
+                                                ui.ctx(),
+                                                &self.adb_path,
+                                                device,
+                                                line
+                                            );
+                                        }
+                                        ui.close_menu();
+                                    }
+                                }
                                 // if ui.button("Copy Free").clicked(){
                                 //     egui::Window::new("My Window").show(ui.ctx(), |ui| {
                                 //         ui.label("Hello World!");
@@ -282,6 +1026,21 @@ impl MyEguiApp {
                         }
                     });
 
+                    // bookmarks are centred, the keyboard cursor only scrolls into view
+                    let scroll = match (jump_row, scroll_row) {
+                        (Some(row), _) => Some((row, Some(egui::Align::Center))),
+                        (None, Some(row)) => Some((row, None)),
+                        (None, None) => None,
+                    };
+                    if let Some((row, align)) = scroll {
+                        let top = if row == 0 { 0.0 } else { logs[row - 1].bottom };
+                        let target = egui::Rect::from_x_y_ranges(
+                            ui.max_rect().x_range(),
+                            ui.max_rect().top() + top..=ui.max_rect().top() + logs[row].bottom
+                        );
+                        ui.scroll_to_rect(target, align);
+                    }
+
                     if scoll_to_bottom {
                         let bottom_rect = egui::Rect::from_x_y_ranges(
                             ui.max_rect().x_range(),
@@ -290,10 +1049,61 @@ impl MyEguiApp {
                         ui.scroll_to_rect(bottom_rect, Some(egui::Align::BOTTOM));
                     }
                 });
+
+            // scrolling up stops following new lines, coming back to the bottom resumes it
+            let offset = output.state.offset.y;
+            let max_offset = (output.content_size.y - output.inner_rect.height()).max(0.0);
+            let at_bottom = offset >= max_offset - 1.0;
+            if offset < self.scroll_offset - 0.5 && !at_bottom {
+                self.autoscroll = false;
+            } else if offset > self.scroll_offset + 0.5 && at_bottom {
+                self.autoscroll = true;
+            }
+            self.scroll_offset = offset;
+        }
+        if theme_changed {
+            self.apply_log_theme(ui.ctx());
         }
     }
 }
 
+impl MyEguiApp {
+    fn show_status_bar(&mut self, ctx: &egui::Context) {
+        egui::TopBottomPanel::bottom("status_bar").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if ui.selectable_label(self.log_open, "Log").clicked() {
+                    self.log_open = !self.log_open;
+                }
+                ui.separator();
+                let mut dismiss = false;
+                if let Some(status) = self.status.as_ref() {
+                    let color = if status.is_error {
+                        egui::Color32::LIGHT_RED
+                    } else {
+                        ui.visuals().text_color()
+                    };
+                    if let Some(command) = status.command.as_ref() {
+                        ui.monospace(format!("> {}", command));
+                    }
+                    ui.colored_label(color, &status.message);
+                    dismiss = ui.small_button("✖").clicked();
+                }
+                if dismiss {
+                    self.status = None;
+                }
+            });
+        });
+
+        egui::Window
+            ::new("Log")
+            .open(&mut self.log_open)
+            .default_size(egui::vec2(600.0, 300.0))
+            .show(ctx, |ui| {
+                egui_logger::logger_ui(ui);
+            });
+    }
+}
+
 impl App for MyEguiApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         //self.demo.ui(ctx);
@@ -310,8 +1120,35 @@ impl App for MyEguiApp {
             self.frame_count = 0;
         }
 
+        self.poll_saving();
+        self.poll_adb_check();
+        self.show_status_bar(ctx);
+        self.handle_shortcuts(ctx);
+        let hint = format!(
+            "{} mark, {} / {} next / previous",
+            self.settings.shortcuts.describe(ctx, Action::ToggleBookmark),
+            self.settings.shortcuts.describe(ctx, Action::NextBookmark),
+            self.settings.shortcuts.describe(ctx, Action::PreviousBookmark)
+        );
+        if let Some(index) = self.bookmarks.show(ctx, &hint) {
+            self.jump_to = Some(index);
+        }
+        let filter = {
+            let worker = self.adb_logcat_worker.as_ref();
+            let log = worker.zip(self.selection.cursor()).and_then(|(w, index)| w.log(index));
+            // pids of a replayed session belong to processes long gone
+            let device = worker
+                .filter(|w| w.session_device().is_none())
+                .map(|w| w.device_name());
+            self.inspector.show(ctx, log, &self.adb_path, device)
+        };
+        if let Some(filter) = filter {
+            self.filter_buffer = filter;
+            self.apply_filter();
+        }
+
         egui::CentralPanel::default().show(ctx, |ui| {
-            if self.frame_count % 120 == 0 {
+            if self.frame_count.is_multiple_of(120) {
                 //self.check_adb_devices();
             }
             egui::Grid
@@ -326,6 +1163,28 @@ impl App for MyEguiApp {
                     ui.label(format!("Frame: {}", self.last_fps));
                     ui.label("adb path:");
                     ui.text_edit_singleline(&mut self.adb_path);
+                    let idle = self.adb_check.is_none();
+                    if ui.add_enabled(idle, egui::Button::new("Check")).clicked() {
+                        let adb_path = self.adb_path.clone();
+                        let check = move || adbx::adb::check_adb(&adb_path);
+                        self.start_adb_check(ui.ctx(), false, check);
+                    }
+                    if ui.add_enabled(idle, egui::Button::new("Detect")).clicked() {
+                        let adb_path = self.adb_path.clone();
+                        let detect = move || adbx::adb::find_working_adb(&adb_path);
+                        self.start_adb_check(ui.ctx(), true, detect);
+                    }
+                    if !idle {
+                        ui.spinner();
+                    }
+                    match self.adb_status.as_ref() {
+                        Ok(status) => {
+                            ui.colored_label(egui::Color32::LIGHT_GREEN, status.describe());
+                        }
+                        Err(err) => {
+                            ui.colored_label(egui::Color32::LIGHT_RED, err.to_string());
+                        }
+                    }
                 });
 
             egui::Grid
@@ -335,11 +1194,14 @@ impl App for MyEguiApp {
                 .show(ui, |ui| {
                     // show a button
                     if ui.button("Refresh Devices").clicked() {
-                        println!("> {} devices", &self.adb_path);
+                        ::log::info!("> {} devices", &self.adb_path);
                         self.check_adb_devices();
                     }
+                    if ui.button("Wireless").clicked() {
+                        self.wireless_panel.open = true;
+                    }
 
-                    if self.adb_devices.len() <= 0 {
+                    if self.adb_devices.is_empty() {
                         ui.label("No device found");
                         return;
                     }
@@ -347,11 +1209,21 @@ impl App for MyEguiApp {
                     // draw a combo box to select device
                     let devices = self.adb_devices.clone();
                     for (i, device) in devices.iter().enumerate() {
-                        if ui.selectable_label(self.selected_device == i, device).clicked() {
+                        let label = match device.state.as_str() {
+                            "device" => device.serial.clone(),
+                            state => format!("{} ({})", device.serial, state),
+                        };
+                        if ui.selectable_label(self.selected_device == i, label).clicked() {
                             self.check_adb_devices();
-                            if i != self.selected_device {
+                            // the list was read again, the device clicked may have moved
+                            let clicked = self.adb_devices
+                                .iter()
+                                .position(|d| d.serial == device.serial)
+                                .filter(|i| *i != self.selected_device);
+                            if let Some(i) = clicked {
                                 self.selected_device = i;
                                 self.adb_logcat_worker = None;
+                                self.forget_marker_lines();
                             }
                         }
                     }
@@ -371,47 +1243,163 @@ impl App for MyEguiApp {
                         if !self.check_adb_devices() {
                             return;
                         }
-                        // if out is none, call adb logcat
-                        if self.adb_logcat_worker.is_none() {
-                            // print command
-                            println!(
-                                "> {} -s {} logcat",
+                        // if out is none, call adb logcat, a replayed session is replaced
+                        let replaying = self.adb_logcat_worker
+                            .as_ref()
+                            .is_some_and(|w| w.session_device().is_some());
+                        if self.adb_logcat_worker.is_none() || replaying {
+                            let device = self.adb_devices[self.selected_device].serial.clone();
+                            let command = adbx::command_line(
                                 &self.adb_path,
-                                &self.adb_devices[self.selected_device]
+                                Some(&device),
+                                &["logcat"]
                             );
+                            ::log::info!("> {}", command);
                             // run adb logcat
-                            self.adb_logcat_worker = Some(
-                                LogcatWorker::new(&self.adb_devices[self.selected_device])
-                            );
-                            self.adb_logcat_worker.as_mut().unwrap().connect(&self.adb_path);
+                            let mut worker = LogcatWorker::new(&device);
+                            self.prepare_worker(&mut worker, ui.ctx());
+                            worker.set_fliter(self.current_filter());
+                            match worker.connect(&self.adb_path) {
+                                Ok(_) => {
+                                    self.adb_logcat_worker = Some(worker);
+                                    self.bookmarks.clear();
+                                    self.selection.clear();
+                                    self.inspector.clear();
+                                    self.forget_marker_lines();
+                                    if self.recording {
+                                        self.start_recording();
+                                    }
+                                }
+                                Err(err) => self.report_error(command, err),
+                            }
+                        }
+                    }
+                    // only a live logcat is recorded, not a replayed session
+                    let replaying = self.adb_logcat_worker
+                        .as_ref()
+                        .is_some_and(|w| w.session_device().is_some());
+                    if !replaying && ui.selectable_label(self.recording, "Record").clicked() {
+                        self.recording = !self.recording;
+                        if self.recording {
+                            self.start_recording();
+                        } else {
+                            self.stop_recording();
                         }
                     }
                     // call logcat -c
                     if ui.button("Clear Logcat").clicked() {
-                        if !self.check_adb_devices() || self.adb_logcat_worker.is_none() {
-                            return;
-                        }
-                        println!(
-                            "> {} -s {} logcat -c",
-                            &self.adb_path,
-                            &self.adb_devices[self.selected_device]
-                        );
-                        self.adb_logcat_worker.as_mut().unwrap().clear(&self.adb_path);
+                        self.clear_logcat();
                     }
                     //show a text edit to fliter logcat
                     ui.text_edit_singleline(&mut self.filter_buffer);
-                    if ui.button("Fliter").clicked() {
-                        // option string if filter is empty
-                        let fliter = if self.filter_buffer.is_empty() {
-                            None
-                        } else {
-                            Some(self.filter_buffer.clone())
-                        };
-                        self.adb_logcat_worker.as_mut().unwrap().set_fliter(fliter);
-                        println!("set fliter: {}", self.filter_buffer);
+                    let mut apply_filter = false;
+                    ui.menu_button("Recent", |ui| {
+                        if self.settings.recent_filters.is_empty() {
+                            ui.label("No recent filter");
+                        }
+                        for filter in self.settings.recent_filters.iter() {
+                            if ui.button(filter).clicked() {
+                                self.filter_buffer = filter.clone();
+                                apply_filter = true;
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                    apply_filter |= ui.button("Fliter").clicked();
+                    if apply_filter {
+                        self.apply_filter();
+                    }
+                    ui.add(
+                        egui::TextEdit
+                            ::singleline(&mut self.find_buffer)
+                            .id(egui::Id::new(FIND_ID))
+                            .hint_text("find")
+                            .desired_width(120.0)
+                    );
+                    if ui.selectable_label(self.autoscroll, "Autoscroll").clicked() {
+                        self.toggle_autoscroll();
+                    }
+                    let (paused, new_lines) = self.adb_logcat_worker
+                        .as_ref()
+                        .map_or((false, 0), |w| (w.is_paused(), w.new_lines()));
+                    let text = if paused {
+                        format!("Resume ({} new)", new_lines)
+                    } else {
+                        "Pause".to_string()
+                    };
+                    if ui.selectable_label(paused, text).clicked() {
+                        self.toggle_pause();
                     }
                     // show a button to scroll to bottom
                     scoll_to_bottom |= ui.button("Scroll Bottom").clicked();
+                    if ui.button("Retrace").clicked() {
+                        self.retrace_panel.open = true;
+                    }
+                    if ui.button("Settings").clicked() {
+                        self.settings_open = true;
+                    }
+                    if ui.button("Themes").clicked() {
+                        self.theme_editor.open = true;
+                    }
+                    if ui.button("Shortcuts").clicked() {
+                        self.shortcut_editor.open = true;
+                    }
+                    if ui.selectable_label(self.bookmarks.open, "Bookmarks").clicked() {
+                        self.bookmarks.open = !self.bookmarks.open;
+                    }
+                    if ui.selectable_label(self.inspector.open, "Inspector").clicked() {
+                        self.inspector.open = !self.inspector.open;
+                    }
+                    ui.menu_button("Session", |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label("file:");
+                            ui.text_edit_singleline(&mut self.settings.session_path);
+                        });
+                        ui.horizontal(|ui| {
+                            if ui.button("Save").clicked() {
+                                self.save_session(ui.ctx());
+                                ui.close_menu();
+                            }
+                            if ui.button("Open").clicked() {
+                                self.open_session(ui.ctx());
+                                ui.close_menu();
+                            }
+                        });
+                    });
+                    if ui.button("Shell").clicked() {
+                        self.shell_panel.open = true;
+                    }
+                    if ui.button("Files").clicked() {
+                        self.file_explorer.open = true;
+                    }
+                    if ui.button("Packages").clicked() {
+                        self.packages_panel.open = true;
+                    }
+                    if ui.button("Launch").clicked() {
+                        self.launcher.open = true;
+                    }
+                    if ui.button("Processes").clicked() {
+                        self.process_monitor.open = true;
+                    }
+                    if ui.button("Device Info").clicked() {
+                        self.device_info_panel.open = true;
+                    }
+                    let capturing = self.screenshot_viewer.is_capturing();
+                    if ui.add_enabled(!capturing, egui::Button::new("Screenshot")).clicked() {
+                        if let Some(device) = self.selected_serial() {
+                            self.screenshot_viewer.capture(ui.ctx(), &self.adb_path, &device);
+                        }
+                    }
+                    let recording = self.screen_recorder.is_recording();
+                    if ui.selectable_label(recording, "Screen Record").clicked() {
+                        self.screen_recorder.open = true;
+                    }
+                    if ui.button("Tombstones").clicked() {
+                        self.tombstone_viewer.open = true;
+                        if let Some(device) = self.selected_serial() {
+                            self.tombstone_viewer.refresh(ui.ctx(), &self.adb_path, &device);
+                        }
+                    }
                 });
 
             ui.separator();
@@ -419,6 +1407,122 @@ impl App for MyEguiApp {
             ui.separator();
         });
 
+        let change = settings::show_settings_window(
+            ctx,
+            &mut self.settings_open,
+            &mut self.settings,
+            &mut self.adb_path
+        );
+        match change {
+            SettingsChange::Theme => {
+                self.settings.apply_theme(ctx);
+                // follow the preset of the same name, e.g. "Light"
+                let name = match self.settings.theme {
+                    settings::Theme::Dark => "Dark",
+                    settings::Theme::Light => "Light",
+                };
+                if self.settings.log_themes.iter().any(|t| t.name == name) {
+                    self.settings.log_theme = name.to_string();
+                }
+                // tag colours depend on the background too
+                self.apply_log_theme(ctx);
+            }
+            SettingsChange::Font => {
+                // default widths are measured in characters of the log font
+                self.columns = column_layout(&self.settings);
+                if let Some(worker) = self.adb_logcat_worker.as_mut() {
+                    worker.set_font_size(self.settings.font_size);
+                    worker.set_columns(&self.columns);
+                }
+            }
+            SettingsChange::Columns => {
+                self.columns = column_layout(&self.settings);
+                if let Some(worker) = self.adb_logcat_worker.as_mut() {
+                    worker.set_columns(&self.columns);
+                }
+            }
+            SettingsChange::None => {}
+        }
+        let theme_changed = self.theme_editor.show(
+            ctx,
+            &mut self.settings.log_themes,
+            &mut self.settings.log_theme
+        );
+        if theme_changed {
+            self.apply_log_theme(ctx);
+        }
+        self.shortcut_editor.show(ctx, &mut self.settings.shortcuts);
+        let device = self.adb_devices.get(self.selected_device).map(|d| d.serial.as_str());
+        self.tombstone_viewer.show(ctx, &self.adb_path, device);
+        if self.retrace_panel.show(ctx, &self.adb_path, device, &mut self.retracer) {
+            if let Some(worker) = self.adb_logcat_worker.as_mut() {
+                worker.set_retracer(self.retracer.clone());
+            }
+        }
+        let history = &mut self.settings.shell_history;
+        self.shell_panel.show(ctx, &self.adb_path, device, history);
+        // shown first, it takes the dropped apks before the file explorer sees them
+        if let Some(filter) = self.packages_panel.show(ctx, &self.adb_path, device) {
+            self.filter_buffer = filter;
+            self.apply_filter();
+        }
+        let device = self.adb_devices.get(self.selected_device).map(|d| d.serial.as_str());
+        match self.launcher.show(ctx, &self.adb_path, device, &mut self.settings.launch) {
+            Some(LauncherEvent::Launch { clear_log }) => {
+                // cleared first so the log starts with the launch
+                if clear_log {
+                    self.clear_logcat();
+                }
+                if let Some(device) = self.selected_serial() {
+                    let filter = self.settings.launch.filter_package;
+                    self.launcher.launch(ctx, &self.adb_path, &device, filter);
+                }
+            }
+            Some(LauncherEvent::Filter(filter)) => {
+                self.filter_buffer = filter;
+                self.apply_filter();
+            }
+            None => {}
+        }
+        let device = self.adb_devices.get(self.selected_device).map(|d| d.serial.as_str());
+        let live_log = self.adb_logcat_worker
+            .as_ref()
+            .is_some_and(|w| w.session_device().is_none() && Some(w.device_name()) == device);
+        if let Some(filter) = self.process_monitor.show(ctx, &self.adb_path, device, live_log) {
+            self.filter_buffer = filter;
+            self.apply_filter();
+        }
+        let device = self.adb_devices.get(self.selected_device).map(|d| d.serial.as_str());
+        let snapshot = self.adb_logcat_worker.as_ref().and_then(|w| w.session_device_info());
+        self.device_info_panel.show(ctx, &self.adb_path, device, snapshot);
+        let recent = &mut self.settings.recent_endpoints;
+        if self.wireless_panel.show(ctx, &self.adb_path, device, recent) {
+            self.check_adb_devices();
+        }
+        let device = self.adb_devices.get(self.selected_device).map(|d| d.serial.as_str());
+        let pull_dir = &mut self.settings.pull_dir;
+        self.file_explorer.show(ctx, &self.adb_path, device, pull_dir);
+        let screenshot_dir = &self.settings.screenshot_dir;
+        let shot = self.screenshot_viewer.poll(ctx, &self.adb_path, screenshot_dir);
+        if let Some((device, marker)) = shot {
+            // the marker goes into the live log of the device it was taken on
+            let worker = self.adb_logcat_worker
+                .as_mut()
+                .filter(|w| w.session_device().is_none() && w.device_name() == device);
+            if let Some(worker) = worker {
+                let index = worker.insert_marker(&marker.line());
+                self.screenshot_viewer.set_line(index);
+            }
+        }
+        if let Some(index) = self.screenshot_viewer.show(ctx) {
+            self.jump_to = Some(index);
+        }
+        let worker = self.adb_logcat_worker.as_mut();
+        let video_dir = &self.settings.video_dir;
+        if let Some(index) = self.screen_recorder.show(ctx, &self.adb_path, worker, video_dir) {
+            self.jump_to = Some(index);
+        }
+
         // if time is not up to 1/60 second, then wait
         let time_elapsed = SystemTime::now().duration_since(time_point).unwrap();
         let time_abundance = 1.0 / (self.frame_limit as f32) - time_elapsed.as_secs_f32();
@@ -431,7 +1535,41 @@ impl App for MyEguiApp {
         }
     }
 
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        self.settings.adb_path = self.adb_path.clone();
+        self.settings.filter = self.filter_buffer.clone();
+        if let Some(device) = self.adb_devices.get(self.selected_device).map(|d| &d.serial) {
+            self.settings.last_device = Some(device.clone());
+        }
+        let tabs = [
+            ("settings", self.settings_open),
+            ("tombstones", self.tombstone_viewer.open),
+            ("retrace", self.retrace_panel.open),
+            ("shell", self.shell_panel.open),
+            ("files", self.file_explorer.open),
+            ("packages", self.packages_panel.open),
+            ("launcher", self.launcher.open),
+            ("processes", self.process_monitor.open),
+            ("device_info", self.device_info_panel.open),
+            ("wireless", self.wireless_panel.open),
+            ("screenshot", self.screenshot_viewer.open),
+            ("screenrecord", self.screen_recorder.open),
+            ("themes", self.theme_editor.open),
+            ("shortcuts", self.shortcut_editor.open),
+            ("bookmarks", self.bookmarks.open),
+            ("inspector", self.inspector.open),
+        ];
+        self.settings.open_tabs = tabs
+            .iter()
+            .filter(|(_, open)| *open)
+            .map(|(name, _)| name.to_string())
+            .collect();
+        self.settings.save(storage);
+    }
+
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
         println!("on_exit");
+        // stops logcat and waits for the recorder to write its tail
+        self.adb_logcat_worker = None;
     }
 }
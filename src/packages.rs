@@ -0,0 +1,502 @@
+use std::collections::{ HashMap, HashSet };
+use std::io::Read;
+use std::path::{ Path, PathBuf };
+
+use crate::device_info::{ parse_wm_density, Display };
+use crate::AdbError;
+
+/// One line of `pm list packages -f -U --show-versioncode`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Package {
+    pub name: String,
+    /// the base apk on the device
+    pub path: String,
+    pub version_code: Option<u64>,
+    pub uid: Option<u32>,
+    pub system: bool,
+    pub disabled: bool,
+}
+
+/// Parses `package:<apk path>=<name> versionCode:<code> uid:<uid>` lines.
+/// The path may hold `=` itself, the name never does.
+pub fn parse_packages(text: &str) -> Vec<Package> {
+    let mut packages: Vec<Package> = text
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.trim().strip_prefix("package:")?.split_whitespace();
+            let first = fields.next()?;
+            let (path, name) = match first.rsplit_once('=') {
+                Some((path, name)) => (path, name),
+                // without -f the line is just the name
+                None => ("", first),
+            };
+            let mut package = Package {
+                name: name.to_string(),
+                path: path.to_string(),
+                ..Package::default()
+            };
+            for field in fields {
+                match field.split_once(':') {
+                    Some(("versionCode", code)) => {
+                        package.version_code = code.parse().ok();
+                    }
+                    Some(("uid", uid)) => {
+                        // a package shared by several users lists `uid:10123,1010123`
+                        package.uid = uid.split(',').next().and_then(|uid| uid.parse().ok());
+                    }
+                    _ => {}
+                }
+            }
+            Some(package)
+        })
+        .collect();
+    packages.sort_by(|a, b| a.name.cmp(&b.name));
+    packages
+}
+
+fn pm(adb_path: &str, device: &str, args: &str) -> Result<String, AdbError> {
+    let output = crate::run_adb(adb_path, Some(device), &["shell", &format!("pm {}", args)])?;
+    let output = String::from_utf8_lossy(&output).to_string();
+    // old devices exit 0 and print the failure
+    let failed = output.lines().find(|line| {
+        line.starts_with("Failure") || line.starts_with("Error") || line.contains("Exception")
+    });
+    match failed {
+        Some(line) => Err(AdbError::Command(line.trim().to_string())),
+        None => Ok(output),
+    }
+}
+
+/// Every installed package, with the system and disabled flags from `-s` and `-d`.
+pub fn list_packages(adb_path: &str, device: &str) -> Result<Vec<Package>, AdbError> {
+    let all = pm(adb_path, device, "list packages -f -U --show-versioncode")?;
+    let mut packages = parse_packages(&all);
+    let names = |text: String| -> HashSet<String> {
+        parse_packages(&text).into_iter().map(|package| package.name).collect()
+    };
+    let system = names(pm(adb_path, device, "list packages -s")?);
+    let disabled = names(pm(adb_path, device, "list packages -d")?);
+    for package in packages.iter_mut() {
+        package.system = system.contains(&package.name);
+        package.disabled = disabled.contains(&package.name);
+    }
+    Ok(packages)
+}
+
+/// A permission of `dumpsys package`, none when it is install time only.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Permission {
+    pub name: String,
+    pub granted: Option<bool>,
+}
+
+/// Requested permissions of `dumpsys package <name>`, with the grant state of the
+/// runtime ones from the `runtime permissions:` section of user 0.
+pub fn parse_permissions(dumpsys: &str) -> Vec<Permission> {
+    let mut requested = Vec::new();
+    let mut granted = std::collections::HashMap::new();
+    let mut section = "";
+    for line in dumpsys.lines() {
+        let trimmed = line.trim();
+        // `requested permissions:`, `runtime permissions:`, `Packages:` and the like
+        if (trimmed.ends_with(':') && !trimmed.contains(' ')) || trimmed.ends_with("permissions:") {
+            section = trimmed;
+            continue;
+        }
+        match section {
+            "requested permissions:" => {
+                // `android.permission.CAMERA` or with `, restricted=true`
+                let name = trimmed.split([',', ':']).next().unwrap_or("").trim();
+                if name.contains('.') && !requested.iter().any(|p: &String| p == name) {
+                    requested.push(name.to_string());
+                }
+            }
+            "runtime permissions:" => {
+                if let Some((name, rest)) = trimmed.split_once(": granted=") {
+                    granted.entry(name.to_string()).or_insert(rest.starts_with("true"));
+                }
+            }
+            _ => {}
+        }
+    }
+    requested
+        .into_iter()
+        .map(|name| Permission {
+            granted: granted.get(&name).copied(),
+            name,
+        })
+        .collect()
+}
+
+pub fn permissions(
+    adb_path: &str,
+    device: &str,
+    package: &str
+) -> Result<Vec<Permission>, AdbError> {
+    let args = ["shell", "dumpsys", "package", package];
+    let output = crate::run_adb(adb_path, Some(device), &args)?;
+    Ok(parse_permissions(&String::from_utf8_lossy(&output)))
+}
+
+pub fn set_permission(
+    adb_path: &str,
+    device: &str,
+    package: &str,
+    permission: &str,
+    grant: bool
+) -> Result<(), AdbError> {
+    let verb = if grant { "grant" } else { "revoke" };
+    pm(adb_path, device, &format!("{} {} {}", verb, package, permission)).map(|_| ())
+}
+
+pub fn clear_data(adb_path: &str, device: &str, package: &str) -> Result<(), AdbError> {
+    pm(adb_path, device, &format!("clear {}", package)).map(|_| ())
+}
+
+/// `pm disable-user`, which works without root, or `pm enable`.
+pub fn set_enabled(
+    adb_path: &str,
+    device: &str,
+    package: &str,
+    enabled: bool
+) -> Result<(), AdbError> {
+    let args = if enabled {
+        format!("enable {}", package)
+    } else {
+        format!("disable-user --user 0 {}", package)
+    };
+    pm(adb_path, device, &args).map(|_| ())
+}
+
+pub fn force_stop(adb_path: &str, device: &str, package: &str) -> Result<(), AdbError> {
+    crate::run_adb(adb_path, Some(device), &["shell", "am", "force-stop", package]).map(|_| ())
+}
+
+pub fn uninstall(adb_path: &str, device: &str, package: &str) -> Result<(), AdbError> {
+    let output = crate::run_adb(adb_path, Some(device), &["uninstall", package])?;
+    check_success(&output)
+}
+
+/// Installs one app from `.apk` files, `.apks` or `.xapk` bundles. Several
+/// files are the splits of one app and go through `install-multiple`.
+pub fn install(adb_path: &str, device: &str, files: &[PathBuf]) -> Result<(), AdbError> {
+    // the bundles are unpacked next to the system temp files and removed after
+    let temp = std::env::temp_dir().join(format!("adbx-install-{}", std::process::id()));
+    let result = install_from(adb_path, device, files, &temp);
+    let _ = std::fs::remove_dir_all(&temp);
+    result
+}
+
+fn install_from(
+    adb_path: &str,
+    device: &str,
+    files: &[PathBuf],
+    temp: &Path
+) -> Result<(), AdbError> {
+    // only bundles need to know the device
+    let config = match files.iter().any(|file| is_bundle(file)) {
+        true => Some(device_config(adb_path, device)?),
+        false => None,
+    };
+    let mut apks = Vec::new();
+    for file in files {
+        match config.as_ref().filter(|_| is_bundle(file)) {
+            Some(config) => {
+                let dir = temp.join(apks.len().to_string());
+                apks.extend(extract_apks(file, &dir, config)?);
+            }
+            None => apks.push(file.clone()),
+        }
+    }
+    if apks.is_empty() {
+        return Err(AdbError::Command("no apk to install".to_string()));
+    }
+    let service = if apks.len() == 1 { "install" } else { "install-multiple" };
+    let mut args = vec![service.to_string(), "-r".to_string()];
+    args.extend(apks.iter().map(|apk| apk.to_string_lossy().to_string()));
+    let args: Vec<&str> = args.iter().map(|arg| arg.as_str()).collect();
+    let output = crate::run_adb(adb_path, Some(device), &args)?;
+    check_success(&output)
+}
+
+// adb prints `Success` or `Failure [INSTALL_FAILED_...]`
+fn check_success(output: &[u8]) -> Result<(), AdbError> {
+    let output = String::from_utf8_lossy(output);
+    match output.lines().find(|line| line.starts_with("Failure")) {
+        Some(line) => Err(AdbError::Command(line.trim().to_string())),
+        None => Ok(()),
+    }
+}
+
+pub fn is_bundle(path: &Path) -> bool {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_ascii_lowercase());
+    matches!(extension.as_deref(), Some("apks" | "xapk"))
+}
+
+/// True for the files `install` takes.
+pub fn is_installable(path: &Path) -> bool {
+    let extension = path.extension().map(|e| e.to_string_lossy().to_ascii_lowercase());
+    is_bundle(path) || extension.as_deref() == Some("apk")
+}
+
+/// What the splits of a bundle are picked for.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct DeviceConfig {
+    /// `arm64-v8a` and the others the device runs, preferred first
+    pub abis: Vec<String>,
+    /// dots per inch apps see
+    pub density: u32,
+}
+
+/// Reads the abis from getprop and the density from `wm density`.
+pub fn device_config(adb_path: &str, device: &str) -> Result<DeviceConfig, AdbError> {
+    let props = crate::get_props(adb_path, device)?;
+    let abis: Vec<String> = props
+        .get("ro.product.cpu.abilist")
+        .filter(|list| !list.trim().is_empty())
+        .or_else(|| props.get("ro.product.cpu.abi"))
+        .map(|list| {
+            list.split(',')
+                .map(|abi| abi.trim().to_string())
+                .filter(|abi| !abi.is_empty())
+                .collect()
+        })
+        .unwrap_or_default();
+    if abis.is_empty() {
+        return Err(AdbError::Command("no ro.product.cpu.abilist on the device".to_string()));
+    }
+    let output = crate::run_adb(adb_path, Some(device), &["shell", "wm", "density"])?;
+    let mut display = Display::default();
+    parse_wm_density(&String::from_utf8_lossy(&output), &mut display);
+    let density = display.override_density
+        .or(display.density)
+        .or_else(|| props.get("ro.sf.lcd_density")?.parse().ok())
+        .ok_or_else(|| AdbError::Command("no screen density on the device".to_string()))?;
+    Ok(DeviceConfig { abis, density })
+}
+
+/// Unpacks the `.apk` entries of a zip bundle the device needs into `dir`,
+/// returning their paths. Bundletool `.apks` keep those under `splits/`,
+/// one abi and one density split per module are taken.
+pub fn extract_apks(
+    bundle: &Path,
+    dir: &Path,
+    config: &DeviceConfig
+) -> std::io::Result<Vec<PathBuf>> {
+    let data = std::fs::read(bundle)?;
+    let entries = zip_entries(&data)?;
+    let names: Vec<&str> = entries
+        .iter()
+        .map(|entry| entry.name.as_str())
+        .collect();
+    let selected = select_splits(&names, config)?;
+    std::fs::create_dir_all(dir)?;
+    let mut apks = Vec::new();
+    for entry in entries.iter().filter(|entry| selected.contains(&entry.name.as_str())) {
+        // `splits/base.apk` and `base.apk` in another folder would collide
+        let name = entry.name.replace(['/', '\\'], "_");
+        let path = dir.join(name);
+        std::fs::write(&path, zip_read(&data, entry)?)?;
+        apks.push(path);
+    }
+    Ok(apks)
+}
+
+const ABIS: [&str; 8] = [
+    "armeabi",
+    "armeabi_v7a",
+    "arm64_v8a",
+    "x86",
+    "x86_64",
+    "mips",
+    "mips64",
+    "riscv64",
+];
+
+const DENSITIES: [(&str, u32); 7] = [
+    ("ldpi", 120),
+    ("mdpi", 160),
+    ("tvdpi", 213),
+    ("hdpi", 240),
+    ("xhdpi", 320),
+    ("xxhdpi", 480),
+    ("xxxhdpi", 640),
+];
+
+// `splits/base-arm64_v8a.apk` is (base, arm64_v8a), `config.xxhdpi.apk` of an xapk (config, xxhdpi)
+fn split_qualifier(name: &str) -> Option<(&str, &str)> {
+    let file = name.rsplit('/').next()?;
+    let stem = file.get(..file.len().checked_sub(4)?)?;
+    match stem.strip_prefix("config.") {
+        Some(qualifier) => Some(("config", qualifier)),
+        None => stem.rsplit_once('-'),
+    }
+}
+
+fn split_density(qualifier: &str) -> Option<u32> {
+    DENSITIES
+        .iter()
+        .find(|(name, _)| *name == qualifier)
+        .map(|(_, density)| *density)
+}
+
+// the smallest bucket not below the screen, else the largest, the way resources are picked
+fn better_density(candidate: u32, current: u32, wanted: u32) -> bool {
+    match (candidate >= wanted, current >= wanted) {
+        (true, true) => candidate < current,
+        (true, false) => true,
+        (false, true) => false,
+        (false, false) => candidate > current,
+    }
+}
+
+// language and feature splits all go, the standalone and universal apks are for older devices
+fn select_splits<'a>(names: &[&'a str], config: &DeviceConfig) -> std::io::Result<Vec<&'a str>> {
+    let apks: Vec<&str> = names
+        .iter()
+        .copied()
+        .filter(|name| name.to_ascii_lowercase().ends_with(".apk"))
+        .filter(|name| !name.starts_with("standalones/"))
+        .collect();
+    let bundletool = apks.iter().any(|name| name.starts_with("splits/"));
+    let apks: Vec<&str> = apks
+        .into_iter()
+        .filter(|name| !bundletool || name.starts_with("splits/"))
+        .collect();
+    let abis: Vec<String> = config.abis
+        .iter()
+        .map(|abi| abi.replace('-', "_"))
+        .collect();
+    // per module the most preferred abi and the closest density
+    let mut abi: HashMap<&str, (usize, &str)> = HashMap::new();
+    let mut density: HashMap<&str, u32> = HashMap::new();
+    let mut with_abi = HashSet::new();
+    for (module, qualifier) in apks.iter().filter_map(|name| split_qualifier(name)) {
+        if ABIS.contains(&qualifier) {
+            with_abi.insert(module);
+            let Some(rank) = abis.iter().position(|abi| abi == qualifier) else {
+                continue;
+            };
+            let best = abi.entry(module).or_insert((rank, qualifier));
+            if rank < best.0 {
+                *best = (rank, qualifier);
+            }
+        } else if let Some(candidate) = split_density(qualifier) {
+            let best = density.entry(module).or_insert(candidate);
+            if better_density(candidate, *best, config.density) {
+                *best = candidate;
+            }
+        }
+    }
+    if let Some(module) = with_abi.iter().find(|module| !abi.contains_key(*module)) {
+        let message = format!("{}: no split for {}", module, config.abis.join(", "));
+        return Err(invalid(&message));
+    }
+    Ok(
+        apks
+            .into_iter()
+            .filter(|name| {
+                let Some((module, qualifier)) = split_qualifier(name) else {
+                    return true;
+                };
+                if ABIS.contains(&qualifier) {
+                    return abi.get(module).is_some_and(|(_, best)| *best == qualifier);
+                }
+                match split_density(qualifier) {
+                    Some(candidate) => density.get(module) == Some(&candidate),
+                    None => true,
+                }
+            })
+            .collect()
+    )
+}
+
+struct ZipEntry {
+    name: String,
+    method: u16,
+    crc: u32,
+    compressed_size: usize,
+    size: usize,
+    offset: usize,
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn u16_at(data: &[u8], at: usize) -> std::io::Result<u16> {
+    let bytes = data.get(at..at + 2).ok_or_else(|| invalid("truncated zip"))?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], at: usize) -> std::io::Result<u32> {
+    let bytes = data.get(at..at + 4).ok_or_else(|| invalid("truncated zip"))?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+// the central directory at the end, zip64 bundles are not supported
+fn zip_entries(data: &[u8]) -> std::io::Result<Vec<ZipEntry>> {
+    // the end record is 22 bytes plus a comment of up to 64 KiB
+    let search_from = data.len().saturating_sub(22 + 0xffff);
+    let end = (search_from..data.len().saturating_sub(21))
+        .rev()
+        .find(|&i| data[i..].starts_with(&[0x50, 0x4b, 0x05, 0x06]))
+        .ok_or_else(|| invalid("not a zip file"))?;
+    let count = u16_at(data, end + 10)? as usize;
+    let mut at = u32_at(data, end + 16)? as usize;
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        if u32_at(data, at)? != 0x02014b50 {
+            return Err(invalid("bad zip central directory"));
+        }
+        let name_len = u16_at(data, at + 28)? as usize;
+        let extra_len = u16_at(data, at + 30)? as usize;
+        let comment_len = u16_at(data, at + 32)? as usize;
+        let name = data.get(at + 46..at + 46 + name_len).ok_or_else(|| invalid("truncated zip"))?;
+        entries.push(ZipEntry {
+            name: String::from_utf8_lossy(name).to_string(),
+            method: u16_at(data, at + 10)?,
+            crc: u32_at(data, at + 16)?,
+            compressed_size: u32_at(data, at + 20)? as usize,
+            size: u32_at(data, at + 24)? as usize,
+            offset: u32_at(data, at + 42)? as usize,
+        });
+        at += 46 + name_len + extra_len + comment_len;
+    }
+    Ok(entries)
+}
+
+fn zip_read(data: &[u8], entry: &ZipEntry) -> std::io::Result<Vec<u8>> {
+    let at = entry.offset;
+    if u32_at(data, at)? != 0x04034b50 {
+        return Err(invalid("bad zip local header"));
+    }
+    // the local extra field may differ from the central one
+    let start = at + 30 + (u16_at(data, at + 26)? as usize) + (u16_at(data, at + 28)? as usize);
+    let compressed = data
+        .get(start..start + entry.compressed_size)
+        .ok_or_else(|| invalid("truncated zip"))?;
+    let content = match entry.method {
+        0 => compressed.to_vec(),
+        8 => {
+            // the sizes come from the file, allocate for what is there and inflate no more
+            // than declared, a wrong size fails the check below
+            let capacity = entry.size.min(compressed.len().saturating_mul(4));
+            let mut content = Vec::with_capacity(capacity);
+            flate2::read::DeflateDecoder
+                ::new(compressed)
+                .take((entry.size as u64) + 1)
+                .read_to_end(&mut content)?;
+            content
+        }
+        method => {
+            return Err(invalid(&format!("{}: zip method {} is not supported", entry.name, method)));
+        }
+    };
+    let mut crc = flate2::Crc::new();
+    crc.update(&content);
+    if content.len() != entry.size || crc.sum() != entry.crc {
+        return Err(invalid(&format!("{}: corrupt zip entry", entry.name)));
+    }
+    Ok(content)
+}
@@ -0,0 +1,427 @@
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+
+use adbx::packages::{ self, Package, Permission };
+use adbx::AdbError;
+
+use crate::panel_status::PanelStatus;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    All,
+    ThirdParty,
+    System,
+    Disabled,
+}
+
+impl Kind {
+    const ALL: [Kind; 4] = [Kind::All, Kind::ThirdParty, Kind::System, Kind::Disabled];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Kind::All => "All",
+            Kind::ThirdParty => "Third-party",
+            Kind::System => "System",
+            Kind::Disabled => "Disabled",
+        }
+    }
+
+    fn matches(&self, package: &Package) -> bool {
+        match self {
+            Kind::All => true,
+            Kind::ThirdParty => !package.system,
+            Kind::System => package.system,
+            Kind::Disabled => package.disabled,
+        }
+    }
+}
+
+/// Installed packages of the selected device, `.apk` files dropped on it are installed.
+pub struct PackagesPanel {
+    pub open: bool,
+    // the device the list belongs to
+    device: Option<String>,
+    packages: Vec<Package>,
+    search: String,
+    kind: Kind,
+    selected: Option<String>,
+    permissions: Vec<Permission>,
+    permission: String,
+    confirm_uninstall: bool,
+    // file names being installed, install takes a while
+    installing: Option<(String, JoinHandle<Result<(), AdbError>>)>,
+    status: PanelStatus,
+}
+
+impl PackagesPanel {
+    pub fn new() -> Self {
+        PackagesPanel {
+            open: false,
+            device: None,
+            packages: Vec::new(),
+            search: String::new(),
+            kind: Kind::ThirdParty,
+            selected: None,
+            permissions: Vec::new(),
+            permission: String::new(),
+            confirm_uninstall: false,
+            installing: None,
+            status: PanelStatus::default(),
+        }
+    }
+
+    // the outcome of an action on the selected package
+    fn done(&mut self, command: String, result: Result<(), AdbError>, message: String) {
+        match result {
+            Ok(()) => {
+                ::log::info!("{}", message);
+                self.status.clear_error();
+                self.status.set(message);
+            }
+            Err(err) => self.status.report(command, err.to_string()),
+        }
+    }
+
+    pub fn refresh(&mut self, adb_path: &str, device: &str) {
+        self.status.clear_error();
+        self.device = Some(device.to_string());
+        match packages::list_packages(adb_path, device) {
+            Ok(packages) => {
+                self.packages = packages;
+            }
+            Err(err) => {
+                self.packages.clear();
+                let args = ["shell", "pm list packages -f -U --show-versioncode"];
+                self.status.report(
+                    adbx::command_line(adb_path, Some(device), &args),
+                    err.to_string()
+                );
+            }
+        }
+        let selected = self.selected.clone();
+        if let Some(name) = selected {
+            if self.packages.iter().any(|p| p.name == name) {
+                self.select(adb_path, device, &name);
+            } else {
+                self.selected = None;
+            }
+        }
+    }
+
+    fn select(&mut self, adb_path: &str, device: &str, name: &str) {
+        self.selected = Some(name.to_string());
+        self.confirm_uninstall = false;
+        match packages::permissions(adb_path, device, name) {
+            Ok(permissions) => {
+                self.permissions = permissions;
+            }
+            Err(err) => {
+                self.permissions.clear();
+                let args = ["shell", "dumpsys", "package", name];
+                self.status.report(
+                    adbx::command_line(adb_path, Some(device), &args),
+                    err.to_string()
+                );
+            }
+        }
+    }
+
+    fn install(&mut self, ctx: &egui::Context, adb_path: &str, device: &str, files: Vec<PathBuf>) {
+        if self.installing.is_some() {
+            self.status.set("wait for the running install".to_string());
+            return;
+        }
+        let names = files
+            .iter()
+            .filter_map(|file| file.file_name())
+            .map(|name| name.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let (adb_path, device) = (adb_path.to_string(), device.to_string());
+        let ctx = ctx.clone();
+        let handle = std::thread::spawn(move || {
+            let result = packages::install(&adb_path, &device, &files);
+            ctx.request_repaint();
+            result
+        });
+        self.status.set(format!("installing {}", names));
+        self.installing = Some((names, handle));
+    }
+
+    fn poll_install(&mut self, adb_path: &str, device: &str) {
+        let finished = self.installing.as_ref().is_some_and(|(_, handle)| handle.is_finished());
+        if !finished {
+            return;
+        }
+        let Some((names, handle)) = self.installing.take() else {
+            return;
+        };
+        let result = handle
+            .join()
+            .unwrap_or_else(|_| Err(AdbError::Command("install panicked".to_string())));
+        let command = adbx::command_line(adb_path, Some(device), &["install", &names]);
+        self.done(command, result, format!("installed {}", names));
+        self.refresh(adb_path, device);
+    }
+
+    /// Returns a log filter on the pids of a package.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        adb_path: &str,
+        device: Option<&str>
+    ) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+        let mut filter = None;
+        let mut open = self.open;
+        egui::Window
+            ::new("Packages")
+            .open(&mut open)
+            .default_size(egui::vec2(760.0, 520.0))
+            .resizable(true)
+            .show(ctx, |ui| {
+                let Some(device) = device else {
+                    ui.label("No device selected");
+                    return;
+                };
+                if self.device.as_deref() != Some(device) {
+                    self.selected = None;
+                    self.refresh(adb_path, device);
+                }
+                self.poll_install(adb_path, device);
+                // apks go to this panel, anything else to the file explorer
+                let files: Vec<PathBuf> = ctx.input_mut(|i| {
+                    let mut files = Vec::new();
+                    i.raw.dropped_files.retain(|file| {
+                        let apk = file.path.as_ref().filter(|p| packages::is_installable(p));
+                        match apk {
+                            Some(path) => {
+                                files.push(path.clone());
+                                false
+                            }
+                            None => true,
+                        }
+                    });
+                    files
+                });
+                if !files.is_empty() {
+                    self.install(ctx, adb_path, device, files);
+                }
+
+                ui.horizontal(|ui| {
+                    if ui.button("Refresh").clicked() {
+                        self.refresh(adb_path, device);
+                    }
+                    ui.add(
+                        egui::TextEdit
+                            ::singleline(&mut self.search)
+                            .hint_text("search")
+                            .desired_width(180.0)
+                    );
+                    for kind in Kind::ALL {
+                        ui.radio_value(&mut self.kind, kind, kind.name());
+                    }
+                });
+                ui.horizontal(|ui| {
+                    if self.installing.is_some() {
+                        ui.spinner();
+                    }
+                    match self.status.status() {
+                        Some(status) => ui.label(status),
+                        None => ui.weak("Drop .apk, .apks or .xapk files here to install"),
+                    };
+                });
+                self.status.show_error(ui);
+                ui.separator();
+                ui.columns(2, |columns| {
+                    self.show_list(&mut columns[0], adb_path, device);
+                    filter = self.show_details(&mut columns[1], adb_path, device);
+                });
+            });
+        self.open = open;
+        filter
+    }
+
+    fn show_list(&mut self, ui: &mut egui::Ui, adb_path: &str, device: &str) {
+        let search = self.search.to_lowercase();
+        let shown: Vec<&Package> = self.packages
+            .iter()
+            .filter(|p| self.kind.matches(p) && p.name.to_lowercase().contains(&search))
+            .collect();
+        ui.weak(format!("{} of {} packages", shown.len(), self.packages.len()));
+        let mut clicked = None;
+        let row_height = ui.text_style_height(&egui::TextStyle::Body);
+        egui::ScrollArea
+            ::vertical()
+            .id_source("packages_list")
+            .auto_shrink([false, false])
+            .show_rows(ui, row_height, shown.len(), |ui, rows| {
+                for package in &shown[rows] {
+                    let selected = self.selected.as_ref() == Some(&package.name);
+                    let text = if package.disabled {
+                        egui::RichText::new(&package.name).weak()
+                    } else {
+                        egui::RichText::new(&package.name)
+                    };
+                    if ui.selectable_label(selected, text).clicked() {
+                        clicked = Some(package.name.clone());
+                    }
+                }
+            });
+        if let Some(name) = clicked {
+            self.select(adb_path, device, &name);
+        }
+    }
+
+    fn show_details(&mut self, ui: &mut egui::Ui, adb_path: &str, device: &str) -> Option<String> {
+        let name = self.selected.clone()?;
+        let package = self.packages.iter().find(|p| p.name == name)?.clone();
+        let mut filter = None;
+        egui::Grid
+            ::new("package_details")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("package");
+                ui.monospace(&package.name);
+                ui.end_row();
+                ui.label("apk");
+                ui.monospace(&package.path);
+                ui.end_row();
+                ui.label("version code");
+                ui.label(package.version_code.map(|c| c.to_string()).unwrap_or_default());
+                ui.end_row();
+                ui.label("uid");
+                ui.label(package.uid.map(|u| u.to_string()).unwrap_or_default());
+                ui.end_row();
+                ui.label("flags");
+                let mut flags = vec![if package.system { "system" } else { "third-party" }];
+                if package.disabled {
+                    flags.push("disabled");
+                }
+                ui.label(flags.join(", "));
+                ui.end_row();
+            });
+        ui.horizontal_wrapped(|ui| {
+            let hint = "Show only the lines of its processes";
+            if ui.button("Filter Logs").on_hover_text(hint).clicked() {
+                match adbx::get_pids(adb_path, device, &name) {
+                    Ok(pids) if pids.is_empty() => {
+                        self.status.set(format!("{} is not running", name));
+                    }
+                    Ok(pids) => {
+                        let pids: Vec<String> = pids.iter().map(|pid| pid.to_string()).collect();
+                        filter = Some(format!("pid:{}", pids.join(",")));
+                    }
+                    Err(err) => {
+                        let args = ["shell", "pidof", name.as_str()];
+                        let command = adbx::command_line(adb_path, Some(device), &args);
+                        self.status.report(command, err.to_string());
+                    }
+                }
+            }
+            if ui.button("Force Stop").clicked() {
+                let result = packages::force_stop(adb_path, device, &name);
+                let command = format!("am force-stop {}", name);
+                let command = adbx::command_line(adb_path, Some(device), &["shell", &command]);
+                self.done(command, result, format!("stopped {}", name));
+            }
+            if ui.button("Clear Data").clicked() {
+                let result = packages::clear_data(adb_path, device, &name);
+                let command = format!("pm clear {}", name);
+                let command = adbx::command_line(adb_path, Some(device), &["shell", &command]);
+                self.done(command, result, format!("cleared the data of {}", name));
+            }
+            let (text, enable) = match package.disabled {
+                true => ("Enable", true),
+                false => ("Disable", false),
+            };
+            if ui.button(text).clicked() {
+                let result = packages::set_enabled(adb_path, device, &name, enable);
+                let command = format!("pm {} {}", text.to_lowercase(), name);
+                let command = adbx::command_line(adb_path, Some(device), &["shell", &command]);
+                self.done(command, result, format!("{}d {}", text.to_lowercase(), name));
+                self.refresh(adb_path, device);
+            }
+            if self.confirm_uninstall {
+                let confirm = egui::Button
+                    ::new("Confirm Uninstall")
+                    .fill(egui::Color32::from_rgb(160, 40, 40));
+                if ui.add(confirm).clicked() {
+                    let result = packages::uninstall(adb_path, device, &name);
+                    let args = ["uninstall", name.as_str()];
+                    let command = adbx::command_line(adb_path, Some(device), &args);
+                    self.done(command, result, format!("uninstalled {}", name));
+                    self.refresh(adb_path, device);
+                }
+            } else if ui.button("Uninstall").clicked() {
+                self.confirm_uninstall = true;
+            }
+        });
+
+        ui.separator();
+        ui.label("permissions");
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit
+                    ::singleline(&mut self.permission)
+                    .hint_text("android.permission.CAMERA")
+                    .desired_width(220.0)
+            );
+            let permission = self.permission.trim().to_string();
+            let mut set = None;
+            if ui.button("Grant").clicked() {
+                set = Some(true);
+            }
+            if ui.button("Revoke").clicked() {
+                set = Some(false);
+            }
+            if let Some(grant) = set.filter(|_| !permission.is_empty()) {
+                self.set_permission(adb_path, device, &name, &permission, grant);
+            }
+        });
+        let mut toggle = None;
+        egui::ScrollArea
+            ::vertical()
+            .id_source("package_permissions")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                for permission in self.permissions.iter() {
+                    match permission.granted {
+                        Some(granted) => {
+                            let mut checked = granted;
+                            if ui.checkbox(&mut checked, &permission.name).changed() {
+                                toggle = Some((permission.name.clone(), checked));
+                            }
+                        }
+                        // install time permissions cannot be changed
+                        None => {
+                            ui.weak(&permission.name);
+                        }
+                    }
+                }
+            });
+        if let Some((permission, grant)) = toggle {
+            self.set_permission(adb_path, device, &name, &permission, grant);
+        }
+        filter
+    }
+
+    fn set_permission(
+        &mut self,
+        adb_path: &str,
+        device: &str,
+        package: &str,
+        permission: &str,
+        grant: bool
+    ) {
+        let result = packages::set_permission(adb_path, device, package, permission, grant);
+        let verb = if grant { "grant" } else { "revoke" };
+        let command = format!("pm {} {} {}", verb, package, permission);
+        let command = adbx::command_line(adb_path, Some(device), &["shell", &command]);
+        self.done(command, result, format!("{} {} {}", verb, package, permission));
+        let package = package.to_string();
+        self.select(adb_path, device, &package);
+    }
+}
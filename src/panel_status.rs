@@ -0,0 +1,49 @@
+/// The error and status lines of a panel. Errors keep the command that failed.
+#[derive(Default)]
+pub struct PanelStatus {
+    error: Option<String>,
+    status: Option<String>,
+}
+
+impl PanelStatus {
+    /// Logs a failed command and shows it as `> command: message`.
+    pub fn report(&mut self, command: String, message: String) {
+        ::log::error!("{}: {}", command, message);
+        self.error = Some(format!("> {}: {}", command, message));
+    }
+
+    pub fn set(&mut self, status: impl Into<String>) {
+        self.status = Some(status.into());
+    }
+
+    /// Clears the error, e.g. after the command succeeded.
+    pub fn clear_error(&mut self) {
+        self.error = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.error = None;
+        self.status = None;
+    }
+
+    pub fn has_error(&self) -> bool {
+        self.error.is_some()
+    }
+
+    pub fn status(&self) -> Option<&str> {
+        self.status.as_deref()
+    }
+
+    pub fn show_error(&self, ui: &mut egui::Ui) {
+        if let Some(error) = self.error.as_ref() {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+    }
+
+    pub fn show(&self, ui: &mut egui::Ui) {
+        self.show_error(ui);
+        if let Some(status) = self.status.as_ref() {
+            ui.label(status);
+        }
+    }
+}
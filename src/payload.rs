@@ -0,0 +1,188 @@
+/// A structured value found in a log message, e.g. a JSON payload or an intent dump.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    Value(String),
+    List(Vec<Node>),
+    Map(Vec<(String, Node)>),
+}
+
+/// Finds a JSON object or array in `message`, falling back to `key=value` pairs
+/// such as `Intent { act=android.intent.action.MAIN cmp=com.example/.Main }`.
+pub fn parse_message(message: &str) -> Option<Node> {
+    parse_json(message).or_else(|| parse_pairs(message))
+}
+
+// the first bracket that starts valid JSON, e.g. after a `[main]` prefix
+fn parse_json(message: &str) -> Option<Node> {
+    message.match_indices(['{', '[']).find_map(|(start, _)| {
+        // trailing text after the payload is fine
+        let mut values = serde_json::Deserializer
+            ::from_str(&message[start..])
+            .into_iter::<serde_json::Value>();
+        match values.next()? {
+            Ok(value) if value.is_object() || value.is_array() => Some(from_json(value)),
+            _ => None,
+        }
+    })
+}
+
+fn from_json(value: serde_json::Value) -> Node {
+    match value {
+        serde_json::Value::Array(items) => Node::List(items.into_iter().map(from_json).collect()),
+        serde_json::Value::Object(entries) => {
+            Node::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, from_json(value)))
+                    .collect()
+            )
+        }
+        // strings keep their quotes to tell "1" from 1
+        scalar => Node::Value(scalar.to_string()),
+    }
+}
+
+// at least two pairs, one `a=b` is readable enough inline
+fn parse_pairs(message: &str) -> Option<Node> {
+    let start = (0..message.len()).find(|i| {
+        message.is_char_boundary(*i) && key_at(message, *i).is_some()
+    })?;
+    let entries = pairs(&message[start..]);
+    if entries.len() < 2 {
+        return None;
+    }
+    Some(Node::Map(entries))
+}
+
+// `key=` starting at `i`, not preceded by a key character
+fn key_at(text: &str, i: usize) -> Option<&str> {
+    let is_key = |c: char| c.is_alphanumeric() || matches!(c, '_' | '.' | '-');
+    if text[..i].chars().next_back().is_some_and(is_key) {
+        return None;
+    }
+    let rest = &text[i..];
+    let end = rest.find(|c: char| !is_key(c))?;
+    (end > 0 && rest[end..].starts_with('=')).then(|| &rest[..end])
+}
+
+// `a=1, b=2` or `a=1 b=two words c=[x, y]`, stopping at an unmatched closing bracket
+fn pairs(text: &str) -> Vec<(String, Node)> {
+    let mut entries = Vec::new();
+    let mut i = 0;
+    while let Some(key) = key_at(text, i) {
+        let value_start = i + key.len() + 1;
+        let mut depth = 0usize;
+        let mut end = text.len();
+        let mut next = None;
+        for (offset, c) in text[value_start..].char_indices() {
+            let at = value_start + offset;
+            match c {
+                '{' | '[' | '(' => depth += 1,
+                '}' | ']' | ')' if depth == 0 => {
+                    end = at;
+                    break;
+                }
+                '}' | ']' | ')' => depth -= 1,
+                ',' if depth == 0 => {
+                    end = at;
+                    next = Some(skip_space(text, at + 1));
+                    break;
+                }
+                c if c.is_whitespace() && depth == 0 => {
+                    let after = skip_space(text, at);
+                    if key_at(text, after).is_some() {
+                        end = at;
+                        next = Some(after);
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        entries.push((key.to_string(), parse_value(text[value_start..end].trim())));
+        match next {
+            Some(next) => {
+                i = next;
+            }
+            None => {
+                break;
+            }
+        }
+    }
+    entries
+}
+
+fn skip_space(text: &str, from: usize) -> usize {
+    text[from..]
+        .find(|c: char| !c.is_whitespace())
+        .map_or(text.len(), |offset| from + offset)
+}
+
+// `{a=1}`, `[x, y]` and `Bundle[{a=1}]` open up, anything else stays text
+fn parse_value(value: &str) -> Node {
+    let Some(open) = value.find(['{', '[']) else {
+        return Node::Value(value.to_string());
+    };
+    let prefix = &value[..open];
+    let close = if value[open..].starts_with('{') { '}' } else { ']' };
+    let wrapped = prefix.chars().all(|c| c.is_alphanumeric() || c == '_') &&
+        value.ends_with(close) &&
+        matching_close(&value[open..]) == Some(value.len() - open - 1);
+    if !wrapped {
+        return Node::Value(value.to_string());
+    }
+    let inner = value[open + 1..value.len() - 1].trim();
+    if inner.is_empty() {
+        return Node::List(Vec::new());
+    }
+    if key_at(inner, 0).is_some() {
+        let entries = pairs(inner);
+        return Node::Map(entries);
+    }
+    if inner.starts_with(['{', '[']) && split_items(inner).len() == 1 {
+        return parse_value(inner);
+    }
+    Node::List(
+        split_items(inner)
+            .into_iter()
+            .map(|item| parse_value(item.trim()))
+            .collect()
+    )
+}
+
+// byte offset of the bracket closing the one `text` starts with
+fn matching_close(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' | '[' | '(' => depth += 1,
+            '}' | ']' | ')' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn split_items(text: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '{' | '[' | '(' => depth += 1,
+            '}' | ']' | ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                items.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&text[start..]);
+    items
+}
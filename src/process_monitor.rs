@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+use std::thread::JoinHandle;
+use std::time::{ Duration, Instant };
+
+use adbx::processes::{ self, Process };
+use adbx::AdbError;
+
+use crate::panel_status::PanelStatus;
+
+// top takes a while, it only runs while the window is open
+const OPEN_INTERVAL: Duration = Duration::from_secs(3);
+const NAMES_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, PartialEq)]
+enum Sort {
+    Pid,
+    Name,
+    Cpu,
+    Memory,
+}
+
+// threads and kill run on a thread like the ps and top refreshes
+struct Pending<T> {
+    pid: u32,
+    command: String,
+    handle: JoinHandle<Result<T, AdbError>>,
+}
+
+/// Processes of the selected device from `ps` and `top`, also naming the pids
+/// of the live log.
+pub struct ProcessMonitor {
+    pub open: bool,
+    device: Option<String>,
+    processes: Vec<Process>,
+    // every pid seen on the device, rows of processes gone since keep their name
+    names: HashMap<u32, String>,
+    // thread names of the processes whose threads were listed
+    thread_names: HashMap<u32, String>,
+    fetch: Option<JoinHandle<Result<Vec<Process>, AdbError>>>,
+    fetched_at: Option<Instant>,
+    search: String,
+    sort: Sort,
+    selected: Option<u32>,
+    threads: Vec<(u32, String)>,
+    loading_threads: Option<Pending<Vec<(u32, String)>>>,
+    confirm_kill: bool,
+    killing: Option<Pending<()>>,
+    status: PanelStatus,
+}
+
+impl ProcessMonitor {
+    pub fn new() -> Self {
+        ProcessMonitor {
+            open: false,
+            device: None,
+            processes: Vec::new(),
+            names: HashMap::new(),
+            thread_names: HashMap::new(),
+            fetch: None,
+            fetched_at: None,
+            search: String::new(),
+            sort: Sort::Cpu,
+            selected: None,
+            threads: Vec::new(),
+            loading_threads: None,
+            confirm_kill: false,
+            killing: None,
+            status: PanelStatus::default(),
+        }
+    }
+
+    /// Process name of a pid of `device`, for the log rows.
+    pub fn process_name(&self, device: &str, pid: u32) -> Option<&str> {
+        if self.device.as_deref() != Some(device) {
+            return None;
+        }
+        self.names.get(&pid).map(|name| name.as_str())
+    }
+
+    pub fn thread_name(&self, device: &str, tid: u32) -> Option<&str> {
+        if self.device.as_deref() != Some(device) {
+            return None;
+        }
+        self.thread_names.get(&tid).map(|name| name.as_str())
+    }
+
+    // refreshes in the background, every few seconds while open and less
+    // often when only the names of a live log are needed
+    fn poll(&mut self, ctx: &egui::Context, adb_path: &str, device: &str, live_log: bool) {
+        if self.device.as_deref() != Some(device) {
+            self.device = Some(device.to_string());
+            self.processes.clear();
+            self.names.clear();
+            self.thread_names.clear();
+            self.threads.clear();
+            self.selected = None;
+            self.fetched_at = None;
+            // a fetch still running for the old device is dropped
+            self.fetch = None;
+            self.loading_threads = None;
+            self.killing = None;
+        }
+        let finished = self.fetch.as_ref().is_some_and(|fetch| fetch.is_finished());
+        if let Some(fetch) = finished.then(|| self.fetch.take()).flatten() {
+            let args = ["shell", "ps", "-A", "-o", "PID,PPID,USER,RSS,NAME"];
+            let command = adbx::command_line(adb_path, Some(device), &args);
+            match fetch.join() {
+                Ok(Ok(processes)) => {
+                    for process in processes.iter() {
+                        self.names.insert(process.pid, process.name.clone());
+                    }
+                    self.processes = processes;
+                    self.status.clear_error();
+                }
+                Ok(Err(err)) => self.status.report(command, err.to_string()),
+                Err(_) => self.status.report(command, "ps thread panicked".to_string()),
+            }
+        }
+        let interval = if self.open { OPEN_INTERVAL } else { NAMES_INTERVAL };
+        let due = self.fetched_at.is_none_or(|at| at.elapsed() >= interval);
+        if self.fetch.is_none() && due && (self.open || live_log) {
+            self.fetched_at = Some(Instant::now());
+            let top = self.open;
+            let (adb_path, device) = (adb_path.to_string(), device.to_string());
+            let ctx = ctx.clone();
+            self.fetch = Some(
+                std::thread::spawn(move || {
+                    let processes = processes::list_processes(&adb_path, &device, top);
+                    ctx.request_repaint();
+                    processes
+                })
+            );
+        }
+        if self.open || live_log {
+            ctx.request_repaint_after(interval);
+        }
+        self.poll_threads();
+        self.poll_kill();
+    }
+
+    fn load_threads(&mut self, ctx: &egui::Context, adb_path: &str, device: &str, pid: u32) {
+        let command = format!("ps -T -p {} -o TID,CMD", pid);
+        let command = adbx::command_line(adb_path, Some(device), &["shell", &command]);
+        let (adb_path, device) = (adb_path.to_string(), device.to_string());
+        let ctx = ctx.clone();
+        let handle = std::thread::spawn(move || {
+            let threads = processes::threads(&adb_path, &device, pid);
+            ctx.request_repaint();
+            threads
+        });
+        self.loading_threads = Some(Pending { pid, command, handle });
+    }
+
+    fn poll_threads(&mut self) {
+        let finished = self.loading_threads.as_ref().is_some_and(|p| p.handle.is_finished());
+        let Some(pending) = finished.then(|| self.loading_threads.take()).flatten() else {
+            return;
+        };
+        let threads = pending.handle
+            .join()
+            .unwrap_or_else(|_| Err(AdbError::Command("ps thread panicked".to_string())));
+        match threads {
+            Ok(threads) => {
+                for (tid, name) in threads.iter() {
+                    self.thread_names.insert(*tid, name.clone());
+                }
+                // another process may have been selected meanwhile
+                if self.selected == Some(pending.pid) {
+                    self.threads = threads;
+                }
+            }
+            Err(err) => {
+                self.threads.clear();
+                self.status.report(pending.command, err.to_string());
+            }
+        }
+    }
+
+    fn kill(&mut self, ctx: &egui::Context, adb_path: &str, device: &str, process: Process) {
+        let command = format!("kill -9 {}", process.pid);
+        let command = adbx::command_line(adb_path, Some(device), &["shell", &command]);
+        let (adb_path, device) = (adb_path.to_string(), device.to_string());
+        let ctx = ctx.clone();
+        let pid = process.pid;
+        let handle = std::thread::spawn(move || {
+            let killed = processes::kill(&adb_path, &device, &process);
+            ctx.request_repaint();
+            killed
+        });
+        self.killing = Some(Pending { pid, command, handle });
+    }
+
+    fn poll_kill(&mut self) {
+        let finished = self.killing.as_ref().is_some_and(|p| p.handle.is_finished());
+        let Some(pending) = finished.then(|| self.killing.take()).flatten() else {
+            return;
+        };
+        let killed = pending.handle
+            .join()
+            .unwrap_or_else(|_| Err(AdbError::Command("kill thread panicked".to_string())));
+        match killed {
+            Ok(()) => {
+                let name = self.names.get(&pending.pid).map(|name| name.as_str());
+                let message = format!("killed {} {}", pending.pid, name.unwrap_or_default());
+                ::log::info!("{}", message);
+                self.status.set(message);
+                // shown gone on the next refresh
+                self.fetched_at = None;
+            }
+            Err(err) => self.status.report(pending.command, err.to_string()),
+        }
+    }
+
+    /// `live_log` is true while the live logcat of `device` is shown. Returns a
+    /// pid filter for the log.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        adb_path: &str,
+        device: Option<&str>,
+        live_log: bool
+    ) -> Option<String> {
+        let device = device?;
+        self.poll(ctx, adb_path, device, live_log);
+        if !self.open {
+            return None;
+        }
+        let mut filter = None;
+        let mut open = self.open;
+        egui::Window
+            ::new("Processes")
+            .open(&mut open)
+            .default_size(egui::vec2(640.0, 560.0))
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit
+                            ::singleline(&mut self.search)
+                            .hint_text("search name, user or pid")
+                            .desired_width(200.0)
+                    );
+                    ui.weak(format!("{} processes", self.processes.len()));
+                    if self.fetch.is_some() {
+                        ui.spinner();
+                    }
+                });
+                self.status.show(ui);
+                if let Some(pid) = self.selected {
+                    filter = self.show_selected(ui, adb_path, device, pid);
+                }
+                ui.separator();
+                if let Some(clicked) = self.show_table(ui) {
+                    self.selected = Some(clicked);
+                    self.confirm_kill = false;
+                    self.threads.clear();
+                    filter = Some(format!("pid:{}", clicked));
+                }
+            });
+        self.open = open;
+        filter
+    }
+
+    fn show_selected(
+        &mut self,
+        ui: &mut egui::Ui,
+        adb_path: &str,
+        device: &str,
+        pid: u32
+    ) -> Option<String> {
+        let mut filter = None;
+        let Some(process) = self.processes.iter().find(|p| p.pid == pid).cloned() else {
+            ui.weak(format!("{} has exited", pid));
+            return None;
+        };
+        ui.horizontal_wrapped(|ui| {
+            ui.strong(format!("{} {}", process.pid, process.name));
+            if ui.button("Filter Logs").clicked() {
+                filter = Some(format!("pid:{}", pid));
+            }
+            let loading = self.loading_threads.is_some();
+            if ui.add_enabled(!loading, egui::Button::new("Threads")).clicked() {
+                self.load_threads(ui.ctx(), adb_path, device, pid);
+            }
+            if self.killing.is_some() {
+                ui.spinner();
+            } else if self.confirm_kill {
+                let confirm = egui::Button
+                    ::new("Confirm Kill")
+                    .fill(egui::Color32::from_rgb(160, 40, 40));
+                if ui.add(confirm).clicked() {
+                    self.confirm_kill = false;
+                    self.kill(ui.ctx(), adb_path, device, process.clone());
+                }
+            } else if ui.button("Kill").clicked() {
+                self.confirm_kill = true;
+            }
+            if loading {
+                ui.spinner();
+            }
+        });
+        if !self.threads.is_empty() {
+            egui::CollapsingHeader
+                ::new(format!("{} threads", self.threads.len()))
+                .id_source("process_threads")
+                .default_open(true)
+                .show(ui, |ui| {
+                    egui::ScrollArea
+                        ::vertical()
+                        .id_source("process_threads_scroll")
+                        .max_height(160.0)
+                        .show(ui, |ui| {
+                            egui::Grid
+                                ::new("process_threads_grid")
+                                .striped(true)
+                                .show(ui, |ui| {
+                                    for (tid, name) in self.threads.iter() {
+                                        ui.monospace(tid.to_string());
+                                        ui.label(name);
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                });
+        }
+        filter
+    }
+
+    // returns the pid of a clicked row
+    fn show_table(&mut self, ui: &mut egui::Ui) -> Option<u32> {
+        let search = self.search.trim().to_lowercase();
+        let mut shown: Vec<&Process> = self.processes
+            .iter()
+            .filter(|p| {
+                search.is_empty() ||
+                    p.name.to_lowercase().contains(&search) ||
+                    p.user.contains(&search) ||
+                    p.pid.to_string() == search
+            })
+            .collect();
+        match self.sort {
+            Sort::Pid => shown.sort_by_key(|p| p.pid),
+            Sort::Name => shown.sort_by(|a, b| a.name.cmp(&b.name)),
+            Sort::Cpu => {
+                shown.sort_by(|a, b| b.cpu.unwrap_or(0.0).total_cmp(&a.cpu.unwrap_or(0.0)));
+            }
+            Sort::Memory => shown.sort_by_key(|p| std::cmp::Reverse(p.rss)),
+        }
+        let mut clicked = None;
+        egui::ScrollArea
+            ::vertical()
+            .id_source("processes_scroll")
+            .auto_shrink([false, false])
+            .show(ui, |ui| {
+                egui::Grid
+                    ::new("processes_grid")
+                    .num_columns(6)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        let headers = [
+                            ("pid", Some(Sort::Pid)),
+                            ("user", None),
+                            ("cpu %", Some(Sort::Cpu)),
+                            ("mem %", None),
+                            ("rss", Some(Sort::Memory)),
+                            ("name", Some(Sort::Name)),
+                        ];
+                        for (title, sort) in headers {
+                            match sort {
+                                Some(sort) => {
+                                    if ui.selectable_label(self.sort == sort, title).clicked() {
+                                        self.sort = sort;
+                                    }
+                                }
+                                None => {
+                                    ui.strong(title);
+                                }
+                            }
+                        }
+                        ui.end_row();
+                        for process in shown {
+                            ui.monospace(process.pid.to_string());
+                            ui.label(&process.user);
+                            let percent = |value: Option<f32>| {
+                                value.map(|v| format!("{:.1}", v)).unwrap_or_default()
+                            };
+                            ui.monospace(percent(process.cpu));
+                            ui.monospace(percent(process.mem));
+                            ui.monospace(format_kib(process.rss));
+                            let selected = self.selected == Some(process.pid);
+                            let hint = format!("parent {}, click to filter the log", process.ppid);
+                            let name = ui
+                                .selectable_label(selected, &process.name)
+                                .on_hover_text(hint);
+                            if name.clicked() {
+                                clicked = Some(process.pid);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        clicked
+    }
+}
+
+/// `512K`, `1.5M` or `2.0G`.
+pub fn format_kib(kib: u64) -> String {
+    if kib >= 1024 * 1024 {
+        format!("{:.1}G", (kib as f64) / 1024.0 / 1024.0)
+    } else if kib >= 1024 {
+        format!("{:.1}M", (kib as f64) / 1024.0)
+    } else {
+        format!("{}K", kib)
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use crate::AdbError;
+
+/// A row of `ps -A -o PID,PPID,USER,RSS,NAME`, with the usage `top` reported.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Process {
+    pub pid: u32,
+    pub ppid: u32,
+    pub user: String,
+    /// resident memory in KiB
+    pub rss: u64,
+    pub name: String,
+    /// percent of one core, so up to 100 times the cores
+    pub cpu: Option<f32>,
+    pub mem: Option<f32>,
+}
+
+/// Parses `ps -A -o PID,PPID,USER,RSS,NAME`, the header line is skipped.
+pub fn parse_ps(text: &str) -> Vec<Process> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let ppid = fields.next()?.parse().ok()?;
+            let user = fields.next()?.to_string();
+            let rss = fields.next()?.parse().ok()?;
+            // kernel threads are named `[kworker/0:1]`, names never hold spaces
+            let name = fields.next()?.to_string();
+            Some(Process {
+                pid,
+                ppid,
+                user,
+                rss,
+                name,
+                ..Process::default()
+            })
+        })
+        .collect()
+}
+
+/// `%CPU` and `%MEM` by pid from `top -b -n 1`. Toybox prints the state and the
+/// cpu under one `S[%CPU]` header, old toolbox top has `CPU%` and no memory.
+pub fn parse_top(text: &str) -> HashMap<u32, (f32, Option<f32>)> {
+    let mut usage = HashMap::new();
+    let mut columns: Option<(usize, usize, Option<usize>)> = None;
+    for line in text.lines() {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let Some((pid, cpu, mem)) = columns else {
+            if !fields.contains(&"PID") {
+                continue;
+            }
+            let header: Vec<&str> = fields
+                .iter()
+                .flat_map(|field| {
+                    match field.split_once('[') {
+                        Some((state, rest)) => vec![state, rest.trim_end_matches(']')],
+                        None => vec![*field],
+                    }
+                })
+                .collect();
+            let position = |names: &[&str]| header.iter().position(|h| names.contains(h));
+            let pid = position(&["PID"]);
+            let cpu = position(&["%CPU", "CPU%"]);
+            if let Some((pid, cpu)) = pid.zip(cpu) {
+                columns = Some((pid, cpu, position(&["%MEM", "MEM%"])));
+            }
+            continue;
+        };
+        let Some(pid) = fields.get(pid).and_then(|pid| pid.parse().ok()) else {
+            continue;
+        };
+        let Some(cpu) = fields.get(cpu).and_then(|cpu| cpu.trim_end_matches('%').parse().ok())
+        else {
+            continue;
+        };
+        let mem = mem.and_then(|mem| fields.get(mem)).and_then(|mem| mem.parse().ok());
+        usage.insert(pid, (cpu, mem));
+    }
+    usage
+}
+
+/// Every process of the device, with its cpu and memory usage when `top` is true.
+pub fn list_processes(adb_path: &str, device: &str, top: bool) -> Result<Vec<Process>, AdbError> {
+    let args = ["shell", "ps", "-A", "-o", "PID,PPID,USER,RSS,NAME"];
+    let output = crate::run_adb(adb_path, Some(device), &args)?;
+    let mut processes = parse_ps(&String::from_utf8_lossy(&output));
+    if top {
+        let output = crate::run_adb(adb_path, Some(device), &["shell", "top", "-b", "-n", "1"])?;
+        let usage = parse_top(&String::from_utf8_lossy(&output));
+        for process in processes.iter_mut() {
+            if let Some((cpu, mem)) = usage.get(&process.pid) {
+                process.cpu = Some(*cpu);
+                process.mem = *mem;
+            }
+        }
+    }
+    Ok(processes)
+}
+
+/// Thread ids and names of `ps -T -p <pid> -o TID,CMD`.
+pub fn parse_threads(text: &str) -> Vec<(u32, String)> {
+    text.lines()
+        .filter_map(|line| {
+            let (tid, name) = line.trim().split_once(char::is_whitespace)?;
+            Some((tid.parse().ok()?, name.trim().to_string()))
+        })
+        .collect()
+}
+
+pub fn threads(adb_path: &str, device: &str, pid: u32) -> Result<Vec<(u32, String)>, AdbError> {
+    let pid = pid.to_string();
+    let args = ["shell", "ps", "-T", "-p", &pid, "-o", "TID,CMD"];
+    let output = crate::run_adb(adb_path, Some(device), &args)?;
+    Ok(parse_threads(&String::from_utf8_lossy(&output)))
+}
+
+/// `kill -9`, through `run-as` for the processes of a debuggable app, which the
+/// shell user may not signal.
+pub fn kill(adb_path: &str, device: &str, process: &Process) -> Result<(), AdbError> {
+    let pid = process.pid.to_string();
+    match crate::run_adb(adb_path, Some(device), &["shell", "kill", "-9", &pid]) {
+        Err(AdbError::Command(message)) if message.contains("not permitted") => {
+            // `com.example.app:remote` runs as com.example.app
+            let package = process.name.split(':').next().unwrap_or_default();
+            let args = ["shell", "run-as", package, "kill", "-9", &pid];
+            crate::run_adb(adb_path, Some(device), &args).map(|_| ())
+        }
+        result => result.map(|_| ()),
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{ BufWriter, Write };
+use std::path::PathBuf;
+
+use flate2::write::GzEncoder;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecordOptions {
+    pub dir: PathBuf,
+    /// file names are `<prefix>.<sequence>.log`, plus `.gz` when compressed
+    pub prefix: String,
+    /// uncompressed bytes per file before rotating
+    pub max_file_bytes: u64,
+    /// older files of this recording are deleted beyond this count, 0 keeps all
+    pub max_files: usize,
+    pub gzip: bool,
+}
+
+enum Output {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl Output {
+    fn finish(self) -> std::io::Result<()> {
+        match self {
+            Output::Plain(mut writer) => writer.flush(),
+            Output::Gzip(encoder) => encoder.finish()?.flush(),
+        }
+    }
+}
+
+/// Tees raw logcat lines to rotating files on disk.
+/// Dropping it closes the current file, so the tail is never lost.
+pub struct Recorder {
+    options: RecordOptions,
+    files: VecDeque<PathBuf>,
+    output: Option<Output>,
+    written: u64,
+    sequence: usize,
+}
+
+impl Recorder {
+    pub fn start(options: RecordOptions) -> std::io::Result<Recorder> {
+        std::fs::create_dir_all(&options.dir)?;
+        let mut recorder = Recorder {
+            options,
+            files: VecDeque::new(),
+            output: None,
+            written: 0,
+            sequence: 0,
+        };
+        recorder.rotate()?;
+        Ok(recorder)
+    }
+
+    /// Files written so far that were not deleted by the count limit, oldest first.
+    pub fn files(&self) -> impl Iterator<Item = &PathBuf> {
+        self.files.iter()
+    }
+
+    pub fn write_line(&mut self, line: &[u8]) -> std::io::Result<()> {
+        if self.written > 0 && self.written + (line.len() as u64) > self.options.max_file_bytes {
+            self.rotate()?;
+        }
+        match self.output.as_mut() {
+            Some(Output::Plain(writer)) => writer.write_all(line)?,
+            Some(Output::Gzip(encoder)) => encoder.write_all(line)?,
+            None => {
+                return Err(std::io::Error::other("recorder closed"));
+            }
+        }
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> std::io::Result<()> {
+        self.close_file()
+    }
+
+    fn close_file(&mut self) -> std::io::Result<()> {
+        match self.output.take() {
+            Some(output) => output.finish(),
+            None => Ok(()),
+        }
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.close_file()?;
+        self.sequence += 1;
+        let extension = if self.options.gzip { "log.gz" } else { "log" };
+        let name = format!("{}.{:03}.{}", self.options.prefix, self.sequence, extension);
+        let path = self.options.dir.join(name);
+        let writer = BufWriter::new(File::create(&path)?);
+        self.output = Some(if self.options.gzip {
+            Output::Gzip(GzEncoder::new(writer, flate2::Compression::default()))
+        } else {
+            Output::Plain(writer)
+        });
+        self.written = 0;
+        self.files.push_back(path);
+        while self.options.max_files > 0 && self.files.len() > self.options.max_files {
+            if let Some(oldest) = self.files.pop_front() {
+                if let Err(err) = std::fs::remove_file(&oldest) {
+                    log::warn!("remove {}: {}", oldest.display(), err);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        if let Err(err) = self.close_file() {
+            log::error!("close recording: {}", err);
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A parsed ProGuard/R8 `mapping.txt`, keyed by obfuscated class name.
+#[derive(Default)]
+pub struct Mapping {
+    classes: HashMap<String, ClassMapping>,
+}
+
+struct ClassMapping {
+    original: String,
+    source_file: Option<String>,
+    methods: HashMap<String, Vec<MethodMapping>>,
+}
+
+struct MethodMapping {
+    // line range in the obfuscated code, absent for methods without line info
+    obfuscated_lines: Option<(u32, u32)>,
+    // set when the method was inlined from another class
+    original_class: Option<String>,
+    original_name: String,
+    original_lines: Option<(u32, Option<u32>)>,
+}
+
+/// One retraced stack frame; inlined methods expand into several.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetracedFrame {
+    pub class: String,
+    pub method: String,
+    pub file: String,
+    pub line: Option<u32>,
+}
+
+impl Mapping {
+    pub fn load(path: &str) -> std::io::Result<Mapping> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(Mapping::parse(&text))
+    }
+
+    pub fn parse(text: &str) -> Mapping {
+        let mut mapping = Mapping::default();
+        let mut current: Option<String> = None;
+        for line in text.lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let indented = line.starts_with(char::is_whitespace);
+            let line = line.trim();
+            if let Some(comment) = line.strip_prefix('#') {
+                // R8 metadata: # {"id":"sourceFile","fileName":"Foo.kt"}
+                if indented || !comment.contains("\"sourceFile\"") {
+                    continue;
+                }
+                let class = current.as_ref().and_then(|c| mapping.classes.get_mut(c));
+                if let (Some(class), Some(file)) = (class, json_string(comment, "fileName")) {
+                    class.source_file = Some(file);
+                }
+                continue;
+            }
+            let Some((original, obfuscated)) = line.split_once(" -> ") else {
+                continue;
+            };
+            if !indented {
+                let obfuscated = obfuscated.trim_end_matches(':').trim().to_string();
+                mapping.classes.insert(obfuscated.clone(), ClassMapping {
+                    original: original.trim().to_string(),
+                    source_file: None,
+                    methods: HashMap::new(),
+                });
+                current = Some(obfuscated);
+                continue;
+            }
+            let class = current.as_ref().and_then(|c| mapping.classes.get_mut(c));
+            if let (Some(class), Some(method)) = (class, parse_method(original)) {
+                class.methods.entry(obfuscated.trim().to_string()).or_default().push(method);
+            }
+        }
+        mapping
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.classes.is_empty()
+    }
+
+    pub fn class_count(&self) -> usize {
+        self.classes.len()
+    }
+
+    pub fn original_class(&self, obfuscated: &str) -> Option<&str> {
+        self.classes.get(obfuscated).map(|c| c.original.as_str())
+    }
+
+    pub fn retrace_frame(
+        &self,
+        class: &str,
+        method: &str,
+        line: Option<u32>
+    ) -> Vec<RetracedFrame> {
+        let Some(class_mapping) = self.classes.get(class) else {
+            return Vec::new();
+        };
+        let candidates = class_mapping.methods
+            .get(method)
+            .map(|m| m.as_slice())
+            .unwrap_or_default();
+
+        let mut matched: Vec<&MethodMapping> = match line {
+            Some(line) =>
+                candidates
+                    .iter()
+                    .filter(|m| m.obfuscated_lines.is_some_and(|(s, e)| s <= line && line <= e))
+                    .collect(),
+            None => Vec::new(),
+        };
+        if matched.is_empty() {
+            matched = candidates
+                .iter()
+                .filter(|m| m.obfuscated_lines.is_none())
+                .collect();
+        }
+        if matched.is_empty() {
+            // ambiguous without a line, keep the first candidate
+            matched = candidates.iter().take(1).collect();
+        }
+        if matched.is_empty() {
+            let original = class_mapping.original.clone();
+            return vec![RetracedFrame {
+                file: self.source_file(&original),
+                class: original,
+                method: method.to_string(),
+                line,
+            }];
+        }
+
+        matched
+            .iter()
+            .map(|m| {
+                let original = m.original_class
+                    .clone()
+                    .unwrap_or_else(|| class_mapping.original.clone());
+                RetracedFrame {
+                    file: self.source_file(&original),
+                    class: original,
+                    method: m.original_name.clone(),
+                    line: original_line(m, line),
+                }
+            })
+            .collect()
+    }
+
+    fn source_file(&self, original_class: &str) -> String {
+        let recorded = self.classes
+            .values()
+            .find(|c| c.original == original_class)
+            .and_then(|c| c.source_file.clone());
+        if let Some(file) = recorded {
+            return file;
+        }
+        let simple = original_class.rsplit('.').next().unwrap_or(original_class);
+        let outer = simple.split('$').next().unwrap_or(simple);
+        format!("{}.java", outer)
+    }
+
+    /// Retraces `at a.b.c(Unknown Source:12)` frames and obfuscated class names in a message.
+    /// Returns None if nothing changed.
+    pub fn retrace(&self, text: &str) -> Option<String> {
+        let retraced = match self.retrace_stack_line(text) {
+            Some(retraced) => retraced,
+            None => self.retrace_class_names(text),
+        };
+        if retraced == text {
+            None
+        } else {
+            Some(retraced)
+        }
+    }
+
+    fn retrace_stack_line(&self, text: &str) -> Option<String> {
+        let at = text.find("at ")?;
+        // a frame is on a line of its own, "failed at a.b(c)" is a sentence
+        let indent = &text[..at];
+        if !indent.chars().all(char::is_whitespace) {
+            return None;
+        }
+        let frame = &text[at + 3..];
+        let open = frame.find('(')?;
+        let close = frame.rfind(')')?;
+        if close < open {
+            return None;
+        }
+        let name = &frame[..open];
+        let is_name_char = |c: char| c.is_alphanumeric() || "._$<>-".contains(c);
+        if !name.chars().all(is_name_char) {
+            return None;
+        }
+        let (class, method) = name.rsplit_once('.')?;
+        let source = &frame[open + 1..close];
+        let line = source
+            .rsplit_once(':')
+            .and_then(|(_, line)| line.parse().ok());
+
+        let frames = self.retrace_frame(class, method, line);
+        if frames.is_empty() {
+            return None;
+        }
+        let lines: Vec<String> = frames
+            .iter()
+            .map(|f| {
+                let location = match f.line {
+                    Some(line) => format!("{}:{}", f.file, line),
+                    None => f.file.clone(),
+                };
+                format!("{}at {}.{}({})", indent, f.class, f.method, location)
+            })
+            .collect();
+        Some(lines.join("\n") + &frame[close + 1..])
+    }
+
+    fn retrace_class_names(&self, text: &str) -> String {
+        let mut result = String::with_capacity(text.len());
+        let mut token_start = None;
+        for (i, c) in text.char_indices() {
+            if c.is_alphanumeric() || c == '_' || c == '$' || c == '.' {
+                if token_start.is_none() {
+                    token_start = Some(i);
+                }
+                continue;
+            }
+            if let Some(start) = token_start.take() {
+                self.retrace_token(&text[start..i], &mut result);
+            }
+            result.push(c);
+        }
+        if let Some(start) = token_start {
+            self.retrace_token(&text[start..], &mut result);
+        }
+        result
+    }
+
+    fn retrace_token(&self, token: &str, result: &mut String) {
+        // a sentence may end right after the class name
+        let trimmed = token.trim_end_matches('.');
+        let class = self.classes.get(trimmed);
+        match class {
+            // bare names like "a" are too likely to be ordinary words
+            Some(class) if trimmed.contains('.') => {
+                result.push_str(&class.original);
+                result.push_str(&token[trimmed.len()..]);
+            }
+            _ => result.push_str(token),
+        }
+    }
+}
+
+// [startline:endline:]type name(args)[:origstart[:origend]]
+fn parse_method(signature: &str) -> Option<MethodMapping> {
+    let open = signature.find('(')?;
+    let close = signature.rfind(')')?;
+
+    let head = &signature[..open];
+    let mut head_parts = head.split(':');
+    let (obfuscated_lines, declaration) = match (head_parts.next(), head_parts.next()) {
+        (Some(start), Some(end)) => {
+            let declaration = head_parts.next()?;
+            (Some((start.trim().parse().ok()?, end.trim().parse().ok()?)), declaration)
+        }
+        _ => (None, head),
+    };
+    let name = declaration.trim().rsplit(' ').next()?;
+    let (original_class, original_name) = match name.rsplit_once('.') {
+        Some((class, name)) => (Some(class.to_string()), name.to_string()),
+        None => (None, name.to_string()),
+    };
+
+    let tail = signature[close + 1..].trim_start_matches(':');
+    let mut tail_parts = tail.split(':').filter(|p| !p.is_empty());
+    let original_start = tail_parts.next().and_then(|s| s.trim().parse().ok());
+    let original_end = tail_parts.next().and_then(|e| e.trim().parse().ok());
+    let original_lines = original_start.map(|start| (start, original_end));
+
+    Some(MethodMapping {
+        obfuscated_lines,
+        original_class,
+        original_name,
+        original_lines,
+    })
+}
+
+fn original_line(method: &MethodMapping, line: Option<u32>) -> Option<u32> {
+    let line = line?;
+    match (method.obfuscated_lines, method.original_lines) {
+        (Some((start, _)), Some((original_start, Some(_)))) => {
+            Some(original_start + line.saturating_sub(start))
+        }
+        (_, Some((original_start, None))) => Some(original_start),
+        _ => Some(line),
+    }
+}
+
+fn json_string(json: &str, key: &str) -> Option<String> {
+    let key = format!("\"{}\"", key);
+    let rest = &json[json.find(&key)? + key.len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+    Some(rest[..rest.find('"')?].to_string())
+}
+
+/// The mapping of one package, applied only to lines logged by its processes.
+#[derive(Clone)]
+pub struct PackageMapping {
+    pub package: String,
+    pub path: String,
+    pub pids: Vec<u32>,
+    pub mapping: Arc<Mapping>,
+}
+
+#[derive(Clone, Default)]
+pub struct Retracer {
+    pub packages: Vec<PackageMapping>,
+}
+
+impl Retracer {
+    pub fn add(&mut self, package: PackageMapping) {
+        self.packages.retain(|p| p.package != package.package);
+        self.packages.push(package);
+    }
+
+    pub fn remove(&mut self, package: &str) {
+        self.packages.retain(|p| p.package != package);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    /// `pid` picks the package mapping; packages with unknown pids apply to every line.
+    pub fn retrace(&self, pid: Option<u32>, text: &str) -> Option<String> {
+        self.packages
+            .iter()
+            .filter(|p| p.pids.is_empty() || pid.is_some_and(|pid| p.pids.contains(&pid)))
+            .find_map(|p| p.mapping.retrace(text))
+    }
+}
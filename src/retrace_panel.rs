@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use adbx::retrace::{ Mapping, PackageMapping, Retracer };
+
+pub struct RetracePanel {
+    pub open: bool,
+    package: String,
+    mapping_path: String,
+    error: Option<String>,
+}
+
+impl RetracePanel {
+    pub fn new() -> Self {
+        RetracePanel {
+            open: false,
+            package: String::new(),
+            mapping_path: String::new(),
+            error: None,
+        }
+    }
+
+    fn pids(&mut self, adb_path: &str, device: &str, package: &str) -> Vec<u32> {
+        match adbx::get_pids(adb_path, device, package) {
+            Ok(pids) => pids,
+            Err(err) => {
+                let args = ["shell", "pidof", package];
+                let command = adbx::command_line(adb_path, Some(device), &args);
+                ::log::error!("{}: {}", command, err);
+                self.error = Some(format!("> {}: {}", command, err));
+                Vec::new()
+            }
+        }
+    }
+
+    /// returns true when the set of mappings changed
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        adb_path: &str,
+        device: Option<&str>,
+        retracer: &mut Arc<Retracer>
+    ) -> bool {
+        let mut changed = false;
+        let mut open = self.open;
+        egui::Window
+            ::new("Retrace")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid
+                    ::new("retrace_grid")
+                    .spacing(egui::vec2(10.0, 10.0))
+                    .show(ui, |ui| {
+                        ui.label("package:");
+                        ui.text_edit_singleline(&mut self.package);
+                        ui.end_row();
+                        ui.label("mapping.txt:");
+                        ui.text_edit_singleline(&mut self.mapping_path);
+                        ui.end_row();
+                    });
+                if ui.button("Load Mapping").clicked() {
+                    let path = self.mapping_path.trim().trim_matches('"').to_string();
+                    let package = self.package.trim().to_string();
+                    self.error = None;
+                    match Mapping::load(&path) {
+                        Ok(mapping) => {
+                            let pids = match device {
+                                Some(device) if !package.is_empty() => {
+                                    self.pids(adb_path, device, &package)
+                                }
+                                _ => Vec::new(),
+                            };
+                            let classes = mapping.class_count();
+                            ::log::info!("load mapping {}: {} classes", path, classes);
+                            Arc::make_mut(retracer).add(PackageMapping {
+                                package,
+                                path,
+                                pids,
+                                mapping: Arc::new(mapping),
+                            });
+                            changed = true;
+                        }
+                        Err(err) => {
+                            ::log::error!("read mapping {}: {}", path, err);
+                            self.error = Some(format!("read {}: {}", path, err));
+                        }
+                    }
+                }
+                if let Some(error) = self.error.as_ref() {
+                    ui.colored_label(egui::Color32::LIGHT_RED, error);
+                }
+                ui.separator();
+
+                let mut removed = None;
+                let mut refresh = false;
+                egui::Grid
+                    ::new("retrace_packages")
+                    .striped(true)
+                    .spacing(egui::vec2(10.0, 4.0))
+                    .show(ui, |ui| {
+                        for package in retracer.packages.iter() {
+                            ui.label(&package.package);
+                            ui.label(&package.path);
+                            if package.pids.is_empty() {
+                                ui.label("all processes");
+                            } else {
+                                ui.label(format!("pids {:?}", package.pids));
+                            }
+                            if ui.button("Remove").clicked() {
+                                removed = Some(package.package.clone());
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if !retracer.is_empty() && device.is_some() {
+                    refresh = ui.button("Refresh Pids").clicked();
+                }
+
+                if let Some(package) = removed {
+                    Arc::make_mut(retracer).remove(&package);
+                    changed = true;
+                }
+                if let (true, Some(device)) = (refresh, device) {
+                    for package in Arc::make_mut(retracer).packages.iter_mut() {
+                        if !package.package.is_empty() {
+                            package.pids = self.pids(adb_path, device, &package.package);
+                        }
+                    }
+                    changed = true;
+                }
+            });
+        self.open = open;
+        changed
+    }
+}
@@ -0,0 +1,368 @@
+use std::path::{ Path, PathBuf };
+use std::thread::JoinHandle;
+
+use adbx::capture::{ Marker, MarkerKind, ScreenRecording };
+use adbx::files::{ self, Transfer };
+use adbx::session::{ self, SessionRecording };
+use adbx::AdbError;
+
+use crate::logcat_worker::LogcatWorker;
+use crate::panel_status::PanelStatus;
+
+/// screenrecord refuses longer recordings
+const TIME_LIMIT_SECS: u32 = 180;
+
+struct Active {
+    recording: ScreenRecording,
+    local: PathBuf,
+    started_at: u64,
+    // None once the log was cleared, the marker row went with it
+    start_line: Option<usize>,
+}
+
+// screenrecord finishing the mp4, pulled once it exited
+struct Stopping {
+    device: String,
+    remote: String,
+    local: PathBuf,
+    handle: JoinHandle<Result<(), AdbError>>,
+    // None when the log it points into was cleared meanwhile
+    info: Option<SessionRecording>,
+}
+
+// the mp4 on its way to the host, added to the log once it is there
+struct Pulling {
+    device: String,
+    transfer: Transfer,
+    // None when the log it points into was cleared meanwhile
+    info: Option<SessionRecording>,
+}
+
+/// Records the screen of the device whose logcat is shown, with marker rows
+/// at the start and the end so the video lines up with the log.
+pub struct ScreenRecorder {
+    pub open: bool,
+    active: Option<Active>,
+    stopping: Vec<Stopping>,
+    pulling: Vec<Pulling>,
+    // position in the video to find in the log, e.g. `1:05.5`
+    offset: String,
+    status: PanelStatus,
+}
+
+impl ScreenRecorder {
+    pub fn new() -> Self {
+        ScreenRecorder {
+            open: false,
+            active: None,
+            stopping: Vec::new(),
+            pulling: Vec::new(),
+            offset: String::new(),
+            status: PanelStatus::default(),
+        }
+    }
+
+    fn report(&mut self, command: String, message: String) {
+        self.status.report(command, message);
+        self.open = true;
+    }
+
+    /// The log was cleared or replaced, the marker rows kept so far are gone.
+    pub fn forget_lines(&mut self) {
+        if let Some(active) = self.active.as_mut() {
+            active.start_line = None;
+        }
+        for stopping in self.stopping.iter_mut() {
+            stopping.info = None;
+        }
+        for pulling in self.pulling.iter_mut() {
+            pulling.info = None;
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub fn start(&mut self, adb_path: &str, worker: &mut LogcatWorker, dir: &str) {
+        if self.active.is_some() {
+            return;
+        }
+        self.open = true;
+        self.status.clear_error();
+        let device = worker.device_name().to_string();
+        let started_at = session::unix_millis(std::time::SystemTime::now());
+        let stamp = session::file_stamp(started_at);
+        let remote = format!("/sdcard/adbx-{}.mp4", stamp);
+        let name: String = device
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        let dir = Path::new(dir.trim().trim_matches('"'));
+        let local = dir.join(format!("{}-{}.mp4", name, stamp));
+        match ScreenRecording::start(adb_path, &device, &remote, TIME_LIMIT_SECS) {
+            Ok(recording) => {
+                let path = local.to_string_lossy();
+                let marker = Marker::new(MarkerKind::RecordStart, started_at, &path);
+                let start_line = worker.insert_marker(&marker.line());
+                self.active = Some(Active {
+                    recording,
+                    local,
+                    started_at,
+                    start_line: Some(start_line),
+                });
+            }
+            Err(err) => {
+                let command = format!("screenrecord {}", remote);
+                let command = adbx::command_line(adb_path, Some(&device), &["shell", &command]);
+                self.report(command, err.to_string());
+            }
+        }
+    }
+
+    // stops screenrecord, the video is pulled once it exited
+    fn stop(&mut self, adb_path: &str, worker: Option<&mut LogcatWorker>) {
+        let Some(active) = self.active.take() else {
+            return;
+        };
+        let device = active.recording.device.clone();
+        let remote = active.recording.remote.clone();
+        let device_start = active.recording.device_start();
+        let ended_at = session::unix_millis(std::time::SystemTime::now());
+        let path = active.local.to_string_lossy().to_string();
+        let stop_line = worker.filter(|w| is_live(w, &device)).map(|worker| {
+            let marker = Marker::new(MarkerKind::RecordStop, ended_at, &path);
+            worker.insert_marker(&marker.line())
+        });
+        let info = active.start_line.map(|start_line| SessionRecording {
+            path,
+            started_at: active.started_at,
+            ended_at,
+            start_line,
+            stop_line,
+            device_start,
+        });
+        let recording = active.recording;
+        let adb_path = adb_path.to_string();
+        let handle = std::thread::spawn(move || recording.stop(&adb_path));
+        self.stopping.push(Stopping {
+            device,
+            remote,
+            local: active.local,
+            handle,
+            info,
+        });
+    }
+
+    fn poll_stops(&mut self, adb_path: &str) {
+        let mut i = 0;
+        while i < self.stopping.len() {
+            if !self.stopping[i].handle.is_finished() {
+                i += 1;
+                continue;
+            }
+            let Stopping { device, remote, local, handle, info } = self.stopping.remove(i);
+            let stopped = handle
+                .join()
+                .unwrap_or_else(|_| Err(AdbError::Command("stop thread panicked".to_string())));
+            // whatever screenrecord wrote so far is still worth pulling
+            if let Err(err) = stopped {
+                let command = format!("screenrecord {}", remote);
+                let command = adbx::command_line(adb_path, Some(&device), &["shell", &command]);
+                self.report(command, err.to_string());
+            }
+            let dir = local.parent().unwrap_or(Path::new("."));
+            if let Err(err) = std::fs::create_dir_all(dir) {
+                self.report(format!("mkdir {}", dir.display()), err.to_string());
+                continue;
+            }
+            match Transfer::pull(adb_path, &device, None, &remote, &local, None) {
+                Ok(transfer) => self.pulling.push(Pulling { device, transfer, info }),
+                Err(err) => self.report(format!("create {}", local.display()), err.to_string()),
+            }
+        }
+    }
+
+    // the device copy is deleted once the host has it
+    fn poll_pulls(&mut self, adb_path: &str, mut worker: Option<&mut LogcatWorker>) {
+        let mut i = 0;
+        while i < self.pulling.len() {
+            if !self.pulling[i].transfer.is_finished() {
+                i += 1;
+                continue;
+            }
+            let Pulling { device, transfer, info } = self.pulling.remove(i);
+            let remote = transfer.remote.clone();
+            let local = transfer.local.clone();
+            let cancelled = transfer.is_cancelled();
+            match transfer.join() {
+                Ok(()) if cancelled => {
+                    self.status.set(format!("pull of {} cancelled", remote));
+                }
+                Ok(()) => {
+                    ::log::info!("screen recording saved to {}", local.display());
+                    self.status.set(format!("saved {}", local.display()));
+                    if let Err(err) = files::remove(adb_path, &device, None, &remote) {
+                        ::log::warn!("rm {}: {}", remote, err);
+                    }
+                    let worker = worker.as_deref_mut().filter(|w| is_live(w, &device));
+                    if let Some((worker, info)) = worker.zip(info) {
+                        worker.add_recording(info);
+                    }
+                }
+                Err(err) => self.report(format!("pull {}", remote), err.to_string()),
+            }
+        }
+    }
+
+    /// `worker` is the log shown, live or replayed. Returns a line to jump to.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        adb_path: &str,
+        mut worker: Option<&mut LogcatWorker>,
+        dir: &str
+    ) -> Option<usize> {
+        // screenrecord ends by itself at the time limit
+        if self.active.as_mut().is_some_and(|active| !active.recording.is_running()) {
+            self.stop(adb_path, worker.as_deref_mut());
+        }
+        self.poll_stops(adb_path);
+        self.poll_pulls(adb_path, worker.as_deref_mut());
+        let busy = !self.stopping.is_empty() || !self.pulling.is_empty();
+        if self.active.is_some() || busy {
+            ctx.request_repaint_after(std::time::Duration::from_millis(250));
+        }
+        if !self.open {
+            return None;
+        }
+        let mut jump_to = None;
+        let mut open = self.open;
+        egui::Window
+            ::new("Screen Recording")
+            .open(&mut open)
+            .default_size(egui::vec2(480.0, 320.0))
+            .resizable(true)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    match self.active.as_ref() {
+                        Some(active) => {
+                            let now = session::unix_millis(std::time::SystemTime::now());
+                            let secs = now.saturating_sub(active.started_at) / 1000;
+                            ui.colored_label(
+                                egui::Color32::LIGHT_RED,
+                                format!("● {}:{:02} / 3:00", secs / 60, secs % 60)
+                            );
+                            if ui.button("Stop").clicked() {
+                                self.stop(adb_path, worker.as_deref_mut());
+                            }
+                        }
+                        None => {
+                            let live = worker
+                                .as_deref_mut()
+                                .filter(|w| w.session_device().is_none());
+                            match live {
+                                Some(worker) => {
+                                    let text = format!("Record {}", worker.device_name());
+                                    if ui.button(text).clicked() {
+                                        self.start(adb_path, worker, dir);
+                                    }
+                                }
+                                None => {
+                                    ui.label("Show the logcat of a device to record it");
+                                }
+                            }
+                        }
+                    }
+                });
+                for stopping in self.stopping.iter() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!("stopping {}", stopping.remote));
+                    });
+                }
+                for pulling in self.pulling.iter() {
+                    ui.horizontal(|ui| {
+                        let transfer = &pulling.transfer;
+                        let text = format!("pulling {}", transfer.remote);
+                        ui.add(egui::ProgressBar::new(0.0).animate(true).text(text));
+                        if ui.button("Cancel").clicked() {
+                            transfer.cancel();
+                        }
+                    });
+                }
+                self.status.show(ui);
+                ui.separator();
+                let Some(worker) = worker.as_deref() else {
+                    return;
+                };
+                if worker.recordings().is_empty() {
+                    ui.weak("No recording in this log");
+                    return;
+                }
+                ui.horizontal(|ui| {
+                    ui.label("video time:");
+                    ui.add(
+                        egui::TextEdit
+                            ::singleline(&mut self.offset)
+                            .hint_text("1:05.5")
+                            .desired_width(80.0)
+                    );
+                });
+                let offset = parse_offset(&self.offset);
+                egui::Grid
+                    ::new("screen_recordings")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, recording) in worker.recordings().iter().enumerate() {
+                            let name = Path::new(&recording.path)
+                                .file_name()
+                                .map(|name| name.to_string_lossy().to_string())
+                                .unwrap_or_else(|| recording.path.clone());
+                            let millis = recording.ended_at.saturating_sub(recording.started_at);
+                            let secs = millis / 1000;
+                            ui.label(name).on_hover_text(&recording.path);
+                            ui.label(format!("{}:{:02}", secs / 60, secs % 60));
+                            ui.horizontal(|ui| {
+                                if ui.button("Start").clicked() {
+                                    jump_to = Some(recording.start_line);
+                                }
+                                let find = egui::Button::new("Find Time");
+                                if ui.add_enabled(offset.is_some(), find).clicked() {
+                                    let line = offset.and_then(|o| worker.recording_line(i, o));
+                                    match line {
+                                        Some(line) => {
+                                            jump_to = Some(line);
+                                        }
+                                        None => {
+                                            let text = format!("nothing logged at {}", self.offset);
+                                            self.status.set(text);
+                                        }
+                                    }
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+            });
+        self.open = open;
+        jump_to
+    }
+}
+
+// the markers only go into the logcat the video was taken alongside
+fn is_live(worker: &LogcatWorker, device: &str) -> bool {
+    worker.session_device().is_none() && worker.device_name() == device
+}
+
+// `65`, `65.5`, `1:05` or `1:05.5` to milliseconds
+fn parse_offset(text: &str) -> Option<u64> {
+    let text = text.trim();
+    let (minutes, seconds) = match text.split_once(':') {
+        Some((minutes, seconds)) => (minutes.parse::<u64>().ok()?, seconds),
+        None => (0, text),
+    };
+    let seconds: f64 = seconds.parse().ok().filter(|s: &f64| *s >= 0.0)?;
+    Some(minutes * 60_000 + (seconds * 1000.0).round() as u64)
+}
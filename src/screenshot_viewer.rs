@@ -0,0 +1,237 @@
+use std::path::{ Path, PathBuf };
+use std::thread::JoinHandle;
+
+use adbx::capture::{ self, Marker, MarkerKind };
+use adbx::session;
+use adbx::AdbError;
+
+use crate::panel_status::PanelStatus;
+
+// a screencap running on its own thread, the UI keeps going meanwhile
+struct Capture {
+    device: String,
+    millis: u64,
+    handle: JoinHandle<Result<Vec<u8>, AdbError>>,
+}
+
+struct Shot {
+    path: PathBuf,
+    texture: egui::TextureHandle,
+    size: egui::Vec2,
+    /// `ColorLog::index` of the marker row
+    line: Option<usize>,
+}
+
+/// Shows the last screenshot, linked to the marker row inserted into the log.
+pub struct ScreenshotViewer {
+    pub open: bool,
+    capture: Option<Capture>,
+    shot: Option<Shot>,
+    /// 1 is one device pixel per point
+    zoom: f32,
+    fit: bool,
+    save_path: String,
+    status: PanelStatus,
+}
+
+impl ScreenshotViewer {
+    pub fn new() -> Self {
+        ScreenshotViewer {
+            open: false,
+            capture: None,
+            shot: None,
+            zoom: 1.0,
+            fit: true,
+            save_path: String::new(),
+            status: PanelStatus::default(),
+        }
+    }
+
+    fn report(&mut self, command: String, message: String) {
+        self.status.report(command, message);
+        self.open = true;
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    pub fn capture(&mut self, ctx: &egui::Context, adb_path: &str, device: &str) {
+        if self.capture.is_some() {
+            return;
+        }
+        let millis = session::unix_millis(std::time::SystemTime::now());
+        let (adb_path, serial) = (adb_path.to_string(), device.to_string());
+        let ctx = ctx.clone();
+        let handle = std::thread::spawn(move || {
+            let png = capture::capture_screenshot(&adb_path, &serial);
+            ctx.request_repaint();
+            png
+        });
+        self.capture = Some(Capture {
+            device: device.to_string(),
+            millis,
+            handle,
+        });
+    }
+
+    /// Saves a finished capture to `dir` and returns the device and the marker
+    /// to insert into its log, see `set_line`.
+    pub fn poll(
+        &mut self,
+        ctx: &egui::Context,
+        adb_path: &str,
+        dir: &str
+    ) -> Option<(String, Marker)> {
+        if !self.capture.as_ref()?.handle.is_finished() {
+            return None;
+        }
+        let capture = self.capture.take()?;
+        let args = ["exec-out", "screencap", "-p"];
+        let command = adbx::command_line(adb_path, Some(&capture.device), &args);
+        let png = match capture.handle.join() {
+            Ok(Ok(png)) => png,
+            Ok(Err(err)) => {
+                self.report(command, err.to_string());
+                return None;
+            }
+            Err(_) => {
+                self.report(command, "screencap thread panicked".to_string());
+                return None;
+            }
+        };
+        let dir = Path::new(dir.trim().trim_matches('"'));
+        let device: String = capture.device
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        let name = format!("{}-{}.png", device, session::file_stamp(capture.millis));
+        let path = dir.join(name);
+        let written = std::fs::create_dir_all(dir).and_then(|_| std::fs::write(&path, &png));
+        if let Err(err) = written {
+            self.report(format!("save {}", path.display()), err.to_string());
+            return None;
+        }
+        ::log::info!("screenshot saved to {}", path.display());
+        self.load(ctx, &path, &png, None);
+        let marker = Marker::new(MarkerKind::Screenshot, capture.millis, &path.to_string_lossy());
+        Some((capture.device, marker))
+    }
+
+    /// Links the shown screenshot to its marker row.
+    pub fn set_line(&mut self, index: usize) {
+        if let Some(shot) = self.shot.as_mut() {
+            shot.line = Some(index);
+        }
+    }
+
+    /// The log was cleared or replaced, the marker row is gone.
+    pub fn forget_line(&mut self) {
+        if let Some(shot) = self.shot.as_mut() {
+            shot.line = None;
+        }
+    }
+
+    /// Opens the screenshot a marker row points at.
+    pub fn open_file(&mut self, ctx: &egui::Context, path: &str, line: Option<usize>) {
+        match std::fs::read(path) {
+            Ok(png) => self.load(ctx, Path::new(path), &png, line),
+            Err(err) => self.report(format!("open {}", path), err.to_string()),
+        }
+    }
+
+    fn load(&mut self, ctx: &egui::Context, path: &Path, png: &[u8], line: Option<usize>) {
+        self.open = true;
+        self.status.clear_error();
+        let image = match capture::decode_png(png) {
+            Ok(image) => image,
+            Err(err) => {
+                self.report(format!("decode {}", path.display()), err.to_string());
+                return;
+            }
+        };
+        let size = [image.width, image.height];
+        let color_image = egui::ColorImage::from_rgba_unmultiplied(size, &image.rgba);
+        let texture = ctx.load_texture("screenshot", color_image, egui::TextureOptions::LINEAR);
+        self.save_path = path.display().to_string();
+        self.shot = Some(Shot {
+            path: path.to_path_buf(),
+            texture,
+            size: egui::vec2(image.width as f32, image.height as f32),
+            line,
+        });
+    }
+
+    /// Returns the marker row to jump to.
+    pub fn show(&mut self, ctx: &egui::Context) -> Option<usize> {
+        if !self.open {
+            return None;
+        }
+        let mut jump_to = None;
+        let mut open = self.open;
+        egui::Window
+            ::new("Screenshot")
+            .open(&mut open)
+            .default_size(egui::vec2(420.0, 720.0))
+            .resizable(true)
+            .show(ctx, |ui| {
+                if self.capture.is_some() {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label("Capturing");
+                    });
+                }
+                self.status.show_error(ui);
+                let Some(shot) = self.shot.as_ref() else {
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.fit, "Fit");
+                    ui.add_enabled(
+                        !self.fit,
+                        egui::Slider::new(&mut self.zoom, 0.1..=4.0).text("zoom")
+                    );
+                    if let Some(line) = shot.line {
+                        if ui.button("Show in Log").clicked() {
+                            jump_to = Some(line);
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.text_edit_singleline(&mut self.save_path);
+                    if ui.button("Save").clicked() {
+                        let to = PathBuf::from(self.save_path.trim().trim_matches('"'));
+                        if to != shot.path {
+                            if let Err(err) = std::fs::copy(&shot.path, &to) {
+                                let command = format!("save {}", to.display());
+                                self.status.report(command, err.to_string());
+                            }
+                        }
+                    }
+                });
+                ui.weak(
+                    format!("{} ({}x{})", shot.path.display(), shot.size.x, shot.size.y)
+                );
+                ui.separator();
+                let available = ui.available_size();
+                if self.fit {
+                    self.zoom = (available.x / shot.size.x).min(available.y / shot.size.y);
+                }
+                egui::ScrollArea::both().auto_shrink([false, false]).show(ui, |ui| {
+                    let sized = egui::load::SizedTexture::new(
+                        shot.texture.id(),
+                        shot.size * self.zoom
+                    );
+                    let image = ui.add(egui::Image::new(sized));
+                    // ctrl + wheel or pinch zooms
+                    let zoom_delta = ui.input(|i| i.zoom_delta());
+                    if image.hovered() && zoom_delta != 1.0 {
+                        self.fit = false;
+                        self.zoom = (self.zoom * zoom_delta).clamp(0.1, 4.0);
+                    }
+                });
+            });
+        self.open = open;
+        jump_to
+    }
+}
@@ -0,0 +1,127 @@
+use std::ops::Range;
+
+/// Selected log lines, keyed by the id a line keeps for its whole life
+/// instead of its row, so it survives filter changes and dropped lines.
+///
+/// Ranges are in id order and may span lines hidden by the filter,
+/// callers show and copy only the lines they have.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Selection {
+    // sorted, disjoint and not touching
+    ranges: Vec<Range<usize>>,
+    anchor: Option<usize>,
+    cursor: Option<usize>,
+}
+
+impl Selection {
+    pub fn new() -> Self {
+        Selection::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Number of selected ids, hidden lines included.
+    pub fn len(&self) -> usize {
+        self.ranges
+            .iter()
+            .map(|r| r.len())
+            .sum()
+    }
+
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    /// Where a shift click or shift arrow extends from.
+    pub fn anchor(&self) -> Option<usize> {
+        self.anchor
+    }
+
+    /// The line moved by the keyboard, the last one clicked otherwise.
+    pub fn cursor(&self) -> Option<usize> {
+        self.cursor
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.position(id).is_ok()
+    }
+
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+        self.anchor = None;
+        self.cursor = None;
+    }
+
+    /// A plain click, selects only `id`.
+    pub fn select(&mut self, id: usize) {
+        self.ranges.clear();
+        self.ranges.push(id..id + 1);
+        self.anchor = Some(id);
+        self.cursor = Some(id);
+    }
+
+    /// A ctrl click, adds or removes `id` and keeps the rest.
+    pub fn toggle(&mut self, id: usize) {
+        match self.position(id) {
+            Ok(i) => {
+                let range = self.ranges.remove(i);
+                let after = id + 1..range.end;
+                let before = range.start..id;
+                for part in [after, before] {
+                    if !part.is_empty() {
+                        self.ranges.insert(i, part);
+                    }
+                }
+            }
+            Err(_) => self.insert(id..id + 1),
+        }
+        self.anchor = Some(id);
+        self.cursor = Some(id);
+    }
+
+    /// A shift click or drag, selects from the anchor to `id`.
+    pub fn extend_to(&mut self, id: usize) {
+        let anchor = self.anchor.unwrap_or(id);
+        self.ranges.clear();
+        self.ranges.push(anchor.min(id)..anchor.max(id) + 1);
+        self.anchor = Some(anchor);
+        self.cursor = Some(id);
+    }
+
+    /// Select every id of `ids`, e.g. all lines that pass the filter.
+    pub fn select_all(&mut self, ids: impl IntoIterator<Item = usize>) {
+        self.clear();
+        for id in ids {
+            self.insert(id..id + 1);
+            self.anchor.get_or_insert(id);
+            self.cursor = Some(id);
+        }
+    }
+
+    fn insert(&mut self, range: Range<usize>) {
+        // ranges ending before the new one starts and are not touching it
+        let start = self.ranges.partition_point(|r| r.end < range.start);
+        // ranges starting at or before its end merge into it
+        let end = self.ranges.partition_point(|r| r.start <= range.end);
+        let mut merged = range;
+        for r in &self.ranges[start..end] {
+            merged = merged.start.min(r.start)..merged.end.max(r.end);
+        }
+        self.ranges.splice(start..end, [merged]);
+    }
+
+    // index of the range holding `id`, or where one would go
+    fn position(&self, id: usize) -> Result<usize, usize> {
+        self.ranges.binary_search_by(|r| {
+            if r.end <= id {
+                std::cmp::Ordering::Less
+            } else if r.start > id {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+    }
+}
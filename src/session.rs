@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+use std::io::{ Read, Write };
+use std::ops::Range;
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{ Deserialize, Serialize };
+
+use crate::device_info::DeviceInfo;
+
+pub const SESSION_EXTENSION: &str = "adbx";
+/// bump when the layout changes and keep reading the older versions
+pub const SESSION_VERSION: u32 = 1;
+const MAGIC: &[u8; 4] = b"ADBX";
+
+/// Properties of the device a session was captured from.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct DeviceProps {
+    pub serial: String,
+    pub model: Option<String>,
+    pub fingerprint: Option<String>,
+    pub sdk: Option<u32>,
+}
+
+impl DeviceProps {
+    pub fn from_getprop(serial: &str, props: &HashMap<String, String>) -> Self {
+        DeviceProps {
+            serial: serial.to_string(),
+            model: props.get("ro.product.model").cloned(),
+            fingerprint: props.get("ro.build.fingerprint").cloned(),
+            sdk: props.get("ro.build.version.sdk").and_then(|sdk| sdk.parse().ok()),
+        }
+    }
+}
+
+/// Byte ranges of the threadtime fields of a line.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LineFields {
+    pub date: Range<usize>,
+    pub time: Range<usize>,
+    pub pid: Range<usize>,
+    pub tid: Range<usize>,
+    pub level: Range<usize>,
+    pub tag: Range<usize>,
+    pub message: Range<usize>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SessionLine {
+    pub raw: String,
+    /// none for lines that are not in threadtime format
+    pub fields: Option<LineFields>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SessionBookmark {
+    /// index into `Session::lines`
+    pub line: usize,
+    pub note: String,
+}
+
+/// A screen recording made during a capture, linked to its marker lines.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SessionRecording {
+    /// the mp4 on the host
+    pub path: String,
+    /// unix milliseconds on the host
+    pub started_at: u64,
+    pub ended_at: u64,
+    /// index into `Session::lines` of the start marker
+    pub start_line: usize,
+    pub stop_line: Option<usize>,
+    /// `MM-DD HH:MM:SS` on the device at the start, in the time of the log lines
+    pub device_start: Option<String>,
+}
+
+impl SessionRecording {
+    /// The first line logged `offset_millis` or later into the video. Lines are
+    /// the raw lines of the capture in order, as in `Session::lines`.
+    pub fn line_at<'a>(
+        &self,
+        lines: impl IntoIterator<Item = &'a str>,
+        offset_millis: u64
+    ) -> Option<usize> {
+        let mut lines = lines.into_iter().enumerate().skip(self.start_line).peekable();
+        // without the device clock, the first line after the marker is the start
+        let start = match self.device_start.as_deref().and_then(threadtime_millis) {
+            Some(start) => start,
+            None => {
+                loop {
+                    let (_, line) = lines.peek()?;
+                    match threadtime_millis(line) {
+                        Some(time) => break time,
+                        None => lines.next(),
+                    };
+                }
+            }
+        };
+        let wanted = start + offset_millis;
+        lines
+            .filter_map(|(i, line)| Some((i, threadtime_millis(line)?)))
+            .find(|(_, time)| *time >= wanted)
+            .map(|(i, _)| i)
+    }
+}
+
+/// Milliseconds since the start of the year of a `MM-DD HH:MM:SS[.mmm]` prefix,
+/// the way threadtime lines start.
+pub fn threadtime_millis(line: &str) -> Option<u64> {
+    const DAYS_BEFORE: [u64; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    let bytes = line.as_bytes();
+    let number = |range: Range<usize>| -> Option<u64> {
+        let digits = line.get(range)?;
+        digits.bytes().all(|b| b.is_ascii_digit()).then(|| digits.parse().ok())?
+    };
+    if bytes.len() < 14 || bytes[2] != b'-' || bytes[5] != b' ' || bytes[8] != b':' {
+        return None;
+    }
+    let month = number(0..2)?.checked_sub(1).filter(|m| *m < 12)?;
+    let day = number(3..5)?.checked_sub(1)?;
+    let (hour, minute, second) = (number(6..8)?, number(9..11)?, number(12..14)?);
+    let millis = match bytes.get(14) {
+        Some(b'.') => number(15..18)?,
+        _ => 0,
+    };
+    let days = DAYS_BEFORE[month as usize] + day;
+    Some((((days * 24 + hour) * 60 + minute) * 60 + second) * 1000 + millis)
+}
+
+/// A saved logcat capture, written as `ADBX`, a little endian version and gzipped JSON.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Session {
+    pub version: u32,
+    pub device: DeviceProps,
+    /// milliseconds since the unix epoch
+    pub started_at: u64,
+    pub ended_at: u64,
+    pub filter: Option<String>,
+    pub lines: Vec<SessionLine>,
+    pub bookmarks: Vec<SessionBookmark>,
+    pub recordings: Vec<SessionRecording>,
+    /// the device as it was when the session was saved, none in older files
+    pub device_info: Option<DeviceInfo>,
+}
+
+impl Session {
+    pub fn write_to(&self, mut writer: impl Write) -> std::io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&SESSION_VERSION.to_le_bytes())?;
+        let mut encoder = GzEncoder::new(writer, flate2::Compression::default());
+        serde_json::to_writer(&mut encoder, self)?;
+        encoder.finish()?.flush()
+    }
+
+    pub fn read_from(mut reader: impl Read) -> std::io::Result<Session> {
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            return Err(invalid_data("not an adbx session".to_string()));
+        }
+        let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        if version > SESSION_VERSION {
+            let message = format!("session version {} needs a newer adbx", version);
+            return Err(invalid_data(message));
+        }
+        let mut session: Session = serde_json::from_reader(GzDecoder::new(reader))?;
+        session.version = version;
+        session.bookmarks.retain(|b| b.line < session.lines.len());
+        session.recordings.retain(|r| r.start_line < session.lines.len());
+        Ok(session)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> std::io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        self.write_to(std::io::BufWriter::new(file))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> std::io::Result<Session> {
+        let file = std::fs::File::open(path)?;
+        Session::read_from(std::io::BufReader::new(file))
+    }
+}
+
+fn invalid_data(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// Milliseconds since the unix epoch, for `Session::started_at`.
+pub fn unix_millis(time: std::time::SystemTime) -> u64 {
+    time.duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// `2024-01-02 03:04:05 UTC` for a `unix_millis` timestamp.
+pub fn format_utc(millis: u64) -> String {
+    let [year, month, day, hour, minute, second] = utc_fields(millis);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        hour,
+        minute,
+        second
+    )
+}
+
+/// `20240102-030405`, for file names.
+pub fn file_stamp(millis: u64) -> String {
+    let [year, month, day, hour, minute, second] = utc_fields(millis);
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}", year, month, day, hour, minute, second)
+}
+
+fn utc_fields(millis: u64) -> [i64; 6] {
+    let secs = (millis / 1000) as i64;
+    let (days, rest) = (secs / 86400, secs % 86400);
+    // civil_from_days, http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + (if month <= 2 { 1 } else { 0 });
+    [year, month, day, rest / 3600, (rest % 3600) / 60, rest % 60]
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use serde::{ Deserialize, Serialize };
+
+use crate::launcher_panel::LaunchOptions;
+use crate::shortcuts::Shortcuts;
+use crate::theme::LogTheme;
+
+pub const SETTINGS_KEY: &str = "adbx_settings";
+/// bump when a field changes meaning and add a step to `Settings::upgrade`
+pub const SETTINGS_VERSION: u32 = 1;
+const RECENT_FILTER_LIMIT: usize = 10;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum Theme {
+    Dark,
+    Light,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct Settings {
+    // missing in files written before versioning, which reads as 0
+    #[serde(default)]
+    pub version: u32,
+    pub adb_path: String,
+    pub filter: String,
+    pub recent_filters: Vec<String>,
+    pub column_widths: HashMap<String, f32>,
+    pub column_order: Vec<String>,
+    pub hidden_columns: Vec<String>,
+    pub theme: Theme,
+    pub log_themes: Vec<LogTheme>,
+    /// name of the entry of `log_themes` used for the log table
+    pub log_theme: String,
+    pub font_size: f32,
+    pub last_device: Option<String>,
+    pub open_tabs: Vec<String>,
+    pub session_path: String,
+    pub record_dir: String,
+    pub record_max_file_mb: u32,
+    /// 0 keeps every file
+    pub record_max_files: usize,
+    pub record_gzip: bool,
+    pub shortcuts: Shortcuts,
+    /// shell commands typed on each device, newest last
+    pub shell_history: HashMap<String, Vec<String>>,
+    /// host directory the file explorer pulls into
+    pub pull_dir: String,
+    pub screenshot_dir: String,
+    pub video_dir: String,
+    pub launch: LaunchOptions,
+    /// `host:port` of devices connected over the network, newest first
+    pub recent_endpoints: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SETTINGS_VERSION,
+            adb_path: adbx::adb::default_adb_path(),
+            filter: String::new(),
+            recent_filters: Vec::new(),
+            column_widths: HashMap::new(),
+            column_order: Vec::new(),
+            hidden_columns: Vec::new(),
+            theme: Theme::Dark,
+            log_themes: LogTheme::presets(),
+            log_theme: "Dark".to_string(),
+            font_size: 14.0,
+            last_device: None,
+            open_tabs: Vec::new(),
+            session_path: "session.adbx".to_string(),
+            record_dir: "recordings".to_string(),
+            record_max_file_mb: 50,
+            record_max_files: 10,
+            record_gzip: true,
+            shortcuts: Shortcuts::default(),
+            shell_history: HashMap::new(),
+            pull_dir: "pulled".to_string(),
+            screenshot_dir: "screenshots".to_string(),
+            video_dir: "videos".to_string(),
+            launch: LaunchOptions::default(),
+            recent_endpoints: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(storage: Option<&dyn eframe::Storage>) -> Self {
+        let settings = storage.and_then(|s| eframe::get_value::<Settings>(s, SETTINGS_KEY));
+        match settings {
+            Some(settings) => settings.upgrade(),
+            None => Settings::default(),
+        }
+    }
+
+    pub fn save(&self, storage: &mut dyn eframe::Storage) {
+        // written by a newer adbx, saving would drop the fields this one does not know
+        if self.is_newer() {
+            return;
+        }
+        eframe::set_value(storage, SETTINGS_KEY, self);
+    }
+
+    fn is_newer(&self) -> bool {
+        self.version > SETTINGS_VERSION
+    }
+
+    pub(crate) fn upgrade(mut self) -> Self {
+        if self.is_newer() {
+            // keep what serde could read, and the file as it is, see `save`
+            ::log::warn!(
+                "settings version {} is newer than {}, they are not saved",
+                self.version,
+                SETTINGS_VERSION
+            );
+            return self;
+        }
+        if self.version < 1 {
+            // unversioned settings could hold an empty path
+            if self.adb_path.trim().is_empty() {
+                self.adb_path = Settings::default().adb_path;
+            }
+        }
+        self.version = SETTINGS_VERSION;
+        self
+    }
+
+    pub fn push_recent_filter(&mut self, filter: &str) {
+        if filter.is_empty() {
+            return;
+        }
+        self.recent_filters.retain(|f| f != filter);
+        self.recent_filters.insert(0, filter.to_string());
+        self.recent_filters.truncate(RECENT_FILTER_LIMIT);
+    }
+
+    pub fn is_tab_open(&self, tab: &str) -> bool {
+        self.open_tabs.iter().any(|t| t == tab)
+    }
+
+    /// The selected log theme with its rules compiled.
+    pub fn current_log_theme(&self) -> LogTheme {
+        let mut theme = self.log_themes
+            .iter()
+            .find(|t| t.name == self.log_theme)
+            .cloned()
+            .unwrap_or_else(|| {
+                match self.theme {
+                    Theme::Dark => LogTheme::dark(),
+                    Theme::Light => LogTheme::light(),
+                }
+            });
+        if let Err(err) = theme.compile() {
+            ::log::warn!("theme {}: {}", theme.name, err);
+        }
+        theme
+    }
+
+    pub fn apply_theme(&self, ctx: &egui::Context) {
+        match self.theme {
+            Theme::Dark => ctx.set_visuals(egui::Visuals::dark()),
+            Theme::Light => ctx.set_visuals(egui::Visuals::light()),
+        }
+    }
+}
+
+#[derive(PartialEq)]
+pub enum SettingsChange {
+    None,
+    Theme,
+    Font,
+    Columns,
+}
+
+pub fn show_settings_window(
+    ctx: &egui::Context,
+    open: &mut bool,
+    settings: &mut Settings,
+    adb_path: &mut String
+) -> SettingsChange {
+    let mut change = SettingsChange::None;
+    egui::Window
+        ::new("Settings")
+        .open(open)
+        .show(ctx, |ui| {
+            egui::Grid
+                ::new("settings_grid")
+                .spacing(egui::vec2(10.0, 10.0))
+                .show(ui, |ui| {
+                    ui.label("adb path:");
+                    ui.text_edit_singleline(adb_path);
+                    ui.end_row();
+
+                    ui.label("theme:");
+                    ui.horizontal(|ui| {
+                        let dark = ui.radio_value(&mut settings.theme, Theme::Dark, "Dark");
+                        let light = ui.radio_value(&mut settings.theme, Theme::Light, "Light");
+                        if dark.changed() || light.changed() {
+                            change = SettingsChange::Theme;
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("font size:");
+                    let slider = egui::Slider::new(&mut settings.font_size, 8.0..=32.0);
+                    let res = ui.add(slider);
+                    // every log is laid out again, so wait for the drag to end
+                    if res.drag_released() || (res.changed() && !res.dragged()) {
+                        change = SettingsChange::Font;
+                    }
+                    ui.end_row();
+
+                    ui.label("recent filters:");
+                    ui.horizontal(|ui| {
+                        ui.label(format!("{}", settings.recent_filters.len()));
+                        if ui.button("Clear").clicked() {
+                            settings.recent_filters.clear();
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("record to:");
+                    ui.text_edit_singleline(&mut settings.record_dir);
+                    ui.end_row();
+
+                    ui.label("record rotation:");
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::DragValue
+                                ::new(&mut settings.record_max_file_mb)
+                                .clamp_range(1..=4096)
+                                .suffix(" MB")
+                        );
+                        ui.label("per file, keep");
+                        ui.add(egui::DragValue::new(&mut settings.record_max_files));
+                        ui.label("files");
+                        ui.checkbox(&mut settings.record_gzip, "gzip");
+                    });
+                    ui.end_row();
+
+                    ui.label("screenshots to:");
+                    ui.text_edit_singleline(&mut settings.screenshot_dir);
+                    ui.end_row();
+
+                    ui.label("screen recordings to:");
+                    ui.text_edit_singleline(&mut settings.video_dir);
+                    ui.end_row();
+
+                    ui.label("columns:");
+                    if ui.button("Reset").clicked() {
+                        settings.column_widths.clear();
+                        settings.column_order.clear();
+                        settings.hidden_columns.clear();
+                        change = SettingsChange::Columns;
+                    }
+                    ui.end_row();
+                });
+            ui.separator();
+            ui.weak(format!("settings version {}", settings.version));
+        });
+    change
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_unversioned_settings() {
+        let json = r#"{ "adb_path": " ", "filter": "tag:ActivityManager" }"#;
+        let settings: Settings = serde_json::from_str(json).unwrap();
+        assert_eq!(settings.version, 0);
+        let settings = settings.upgrade();
+        assert_eq!(settings.version, 1);
+        assert_eq!(settings.adb_path, Settings::default().adb_path);
+        assert_eq!(settings.filter, "tag:ActivityManager");
+
+        let json = r#"{ "version": 1, "adb_path": "/opt/sdk/platform-tools/adb" }"#;
+        let settings: Settings = serde_json::from_str(json).unwrap();
+        assert_eq!(settings.upgrade().adb_path, "/opt/sdk/platform-tools/adb");
+    }
+
+    #[test]
+    fn keeps_settings_of_a_newer_version() {
+        let json = r#"{ "version": 2, "adb_path": "", "font_size": 20.0 }"#;
+        let settings: Settings = serde_json::from_str(json).unwrap();
+        let settings = settings.upgrade();
+        assert_eq!(settings.version, 2);
+        assert!(settings.is_newer());
+        assert_eq!(settings.font_size, 20.0);
+    }
+}
@@ -0,0 +1,467 @@
+use std::collections::HashMap;
+use std::io::{ Read, Write };
+use std::process::{ Child, ChildStdin };
+use std::sync::{ Arc, Mutex };
+use std::thread::JoinHandle;
+
+use adbx::terminal::{ self, Cell, Color, Terminal };
+use adbx::AdbError;
+
+const SCROLLBACK_LINES: usize = 5000;
+const HISTORY_LIMIT: usize = 100;
+
+/// `adb shell` with a remote pty, read by two threads into a shared buffer.
+struct ShellSession {
+    device: String,
+    child: Child,
+    stdin: ChildStdin,
+    output: Arc<Mutex<Vec<u8>>>,
+    readers: Vec<JoinHandle<()>>,
+    terminal: Terminal,
+    // typed since the last enter, saved to the history
+    line: String,
+    exited: bool,
+}
+
+impl ShellSession {
+    fn start(
+        ctx: &egui::Context,
+        adb_path: &str,
+        device: &str,
+        cols: usize,
+        rows: usize
+    ) -> Result<Self, AdbError> {
+        let path = adb_path.trim().trim_matches('"');
+        // -tt asks for a pty even though our stdin is a pipe, stty sizes it to the panel
+        let command = format!("stty rows {} cols {} 2>/dev/null; exec sh", rows, cols);
+        let mut child = adbx
+            ::adb_command(adb_path, Some(device))
+            .args(["shell", "-tt", &command])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .map_err(|err| AdbError::from_spawn(path, err))?;
+        let stdin = child.stdin.take().expect("!stdin");
+        let output = Arc::new(Mutex::new(Vec::new()));
+        let mut readers = Vec::new();
+        let streams: [Box<dyn Read + Send>; 2] = [
+            Box::new(child.stdout.take().expect("!stdout")),
+            Box::new(child.stderr.take().expect("!stderr")),
+        ];
+        for mut stream in streams {
+            let output = output.clone();
+            let ctx = ctx.clone();
+            readers.push(
+                std::thread::spawn(move || {
+                    let mut buf = [0u8; 4096];
+                    while let Ok(got) = stream.read(&mut buf) {
+                        if got == 0 {
+                            break;
+                        }
+                        output.lock().expect("!lock").extend_from_slice(&buf[..got]);
+                        ctx.request_repaint();
+                    }
+                })
+            );
+        }
+        Ok(ShellSession {
+            device: device.to_string(),
+            child,
+            stdin,
+            output,
+            readers,
+            terminal: Terminal::new(cols, rows, SCROLLBACK_LINES),
+            line: String::new(),
+            exited: false,
+        })
+    }
+
+    fn send(&mut self, bytes: &[u8]) {
+        let result = self.stdin.write_all(bytes).and_then(|_| self.stdin.flush());
+        if let Err(err) = result {
+            ::log::error!("shell {}: {}", self.device, err);
+        }
+    }
+
+    // the remote pty is sized like at the start, by stty from the shell
+    fn resize(&mut self, cols: usize, rows: usize) {
+        if self.exited || (self.terminal.cols(), self.terminal.rows()) == (cols, rows) {
+            return;
+        }
+        self.terminal.resize(cols, rows);
+        self.send(format!("stty rows {} cols {}\r", rows, cols).as_bytes());
+    }
+
+    fn update(&mut self) {
+        let bytes = std::mem::take(&mut *self.output.lock().expect("!lock"));
+        if !bytes.is_empty() {
+            self.terminal.feed(&bytes);
+        }
+        if !self.exited {
+            if let Ok(Some(status)) = self.child.try_wait() {
+                ::log::info!("shell {} exited: {}", self.device, status);
+                self.exited = true;
+            }
+        }
+    }
+}
+
+impl Drop for ShellSession {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        for reader in self.readers.drain(..) {
+            let _ = reader.join();
+        }
+    }
+}
+
+/// An interactive shell on the selected device.
+pub struct ShellPanel {
+    pub open: bool,
+    session: Option<ShellSession>,
+    error: Option<String>,
+}
+
+impl ShellPanel {
+    pub fn new() -> Self {
+        ShellPanel {
+            open: false,
+            session: None,
+            error: None,
+        }
+    }
+
+    /// `history` holds the commands typed on each device, newest last.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        adb_path: &str,
+        device: Option<&str>,
+        history: &mut HashMap<String, Vec<String>>
+    ) {
+        let mut open = self.open;
+        egui::Window
+            ::new("Shell")
+            .open(&mut open)
+            .default_size(egui::vec2(800.0, 480.0))
+            .resizable(true)
+            .show(ctx, |ui| {
+                self.show_toolbar(ui, adb_path, device, history);
+                if let Some(error) = self.error.as_ref() {
+                    ui.colored_label(egui::Color32::LIGHT_RED, error);
+                }
+                ui.separator();
+                let Some(session) = self.session.as_mut() else {
+                    ui.weak("Connect to open a shell on the selected device");
+                    return;
+                };
+                session.update();
+                // not while the window edge is dragged, each size would be sent
+                if !ui.input(|i| i.pointer.any_down()) {
+                    let (cols, rows) = terminal_size(ui);
+                    session.resize(cols, rows);
+                }
+                show_terminal(ui, session, history);
+            });
+        self.open = open;
+        // the shell ends with the window
+        if !self.open {
+            self.session = None;
+        }
+    }
+
+    fn show_toolbar(
+        &mut self,
+        ui: &mut egui::Ui,
+        adb_path: &str,
+        device: Option<&str>,
+        history: &mut HashMap<String, Vec<String>>
+    ) {
+        ui.horizontal(|ui| {
+            let Some(device) = device else {
+                ui.label("No device selected");
+                return;
+            };
+            let connected = self.session.as_ref().filter(|s| !s.exited);
+            match connected {
+                Some(session) => {
+                    ui.label(format!("{} connected", session.device));
+                    let other = session.device != device;
+                    if other && ui.button(format!("Switch to {}", device)).clicked() {
+                        self.connect(ui, adb_path, device);
+                    }
+                    if ui.button("Disconnect").clicked() {
+                        self.session = None;
+                    }
+                }
+                None => {
+                    if ui.button(format!("Connect {}", device)).clicked() {
+                        self.connect(ui, adb_path, device);
+                    }
+                }
+            }
+            let Some(session) = self.session.as_mut() else {
+                return;
+            };
+            ui.menu_button("History", |ui| {
+                let commands = history.get(&session.device).map(|c| c.as_slice()).unwrap_or(&[]);
+                if commands.is_empty() {
+                    ui.label("No commands yet");
+                }
+                let mut run = None;
+                for command in commands.iter().rev() {
+                    if ui.button(command).clicked() {
+                        run = Some(command.clone());
+                        ui.close_menu();
+                    }
+                }
+                if let Some(command) = run {
+                    session.send(format!("{}\r", command).as_bytes());
+                }
+            });
+            if ui.button("Copy All").clicked() {
+                ui.ctx().copy_text(session.terminal.text());
+            }
+            if ui.button("Ctrl+C").on_hover_text("interrupt the running command").clicked() {
+                session.send(b"\x03");
+            }
+        });
+    }
+
+    fn connect(&mut self, ui: &egui::Ui, adb_path: &str, device: &str) {
+        let (cols, rows) = terminal_size(ui);
+        self.session = None;
+        let args = ["shell", "-tt"];
+        ::log::info!("> {}", adbx::command_line(adb_path, Some(device), &args));
+        match ShellSession::start(ui.ctx(), adb_path, device, cols, rows) {
+            Ok(session) => {
+                self.session = Some(session);
+                self.error = None;
+            }
+            Err(err) => {
+                let command = adbx::command_line(adb_path, Some(device), &args);
+                ::log::error!("{}: {}", command, err);
+                self.error = Some(format!("> {}: {}", command, err));
+            }
+        }
+    }
+}
+
+// size the pty to the space left in the window
+fn terminal_size(ui: &egui::Ui) -> (usize, usize) {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let (char_width, row_height) = ui.fonts(|f| {
+        (f.glyph_width(&font_id, 'M'), f.row_height(&font_id))
+    });
+    let size = ui.available_size();
+    let cols = ((size.x / char_width) as usize).clamp(20, 400);
+    let rows = ((size.y / row_height) as usize).clamp(5, 200);
+    (cols, rows)
+}
+
+fn show_terminal(
+    ui: &mut egui::Ui,
+    session: &mut ShellSession,
+    history: &mut HashMap<String, Vec<String>>
+) {
+    let font_id = egui::TextStyle::Monospace.resolve(ui.style());
+    let (char_width, row_height) = ui.fonts(|f| {
+        (f.glyph_width(&font_id, 'M'), f.row_height(&font_id))
+    });
+    let id = egui::Id::new("shell_terminal");
+    let has_focus = ui.memory(|m| m.has_focus(id));
+    if has_focus {
+        // keys typed here go to the shell, not to egui focus moves
+        ui.memory_mut(|m| {
+            m.set_focus_lock_filter(id, egui::EventFilter {
+                tab: true,
+                arrows: true,
+                escape: true,
+            })
+        });
+        let events = ui.input(|i| i.events.clone());
+        for event in events {
+            handle_event(session, history, &event);
+        }
+    }
+
+    let terminal = &session.terminal;
+    let cursor = terminal.cursor().filter(|_| has_focus);
+    let response = egui::ScrollArea
+        ::vertical()
+        .auto_shrink([false, false])
+        .stick_to_bottom(true)
+        .show_rows(ui, row_height, terminal.line_count(), |ui, range| {
+            for (index, line) in terminal.lines().enumerate().skip(range.start).take(range.len()) {
+                let job = line_job(ui, line, &font_id);
+                let (rect, _) = ui.allocate_exact_size(
+                    egui::vec2(ui.available_width(), row_height),
+                    egui::Sense::hover()
+                );
+                let galley = ui.fonts(|f| f.layout_job(job));
+                ui.painter().galley(rect.min, galley);
+                if let Some((_, col)) = cursor.filter(|(row, _)| *row == index) {
+                    let x = rect.left() + (col as f32) * char_width;
+                    let block = egui::Rect::from_min_size(
+                        egui::pos2(x, rect.top()),
+                        egui::vec2(char_width, row_height)
+                    );
+                    let color = ui.visuals().text_color().gamma_multiply(0.5);
+                    ui.painter().rect_filled(block, 0.0, color);
+                }
+            }
+        });
+    let area = ui.interact(response.inner_rect, id, egui::Sense::click());
+    if area.clicked() {
+        area.request_focus();
+    }
+    if has_focus {
+        ui.painter().rect_stroke(
+            response.inner_rect,
+            0.0,
+            ui.visuals().selection.stroke
+        );
+    }
+    if session.exited {
+        ui.weak("The shell exited, connect again to start a new one");
+    }
+}
+
+fn handle_event(
+    session: &mut ShellSession,
+    history: &mut HashMap<String, Vec<String>>,
+    event: &egui::Event
+) {
+    match event {
+        egui::Event::Text(text) => {
+            session.line.push_str(text);
+            session.send(text.as_bytes());
+        }
+        egui::Event::Paste(text) => {
+            session.line.push_str(text);
+            session.send(text.replace('\n', "\r").as_bytes());
+        }
+        egui::Event::Key { key, pressed: true, modifiers, .. } => {
+            if modifiers.ctrl && !modifiers.alt {
+                // Ctrl+V arrives as a paste too
+                if *key == egui::Key::V {
+                    return;
+                }
+                if let Some(code) = control_code(*key) {
+                    session.line.clear();
+                    session.send(&[code]);
+                }
+                return;
+            }
+            let bytes: &[u8] = match key {
+                egui::Key::Enter => {
+                    let line = std::mem::take(&mut session.line);
+                    remember(history, &session.device, line.trim());
+                    b"\r"
+                }
+                egui::Key::Backspace => {
+                    session.line.pop();
+                    b"\x7f"
+                }
+                egui::Key::Tab => b"\t",
+                egui::Key::Escape => b"\x1b",
+                egui::Key::ArrowUp => b"\x1b[A",
+                egui::Key::ArrowDown => b"\x1b[B",
+                egui::Key::ArrowRight => b"\x1b[C",
+                egui::Key::ArrowLeft => b"\x1b[D",
+                egui::Key::Home => b"\x1b[H",
+                egui::Key::End => b"\x1b[F",
+                egui::Key::Delete => b"\x1b[3~",
+                egui::Key::PageUp => b"\x1b[5~",
+                egui::Key::PageDown => b"\x1b[6~",
+                _ => {
+                    return;
+                }
+            };
+            // the line editor of the shell recalls it, keep only typed lines
+            if matches!(key, egui::Key::ArrowUp | egui::Key::ArrowDown) {
+                session.line.clear();
+            }
+            session.send(bytes);
+        }
+        _ => {}
+    }
+}
+
+// Ctrl+A is 1 up to Ctrl+Z at 26
+fn control_code(key: egui::Key) -> Option<u8> {
+    let name = key.name();
+    let letter = name.as_bytes().first().copied()?;
+    (name.len() == 1 && letter.is_ascii_uppercase()).then(|| letter - b'A' + 1)
+}
+
+fn remember(history: &mut HashMap<String, Vec<String>>, device: &str, line: &str) {
+    if line.is_empty() {
+        return;
+    }
+    let commands = history.entry(device.to_string()).or_default();
+    commands.retain(|c| c != line);
+    commands.push(line.to_string());
+    if commands.len() > HISTORY_LIMIT {
+        commands.remove(0);
+    }
+}
+
+fn line_job(ui: &egui::Ui, line: &[Cell], font_id: &egui::FontId) -> egui::text::LayoutJob {
+    let mut job = egui::text::LayoutJob::default();
+    let default_fg = ui.visuals().text_color();
+    let to_color32 = |color: Color, default: egui::Color32| {
+        match color {
+            Color::Default => default,
+            Color::Indexed(index) => {
+                let [r, g, b] = terminal::indexed_rgb(index);
+                egui::Color32::from_rgb(r, g, b)
+            }
+            Color::Rgb([r, g, b]) => egui::Color32::from_rgb(r, g, b),
+        }
+    };
+    // trailing blanks without a background are not drawn
+    let end = line
+        .iter()
+        .rposition(|cell| cell.ch != ' ' || cell.style.bg != Color::Default)
+        .map_or(0, |i| i + 1);
+    let mut start = 0;
+    while start < end {
+        let style = line[start].style;
+        let run = line[start..end]
+            .iter()
+            .take_while(|cell| cell.style == style)
+            .count();
+        let text: String = line[start..start + run]
+            .iter()
+            .map(|cell| cell.ch)
+            .collect();
+        let mut color = to_color32(style.fg, default_fg);
+        let mut background = to_color32(style.bg, egui::Color32::TRANSPARENT);
+        if style.inverse {
+            let inverted = if background == egui::Color32::TRANSPARENT {
+                ui.visuals().panel_fill
+            } else {
+                background
+            };
+            background = color;
+            color = inverted;
+        }
+        job.append(&text, 0.0, egui::TextFormat {
+            font_id: font_id.clone(),
+            color,
+            background,
+            italics: style.italic,
+            underline: if style.underline {
+                egui::Stroke::new(1.0, color)
+            } else {
+                egui::Stroke::NONE
+            },
+            ..Default::default()
+        });
+        start += run;
+    }
+    job
+}
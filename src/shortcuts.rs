@@ -0,0 +1,278 @@
+use egui::{ Key, KeyboardShortcut, Modifiers };
+use serde::{ Deserialize, Serialize };
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Copy,
+    Find,
+    FindNext,
+    FindPrevious,
+    Clear,
+    ToggleAutoscroll,
+    SelectAll,
+    ToggleBookmark,
+    NextBookmark,
+    PreviousBookmark,
+}
+
+impl Action {
+    pub const ALL: [Action; 16] = [
+        Action::Up,
+        Action::Down,
+        Action::PageUp,
+        Action::PageDown,
+        Action::Home,
+        Action::End,
+        Action::Copy,
+        Action::Find,
+        Action::FindNext,
+        Action::FindPrevious,
+        Action::Clear,
+        Action::ToggleAutoscroll,
+        Action::SelectAll,
+        Action::ToggleBookmark,
+        Action::NextBookmark,
+        Action::PreviousBookmark,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Up => "cursor up",
+            Action::Down => "cursor down",
+            Action::PageUp => "page up",
+            Action::PageDown => "page down",
+            Action::Home => "first row",
+            Action::End => "last row",
+            Action::Copy => "copy selection",
+            Action::Find => "focus find",
+            Action::FindNext => "next match",
+            Action::FindPrevious => "previous match",
+            Action::Clear => "clear logcat",
+            Action::ToggleAutoscroll => "pause autoscroll",
+            Action::SelectAll => "select all",
+            Action::ToggleBookmark => "toggle bookmark",
+            Action::NextBookmark => "next bookmark",
+            Action::PreviousBookmark => "previous bookmark",
+        }
+    }
+
+    /// cursor moves that extend the selection when shift is held too
+    pub fn extends_selection(&self) -> bool {
+        matches!(
+            self,
+            Action::Up |
+                Action::Down |
+                Action::PageUp |
+                Action::PageDown |
+                Action::Home |
+                Action::End
+        )
+    }
+
+    /// actions that still work while a text field has the keyboard focus
+    pub fn works_in_text(&self) -> bool {
+        matches!(self, Action::Find | Action::FindNext | Action::FindPrevious)
+    }
+
+    fn default_shortcut(&self) -> KeyboardShortcut {
+        let (modifiers, key) = match self {
+            Action::Up => (Modifiers::NONE, Key::ArrowUp),
+            Action::Down => (Modifiers::NONE, Key::ArrowDown),
+            Action::PageUp => (Modifiers::NONE, Key::PageUp),
+            Action::PageDown => (Modifiers::NONE, Key::PageDown),
+            Action::Home => (Modifiers::NONE, Key::Home),
+            Action::End => (Modifiers::NONE, Key::End),
+            Action::Copy => (Modifiers::COMMAND, Key::C),
+            Action::Find => (Modifiers::COMMAND, Key::F),
+            Action::FindNext => (Modifiers::NONE, Key::F3),
+            Action::FindPrevious => (Modifiers::SHIFT, Key::F3),
+            Action::Clear => (Modifiers::COMMAND, Key::L),
+            Action::ToggleAutoscroll => (Modifiers::NONE, Key::Space),
+            Action::SelectAll => (Modifiers::COMMAND, Key::A),
+            Action::ToggleBookmark => (Modifiers::COMMAND, Key::F2),
+            Action::NextBookmark => (Modifiers::NONE, Key::F2),
+            Action::PreviousBookmark => (Modifiers::SHIFT, Key::F2),
+        };
+        KeyboardShortcut::new(modifiers, key)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct Binding {
+    pub action: Action,
+    pub modifiers: Modifiers,
+    pub key: Key,
+}
+
+impl Binding {
+    pub fn shortcut(&self) -> KeyboardShortcut {
+        KeyboardShortcut::new(self.modifiers, self.key)
+    }
+}
+
+/// Key bindings of the log view, saved with the settings.
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Shortcuts {
+    pub bindings: Vec<Binding>,
+}
+
+impl Default for Shortcuts {
+    fn default() -> Self {
+        Shortcuts {
+            bindings: Action::ALL
+                .iter()
+                .map(|action| {
+                    let shortcut = action.default_shortcut();
+                    Binding {
+                        action: *action,
+                        modifiers: shortcut.modifiers,
+                        key: shortcut.key,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Shortcuts {
+    pub fn get(&self, action: Action) -> KeyboardShortcut {
+        self.bindings
+            .iter()
+            .find(|b| b.action == action)
+            .map(|b| b.shortcut())
+            .unwrap_or_else(|| action.default_shortcut())
+    }
+
+    pub fn set(&mut self, action: Action, shortcut: KeyboardShortcut) {
+        self.bindings.retain(|b| b.action != action);
+        self.bindings.push(Binding {
+            action,
+            modifiers: shortcut.modifiers,
+            key: shortcut.key,
+        });
+    }
+
+    /// The text shown for an action, e.g. "Ctrl+F2".
+    pub fn describe(&self, ctx: &egui::Context, action: Action) -> String {
+        ctx.format_shortcut(&self.get(action))
+    }
+
+    /// Consumes the key presses of every bound action, returning the actions
+    /// with whether shift was held on top of the binding.
+    pub fn pressed(&self, ctx: &egui::Context) -> Vec<(Action, bool)> {
+        let text_focused = ctx.memory(|m| m.focus().is_some());
+        let mut pressed = Vec::new();
+        ctx.input_mut(|input| {
+            for action in Action::ALL {
+                if text_focused && !action.works_in_text() {
+                    continue;
+                }
+                let shortcut = self.get(action);
+                if input.consume_shortcut(&shortcut) {
+                    pressed.push((action, false));
+                } else if action.extends_selection() && !shortcut.modifiers.shift {
+                    let extended = KeyboardShortcut::new(
+                        shortcut.modifiers | Modifiers::SHIFT,
+                        shortcut.key
+                    );
+                    if input.consume_shortcut(&extended) {
+                        pressed.push((action, true));
+                    }
+                }
+            }
+        });
+        pressed
+    }
+}
+
+/// Lists the bindings, click one and press the new keys to rebind it.
+pub struct ShortcutEditor {
+    pub open: bool,
+    capturing: Option<Action>,
+}
+
+impl ShortcutEditor {
+    pub fn new() -> Self {
+        ShortcutEditor {
+            open: false,
+            capturing: None,
+        }
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.open && self.capturing.is_some()
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, shortcuts: &mut Shortcuts) {
+        if let Some(action) = self.capturing {
+            if let Some(shortcut) = captured_shortcut(ctx) {
+                if shortcut.key != Key::Escape {
+                    shortcuts.set(action, shortcut);
+                }
+                self.capturing = None;
+            }
+        }
+
+        let mut open = self.open;
+        egui::Window
+            ::new("Shortcuts")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                egui::Grid
+                    ::new("shortcut_grid")
+                    .striped(true)
+                    .spacing(egui::vec2(10.0, 4.0))
+                    .show(ui, |ui| {
+                        for action in Action::ALL {
+                            ui.label(action.name());
+                            let text = if self.capturing == Some(action) {
+                                "press keys, Esc to cancel".to_string()
+                            } else {
+                                shortcuts.describe(ctx, action)
+                            };
+                            let capturing = self.capturing == Some(action);
+                            if ui.selectable_label(capturing, text).clicked() {
+                                self.capturing = Some(action);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                ui.separator();
+                if ui.button("Reset").clicked() {
+                    *shortcuts = Shortcuts::default();
+                    self.capturing = None;
+                }
+            });
+        self.open = open;
+        if !self.open {
+            self.capturing = None;
+        }
+    }
+}
+
+fn captured_shortcut(ctx: &egui::Context) -> Option<KeyboardShortcut> {
+    ctx.input(|input| {
+        input.events.iter().find_map(|event| {
+            match event {
+                egui::Event::Key { key, pressed: true, modifiers, .. } => {
+                    // store the portable command modifier instead of ctrl or cmd
+                    let modifiers = Modifiers {
+                        alt: modifiers.alt,
+                        shift: modifiers.shift,
+                        command: modifiers.command,
+                        ..Default::default()
+                    };
+                    Some(KeyboardShortcut::new(modifiers, *key))
+                }
+                _ => None,
+            }
+        })
+    })
+}
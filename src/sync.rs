@@ -0,0 +1,397 @@
+use std::io::{ Read, Write };
+use std::net::{ SocketAddr, TcpStream };
+
+use crate::AdbError;
+
+/// Largest DATA payload, both ways.
+pub const SYNC_DATA_MAX: usize = 64 * 1024;
+/// Longest remote path adbd accepts in a request.
+pub const PATH_MAX: usize = 1024;
+
+pub const S_IFMT: u32 = 0o170000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+pub const S_IFLNK: u32 = 0o120000;
+
+/// `stat` of a remote file. `STAT` and `LIST` only fill mode, size and mtime,
+/// the other fields come with `STA2` and `LIS2`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FileStat {
+    pub mode: u32,
+    pub size: u64,
+    /// seconds since the epoch
+    pub mtime: i64,
+    pub dev: u64,
+    pub ino: u64,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub atime: i64,
+    pub ctime: i64,
+}
+
+impl FileStat {
+    pub fn is_dir(&self) -> bool {
+        self.mode & S_IFMT == S_IFDIR
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.mode & S_IFMT == S_IFREG
+    }
+
+    pub fn is_link(&self) -> bool {
+        self.mode & S_IFMT == S_IFLNK
+    }
+
+    /// `rwx` bits without the file type, e.g. 0o644.
+    pub fn permissions(&self) -> u32 {
+        self.mode & 0o7777
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct DirEntry {
+    pub name: String,
+    pub stat: FileStat,
+}
+
+/// Client side of the `sync:` service, which adbd uses for push, pull and ls.
+///
+/// Every message is a 4 byte id followed by a little endian u32, a length or a
+/// field, e.g. `SEND` + length + `path,mode` and then `DATA` chunks and `DONE`.
+/// Works over any stream, usually one from [`SyncClient::connect`].
+pub struct SyncClient<S: Read + Write> {
+    stream: S,
+}
+
+impl SyncClient<TcpStream> {
+    /// Opens `sync:` on `device` through the local adb server.
+    pub fn connect(device: &str) -> Result<Self, AdbError> {
+        SyncClient::connect_at(crate::adb::server_address(), device)
+    }
+
+    /// Same as [`SyncClient::connect`] for a server on another port.
+    pub fn connect_at(address: SocketAddr, device: &str) -> Result<Self, AdbError> {
+        let mut stream = TcpStream::connect(address).map_err(|err| {
+            AdbError::ServerDown(format!("{}: {}", address, err))
+        })?;
+        host_request(&mut stream, &format!("host:transport:{}", device), device)?;
+        host_request(&mut stream, "sync:", device)?;
+        Ok(SyncClient::new(stream))
+    }
+}
+
+// a smart socket request, answered with OKAY or FAIL + hex length + message
+fn host_request(stream: &mut TcpStream, request: &str, device: &str) -> Result<(), AdbError> {
+    stream.write_all(format!("{:04x}{}", request.len(), request).as_bytes())?;
+    let mut status = [0u8; 4];
+    stream.read_exact(&mut status)?;
+    match &status {
+        b"OKAY" => Ok(()),
+        b"FAIL" => {
+            let mut length = [0u8; 4];
+            stream.read_exact(&mut length)?;
+            let length = std::str
+                ::from_utf8(&length)
+                .ok()
+                .and_then(|hex| usize::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| protocol_error("bad FAIL length"))?;
+            let mut message = vec![0u8; length];
+            stream.read_exact(&mut message)?;
+            Err(AdbError::from_server(Some(device), &String::from_utf8_lossy(&message)))
+        }
+        _ => Err(protocol_error(&format!("unexpected {:?}", String::from_utf8_lossy(&status)))),
+    }
+}
+
+impl<S: Read + Write> SyncClient<S> {
+    /// Speaks sync on a stream where `sync:` is already open.
+    pub fn new(stream: S) -> Self {
+        SyncClient { stream }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// The stream, e.g. to shut it down from another thread.
+    pub fn stream(&self) -> &S {
+        &self.stream
+    }
+
+    /// `STAT`, a missing file has a zero mode rather than an error.
+    pub fn stat(&mut self, path: &str) -> Result<FileStat, AdbError> {
+        self.request(b"STAT", path)?;
+        self.expect_id(b"STAT")?;
+        let mode = self.read_u32()?;
+        let size = self.read_u32()?;
+        let mtime = self.read_u32()?;
+        Ok(FileStat {
+            mode,
+            size: size as u64,
+            mtime: mtime as i64,
+            ..FileStat::default()
+        })
+    }
+
+    /// `STA2`, with 64 bit sizes and times, needs the `stat_v2` feature of Android 8+.
+    pub fn stat2(&mut self, path: &str) -> Result<FileStat, AdbError> {
+        self.request(b"STA2", path)?;
+        self.expect_id(b"STA2")?;
+        let (error, stat) = self.read_stat2()?;
+        if error != 0 {
+            return Err(AdbError::Command(format!("{}: {}", path, errno_message(error))));
+        }
+        Ok(stat)
+    }
+
+    /// `LIST`, the entries of a directory including `.` and `..`.
+    pub fn list(&mut self, path: &str) -> Result<Vec<DirEntry>, AdbError> {
+        self.request(b"LIST", path)?;
+        let mut entries = Vec::new();
+        loop {
+            let id = self.read_id()?;
+            let mode = self.read_u32()?;
+            let size = self.read_u32()?;
+            let mtime = self.read_u32()?;
+            let length = self.read_u32()?;
+            match &id {
+                b"DENT" => {
+                    let name = self.read_string(length)?;
+                    let stat = FileStat {
+                        mode,
+                        size: size as u64,
+                        mtime: mtime as i64,
+                        ..FileStat::default()
+                    };
+                    entries.push(DirEntry { name, stat });
+                }
+                b"DONE" => {
+                    return Ok(entries);
+                }
+                _ => {
+                    return Err(self.unexpected(id, length));
+                }
+            }
+        }
+    }
+
+    /// `LIS2`, like `LIST` with the fields of `STA2`. Entries that cannot be
+    /// stat'ed come back with their errno set, those are skipped.
+    pub fn list2(&mut self, path: &str) -> Result<Vec<DirEntry>, AdbError> {
+        self.request(b"LIS2", path)?;
+        let mut entries = Vec::new();
+        loop {
+            let id = self.read_id()?;
+            if &id == b"FAIL" {
+                let length = self.read_u32()?;
+                return Err(self.unexpected(id, length));
+            }
+            let (error, stat) = self.read_stat2()?;
+            let length = self.read_u32()?;
+            match &id {
+                b"DNT2" => {
+                    let name = self.read_string(length)?;
+                    if error == 0 {
+                        entries.push(DirEntry { name, stat });
+                    }
+                }
+                b"DONE" => {
+                    return Ok(entries);
+                }
+                _ => {
+                    return Err(self.unexpected(id, length));
+                }
+            }
+        }
+    }
+
+    /// `SEND`, writes everything `data` yields to `path` with the given mode,
+    /// e.g. `S_IFREG | 0o644`, and modification time. Returns the bytes sent.
+    pub fn send(
+        &mut self,
+        data: &mut impl Read,
+        path: &str,
+        mode: u32,
+        mtime: u32
+    ) -> Result<u64, AdbError> {
+        // the mode is decimal text after the path
+        self.request(b"SEND", &format!("{},{}", path, mode))?;
+        let mut buf = vec![0u8; SYNC_DATA_MAX];
+        let mut sent = 0u64;
+        loop {
+            let got = read_full(data, &mut buf)?;
+            if got == 0 {
+                break;
+            }
+            self.write_header(b"DATA", got as u32)?;
+            self.stream.write_all(&buf[..got])?;
+            sent += got as u64;
+        }
+        self.write_header(b"DONE", mtime)?;
+        self.stream.flush()?;
+        let id = self.read_id()?;
+        let length = self.read_u32()?;
+        match &id {
+            b"OKAY" => Ok(sent),
+            _ => Err(self.unexpected(id, length)),
+        }
+    }
+
+    /// `RECV`, copies the file at `path` into `out`. Returns the bytes received.
+    pub fn recv(&mut self, path: &str, out: &mut impl Write) -> Result<u64, AdbError> {
+        self.request(b"RECV", path)?;
+        let mut buf = vec![0u8; SYNC_DATA_MAX];
+        let mut received = 0u64;
+        loop {
+            let id = self.read_id()?;
+            let length = self.read_u32()?;
+            match &id {
+                b"DATA" => {
+                    let length = length as usize;
+                    if length > SYNC_DATA_MAX {
+                        return Err(protocol_error(&format!("DATA of {} bytes", length)));
+                    }
+                    self.stream.read_exact(&mut buf[..length])?;
+                    out.write_all(&buf[..length])?;
+                    received += length as u64;
+                }
+                b"DONE" => {
+                    out.flush()?;
+                    return Ok(received);
+                }
+                _ => {
+                    return Err(self.unexpected(id, length));
+                }
+            }
+        }
+    }
+
+    /// `QUIT`, ends the service so adbd closes the stream.
+    pub fn quit(mut self) -> Result<(), AdbError> {
+        self.write_header(b"QUIT", 0)?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    fn request(&mut self, id: &[u8; 4], path: &str) -> Result<(), AdbError> {
+        if path.len() > PATH_MAX {
+            return Err(AdbError::Command(format!("path too long: {}", path)));
+        }
+        self.write_header(id, path.len() as u32)?;
+        self.stream.write_all(path.as_bytes())?;
+        self.stream.flush()?;
+        Ok(())
+    }
+
+    fn write_header(&mut self, id: &[u8; 4], value: u32) -> Result<(), AdbError> {
+        let mut header = [0u8; 8];
+        header[..4].copy_from_slice(id);
+        header[4..].copy_from_slice(&value.to_le_bytes());
+        self.stream.write_all(&header)?;
+        Ok(())
+    }
+
+    fn expect_id(&mut self, expected: &[u8; 4]) -> Result<(), AdbError> {
+        let id = self.read_id()?;
+        if &id == expected {
+            return Ok(());
+        }
+        let length = self.read_u32()?;
+        Err(self.unexpected(id, length))
+    }
+
+    // FAIL carries a message, anything else is a protocol error
+    fn unexpected(&mut self, id: [u8; 4], length: u32) -> AdbError {
+        if &id != b"FAIL" {
+            return protocol_error(&format!("unexpected {:?}", String::from_utf8_lossy(&id)));
+        }
+        match self.read_string(length) {
+            Ok(message) => AdbError::Command(message),
+            Err(err) => err,
+        }
+    }
+
+    // error, dev, ino, mode, nlink, uid, gid, size, atime, mtime, ctime
+    fn read_stat2(&mut self) -> Result<(u32, FileStat), AdbError> {
+        let error = self.read_u32()?;
+        let stat = FileStat {
+            dev: self.read_u64()?,
+            ino: self.read_u64()?,
+            mode: self.read_u32()?,
+            nlink: self.read_u32()?,
+            uid: self.read_u32()?,
+            gid: self.read_u32()?,
+            size: self.read_u64()?,
+            atime: self.read_u64()? as i64,
+            mtime: self.read_u64()? as i64,
+            ctime: self.read_u64()? as i64,
+        };
+        Ok((error, stat))
+    }
+
+    fn read_id(&mut self) -> Result<[u8; 4], AdbError> {
+        let mut id = [0u8; 4];
+        self.stream.read_exact(&mut id)?;
+        Ok(id)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, AdbError> {
+        let mut bytes = [0u8; 4];
+        self.stream.read_exact(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, AdbError> {
+        let mut bytes = [0u8; 8];
+        self.stream.read_exact(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn read_string(&mut self, length: u32) -> Result<String, AdbError> {
+        if length as usize > SYNC_DATA_MAX {
+            return Err(protocol_error(&format!("string of {} bytes", length)));
+        }
+        let mut bytes = vec![0u8; length as usize];
+        self.stream.read_exact(&mut bytes)?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+}
+
+// fills `buf` unless the reader ends first, so only the last DATA is short
+fn read_full(data: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match data.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(got) => {
+                filled += got;
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => {
+                return Err(err);
+            }
+        }
+    }
+    Ok(filled)
+}
+
+fn protocol_error(message: &str) -> AdbError {
+    AdbError::Command(format!("sync protocol error: {}", message))
+}
+
+// errno values of Linux, which is what adbd runs on
+fn errno_message(errno: u32) -> String {
+    let message = match errno {
+        1 => "Operation not permitted",
+        2 => "No such file or directory",
+        13 => "Permission denied",
+        20 => "Not a directory",
+        21 => "Is a directory",
+        40 => "Too many levels of symbolic links",
+        _ => {
+            return format!("errno {}", errno);
+        }
+    };
+    message.to_string()
+}
@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+pub type Rgb = [u8; 3];
+
+/// WCAG AA contrast for normal text.
+pub const MIN_CONTRAST: f64 = 4.5;
+const PALETTE_SIZE: usize = 24;
+
+/// FNV-1a over the UTF-8 bytes, so a tag keeps its colour across runs and releases.
+pub fn stable_hash(tag: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in tag.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+/// Colours for log tags, evenly spaced in hue and readable on one background.
+#[derive(Clone, Debug)]
+pub struct TagPalette {
+    background: Rgb,
+    colors: Vec<Rgb>,
+    pinned: HashMap<String, Rgb>,
+}
+
+impl TagPalette {
+    pub fn for_background(background: Rgb) -> Self {
+        // lighter text on dark backgrounds and darker text on light ones
+        let towards_white = contrast_ratio([255, 255, 255], background) >=
+            contrast_ratio([0, 0, 0], background);
+        let lightness = if towards_white { 0.78 } else { 0.5 };
+        let colors = (0..PALETTE_SIZE)
+            .map(|i| {
+                let hue = ((i as f64) / (PALETTE_SIZE as f64)) * std::f64::consts::TAU;
+                readable_color(lightness, 0.13, hue, background, towards_white)
+            })
+            .collect();
+        TagPalette {
+            background,
+            colors,
+            pinned: HashMap::new(),
+        }
+    }
+
+    pub fn background(&self) -> Rgb {
+        self.background
+    }
+
+    pub fn colors(&self) -> &[Rgb] {
+        &self.colors
+    }
+
+    /// A pinned colour is used as is, even if it has poor contrast.
+    pub fn pin(&mut self, tag: &str, color: Rgb) {
+        self.pinned.insert(tag.to_string(), color);
+    }
+
+    pub fn unpin(&mut self, tag: &str) {
+        self.pinned.remove(tag);
+    }
+
+    pub fn color(&self, tag: &str) -> Rgb {
+        if let Some(color) = self.pinned.get(tag) {
+            return *color;
+        }
+        let index = (stable_hash(tag) % (self.colors.len() as u64)) as usize;
+        self.colors[index]
+    }
+}
+
+// walk the lightness towards white or black until the colour is readable;
+// pure white or black always reaches the minimum contrast
+fn readable_color(lightness: f64, chroma: f64, hue: f64, background: Rgb, up: bool) -> Rgb {
+    let mut lightness = lightness;
+    loop {
+        let color = oklch_to_srgb(lightness, chroma, hue);
+        if contrast_ratio(color, background) >= MIN_CONTRAST {
+            return color;
+        }
+        lightness += if up { 0.02 } else { -0.02 };
+        if !(0.0..=1.0).contains(&lightness) {
+            return if up { [255, 255, 255] } else { [0, 0, 0] };
+        }
+    }
+}
+
+/// WCAG 2 contrast ratio between two sRGB colours, from 1 to 21.
+pub fn contrast_ratio(a: Rgb, b: Rgb) -> f64 {
+    let (la, lb) = (relative_luminance(a), relative_luminance(b));
+    let (light, dark) = if la > lb { (la, lb) } else { (lb, la) };
+    (light + 0.05) / (dark + 0.05)
+}
+
+pub fn relative_luminance(color: Rgb) -> f64 {
+    let [r, g, b] = color.map(|c| srgb_to_linear((c as f64) / 255.0));
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 { c / 12.92 } else { ((c + 0.055) / 1.055).powf(2.4) }
+}
+
+fn linear_to_srgb(c: f64) -> f64 {
+    if c <= 0.0031308 { c * 12.92 } else { 1.055 * c.powf(1.0 / 2.4) - 0.055 }
+}
+
+// https://bottosson.github.io/posts/oklab/, out of gamut channels are clamped
+fn oklch_to_srgb(lightness: f64, chroma: f64, hue: f64) -> Rgb {
+    let a = chroma * hue.cos();
+    let b = chroma * hue.sin();
+    let l = (lightness + 0.3963377774 * a + 0.2158037573 * b).powi(3);
+    let m = (lightness - 0.1055613458 * a - 0.0638541728 * b).powi(3);
+    let s = (lightness - 0.0894841775 * a - 1.291485548 * b).powi(3);
+    let linear = [
+        4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+        -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+        -0.0041960863 * l - 0.7034186147 * m + 1.707614701 * s,
+    ];
+    linear.map(|c| (linear_to_srgb(c.clamp(0.0, 1.0)) * 255.0).round() as u8)
+}
@@ -0,0 +1,670 @@
+use std::collections::VecDeque;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Color {
+    #[default]
+    Default,
+    /// the 16 ANSI colours, then the 6x6x6 cube and the grey ramp
+    Indexed(u8),
+    Rgb([u8; 3]),
+}
+
+/// The xterm RGB value of a 256 colour index.
+pub fn indexed_rgb(index: u8) -> [u8; 3] {
+    const ANSI: [[u8; 3]; 16] = [
+        [0x00, 0x00, 0x00],
+        [0xcd, 0x31, 0x31],
+        [0x0d, 0xbc, 0x79],
+        [0xe5, 0xe5, 0x10],
+        [0x24, 0x72, 0xc8],
+        [0xbc, 0x3f, 0xbc],
+        [0x11, 0xa8, 0xcd],
+        [0xe5, 0xe5, 0xe5],
+        [0x66, 0x66, 0x66],
+        [0xf1, 0x4c, 0x4c],
+        [0x23, 0xd1, 0x8b],
+        [0xf5, 0xf5, 0x43],
+        [0x3b, 0x8e, 0xea],
+        [0xd6, 0x70, 0xd6],
+        [0x29, 0xb8, 0xdb],
+        [0xff, 0xff, 0xff],
+    ];
+    match index {
+        0..=15 => ANSI[index as usize],
+        16..=231 => {
+            let i = index - 16;
+            let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+            [level(i / 36), level((i / 6) % 6), level(i % 6)]
+        }
+        _ => {
+            let grey = 8 + (index - 232) * 10;
+            [grey, grey, grey]
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    pub fg: Color,
+    pub bg: Color,
+    pub bold: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub ch: char,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            ch: ' ',
+            style: Style::default(),
+        }
+    }
+}
+
+enum State {
+    Ground,
+    Escape,
+    // ESC ( and friends pick a charset, the next byte is skipped
+    Charset,
+    Csi(String),
+    // until BEL or ESC \
+    Osc(bool),
+}
+
+/// A VT100 screen fed with the raw output of a shell, with scrollback.
+///
+/// Handles the escapes shells and toybox tools print: cursor moves, erasing,
+/// colours, scroll regions and the alternate screen. Every character is one cell wide.
+pub struct Terminal {
+    cols: usize,
+    rows: usize,
+    max_scrollback: usize,
+    scrollback: VecDeque<Vec<Cell>>,
+    lines: Vec<Vec<Cell>>,
+    row: usize,
+    col: usize,
+    // the last column was written, the next character wraps first
+    wrap_pending: bool,
+    saved: (usize, usize),
+    style: Style,
+    scroll_top: usize,
+    scroll_bottom: usize,
+    // main screen lines and cursor while the alternate one is shown
+    main_screen: Option<(Vec<Vec<Cell>>, usize, usize)>,
+    cursor_visible: bool,
+    state: State,
+    utf8: Vec<u8>,
+}
+
+impl Terminal {
+    pub fn new(cols: usize, rows: usize, max_scrollback: usize) -> Self {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        Terminal {
+            cols,
+            rows,
+            max_scrollback,
+            scrollback: VecDeque::new(),
+            lines: vec![vec![Cell::default(); cols]; rows],
+            row: 0,
+            col: 0,
+            wrap_pending: false,
+            saved: (0, 0),
+            style: Style::default(),
+            scroll_top: 0,
+            scroll_bottom: rows - 1,
+            main_screen: None,
+            cursor_visible: true,
+            state: State::Ground,
+            utf8: Vec::new(),
+        }
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    /// Changes the screen size, the cursor keeps its line and lines pushed
+    /// off the top go to the scrollback. The scroll region is reset.
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        if cols == self.cols && rows == self.rows {
+            return;
+        }
+        let above = (self.row + 1).saturating_sub(rows);
+        let keep = self.main_screen.is_none() && self.max_scrollback > 0;
+        for line in self.lines.drain(..above) {
+            if keep {
+                if self.scrollback.len() == self.max_scrollback {
+                    self.scrollback.pop_front();
+                }
+                self.scrollback.push_back(line);
+            }
+        }
+        self.row -= above;
+        fit_lines(&mut self.lines, cols, rows);
+        if let Some((main, row, col)) = self.main_screen.as_mut() {
+            fit_lines(main, cols, rows);
+            *row = (*row).min(rows - 1);
+            *col = (*col).min(cols - 1);
+        }
+        self.cols = cols;
+        self.rows = rows;
+        self.col = self.col.min(cols - 1);
+        self.saved = (self.saved.0.min(rows - 1), self.saved.1.min(cols - 1));
+        self.wrap_pending = false;
+        self.scroll_top = 0;
+        self.scroll_bottom = rows - 1;
+    }
+
+    /// Scrollback lines followed by the screen.
+    pub fn lines(&self) -> impl Iterator<Item = &[Cell]> {
+        self.scrollback
+            .iter()
+            .chain(self.lines.iter())
+            .map(|line| line.as_slice())
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.scrollback.len() + self.rows
+    }
+
+    /// Line of `lines()` and column of the cursor, none while hidden.
+    pub fn cursor(&self) -> Option<(usize, usize)> {
+        self.cursor_visible.then_some((self.scrollback.len() + self.row, self.col))
+    }
+
+    /// The text of line `index` of `lines()`, without trailing blanks.
+    pub fn line_text(&self, index: usize) -> String {
+        let line = if index < self.scrollback.len() {
+            self.scrollback.get(index)
+        } else {
+            self.lines.get(index - self.scrollback.len())
+        };
+        let text: String = line
+            .map(|line| line.iter().map(|cell| cell.ch).collect())
+            .unwrap_or_default();
+        text.trim_end().to_string()
+    }
+
+    /// Everything, for copying, with trailing empty lines dropped.
+    pub fn text(&self) -> String {
+        let lines: Vec<String> = (0..self.line_count()).map(|i| self.line_text(i)).collect();
+        let end = lines
+            .iter()
+            .rposition(|line| !line.is_empty())
+            .map_or(0, |i| i + 1);
+        lines[..end].join("\n")
+    }
+
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            if !self.utf8.is_empty() || (byte >= 0x80 && matches!(self.state, State::Ground)) {
+                self.feed_utf8(byte);
+                continue;
+            }
+            self.feed_byte(byte);
+        }
+    }
+
+    fn feed_utf8(&mut self, byte: u8) {
+        if !self.utf8.is_empty() && (byte & 0xc0) != 0x80 {
+            // a broken sequence, show it and start over with this byte
+            self.utf8.clear();
+            self.print(char::REPLACEMENT_CHARACTER);
+            self.feed(&[byte]);
+            return;
+        }
+        self.utf8.push(byte);
+        let expected = match self.utf8[0] {
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            0xf0..=0xf7 => 4,
+            _ => 1,
+        };
+        if self.utf8.len() < expected {
+            return;
+        }
+        let ch = std::str
+            ::from_utf8(&self.utf8)
+            .ok()
+            .and_then(|s| s.chars().next())
+            .unwrap_or(char::REPLACEMENT_CHARACTER);
+        self.utf8.clear();
+        self.print(ch);
+    }
+
+    fn feed_byte(&mut self, byte: u8) {
+        let state = std::mem::replace(&mut self.state, State::Ground);
+        self.state = match state {
+            State::Ground => {
+                self.control(byte);
+                return;
+            }
+            State::Escape => {
+                match byte {
+                    b'[' => State::Csi(String::new()),
+                    b']' => State::Osc(false),
+                    b'(' | b')' | b'*' | b'+' => State::Charset,
+                    _ => {
+                        self.escape(byte);
+                        State::Ground
+                    }
+                }
+            }
+            State::Charset => State::Ground,
+            State::Csi(mut params) => {
+                match byte {
+                    0x40..=0x7e => {
+                        self.csi(&params, byte);
+                        State::Ground
+                    }
+                    0x18 | 0x1a => State::Ground,
+                    0x1b => State::Escape,
+                    _ => {
+                        params.push(byte as char);
+                        State::Csi(params)
+                    }
+                }
+            }
+            State::Osc(escaped) => {
+                match byte {
+                    0x07 => State::Ground,
+                    b'\\' if escaped => State::Ground,
+                    _ => State::Osc(byte == 0x1b),
+                }
+            }
+        };
+    }
+
+    fn control(&mut self, byte: u8) {
+        match byte {
+            0x1b => {
+                self.state = State::Escape;
+            }
+            b'\n' | 0x0b | 0x0c => self.line_feed(),
+            b'\r' => {
+                self.col = 0;
+                self.wrap_pending = false;
+            }
+            0x08 => {
+                self.col = self.col.saturating_sub(1);
+                self.wrap_pending = false;
+            }
+            b'\t' => {
+                self.col = ((self.col / 8 + 1) * 8).min(self.cols - 1);
+            }
+            0x00..=0x1f | 0x7f => {}
+            _ => self.print(byte as char),
+        }
+    }
+
+    fn escape(&mut self, byte: u8) {
+        match byte {
+            b'7' => {
+                self.saved = (self.row, self.col);
+            }
+            b'8' => {
+                (self.row, self.col) = self.saved;
+                self.wrap_pending = false;
+            }
+            b'D' => self.line_feed(),
+            b'E' => {
+                self.col = 0;
+                self.line_feed();
+            }
+            b'M' => {
+                if self.row == self.scroll_top {
+                    self.scroll_down(1);
+                } else {
+                    self.row = self.row.saturating_sub(1);
+                }
+            }
+            b'c' => {
+                *self = Terminal::new(self.cols, self.rows, self.max_scrollback);
+            }
+            _ => {}
+        }
+    }
+
+    fn csi(&mut self, params: &str, action: u8) {
+        let private = params.starts_with('?');
+        let values: Vec<usize> = params
+            .trim_start_matches(['?', '>', '='])
+            .split(';')
+            .map(|p| p.parse().unwrap_or(0))
+            .collect();
+        let value = |i: usize, default: usize| {
+            values
+                .get(i)
+                .copied()
+                .filter(|v| *v != 0)
+                .unwrap_or(default)
+        };
+        let n = value(0, 1);
+        self.wrap_pending = false;
+        match action {
+            b'A' => {
+                self.row = self.row.saturating_sub(n).max(self.top_limit());
+            }
+            b'B' => {
+                self.row = self.row.saturating_add(n).min(self.bottom_limit());
+            }
+            b'C' => {
+                self.col = self.col.saturating_add(n).min(self.cols - 1);
+            }
+            b'D' => {
+                self.col = self.col.saturating_sub(n);
+            }
+            b'E' => {
+                self.row = self.row.saturating_add(n).min(self.bottom_limit());
+                self.col = 0;
+            }
+            b'F' => {
+                self.row = self.row.saturating_sub(n).max(self.top_limit());
+                self.col = 0;
+            }
+            b'G' | b'`' => {
+                self.col = (n - 1).min(self.cols - 1);
+            }
+            b'd' => {
+                self.row = (n - 1).min(self.rows - 1);
+            }
+            b'H' | b'f' => {
+                self.row = (value(0, 1) - 1).min(self.rows - 1);
+                self.col = (value(1, 1) - 1).min(self.cols - 1);
+            }
+            b'J' => {
+                match values[0] {
+                    0 => {
+                        self.erase_line(self.row, self.col, self.cols);
+                        for row in self.row + 1..self.rows {
+                            self.erase_line(row, 0, self.cols);
+                        }
+                    }
+                    1 => {
+                        for row in 0..self.row {
+                            self.erase_line(row, 0, self.cols);
+                        }
+                        self.erase_line(self.row, 0, self.col + 1);
+                    }
+                    2 => {
+                        for row in 0..self.rows {
+                            self.erase_line(row, 0, self.cols);
+                        }
+                    }
+                    3 => self.scrollback.clear(),
+                    _ => {}
+                }
+            }
+            b'K' => {
+                match values[0] {
+                    0 => self.erase_line(self.row, self.col, self.cols),
+                    1 => self.erase_line(self.row, 0, self.col + 1),
+                    2 => self.erase_line(self.row, 0, self.cols),
+                    _ => {}
+                }
+            }
+            b'X' => {
+                let end = self.col.saturating_add(n).min(self.cols);
+                self.erase_line(self.row, self.col, end);
+            }
+            b'P' => {
+                let blank = self.blank();
+                let line = &mut self.lines[self.row];
+                let n = n.min(self.cols - self.col);
+                line.drain(self.col..self.col + n);
+                line.extend(std::iter::repeat_n(blank, n));
+            }
+            b'@' => {
+                let blank = self.blank();
+                let line = &mut self.lines[self.row];
+                let n = n.min(self.cols - self.col);
+                line.truncate(self.cols - n);
+                line.splice(self.col..self.col, std::iter::repeat_n(blank, n));
+            }
+            // insert and delete lines scroll the region below the cursor
+            b'L' if self.in_scroll_region() => {
+                let top = std::mem::replace(&mut self.scroll_top, self.row);
+                self.scroll_down(n);
+                self.scroll_top = top;
+            }
+            b'M' if self.in_scroll_region() => {
+                let top = std::mem::replace(&mut self.scroll_top, self.row);
+                self.scroll_region_up(n, false);
+                self.scroll_top = top;
+            }
+            b'S' => self.scroll_region_up(n, true),
+            b'T' => self.scroll_down(n),
+            b'm' => self.sgr(&values),
+            b'r' => {
+                let top = value(0, 1) - 1;
+                let bottom = value(1, self.rows) - 1;
+                if top < bottom && bottom < self.rows {
+                    self.scroll_top = top;
+                    self.scroll_bottom = bottom;
+                    self.row = 0;
+                    self.col = 0;
+                }
+            }
+            b's' => {
+                self.saved = (self.row, self.col);
+            }
+            b'u' => {
+                (self.row, self.col) = self.saved;
+            }
+            b'h' | b'l' if private => {
+                let on = action == b'h';
+                for mode in values.iter() {
+                    match mode {
+                        25 => {
+                            self.cursor_visible = on;
+                        }
+                        47 | 1047 | 1049 => self.alternate_screen(on),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn sgr(&mut self, values: &[usize]) {
+        let mut i = 0;
+        while i < values.len() {
+            let style = &mut self.style;
+            match values[i] {
+                0 => {
+                    *style = Style::default();
+                }
+                1 => {
+                    style.bold = true;
+                }
+                3 => {
+                    style.italic = true;
+                }
+                4 => {
+                    style.underline = true;
+                }
+                7 => {
+                    style.inverse = true;
+                }
+                22 => {
+                    style.bold = false;
+                }
+                23 => {
+                    style.italic = false;
+                }
+                24 => {
+                    style.underline = false;
+                }
+                27 => {
+                    style.inverse = false;
+                }
+                v @ 30..=37 => {
+                    style.fg = Color::Indexed((v - 30) as u8);
+                }
+                v @ 40..=47 => {
+                    style.bg = Color::Indexed((v - 40) as u8);
+                }
+                v @ 90..=97 => {
+                    style.fg = Color::Indexed((v - 90 + 8) as u8);
+                }
+                v @ 100..=107 => {
+                    style.bg = Color::Indexed((v - 100 + 8) as u8);
+                }
+                39 => {
+                    style.fg = Color::Default;
+                }
+                49 => {
+                    style.bg = Color::Default;
+                }
+                v @ (38 | 48) => {
+                    // 38;5;n or 38;2;r;g;b
+                    let color = match values.get(i + 1) {
+                        Some(5) => {
+                            let index = values.get(i + 2).copied().unwrap_or(0);
+                            i += 2;
+                            Color::Indexed(index.min(255) as u8)
+                        }
+                        Some(2) => {
+                            let channel = |j: usize| {
+                                values
+                                    .get(i + j)
+                                    .copied()
+                                    .unwrap_or(0)
+                                    .min(255) as u8
+                            };
+                            let rgb = [channel(2), channel(3), channel(4)];
+                            i += 4;
+                            Color::Rgb(rgb)
+                        }
+                        _ => Color::Default,
+                    };
+                    if v == 38 {
+                        style.fg = color;
+                    } else {
+                        style.bg = color;
+                    }
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+    }
+
+    fn print(&mut self, ch: char) {
+        if self.wrap_pending {
+            self.col = 0;
+            self.line_feed();
+            self.wrap_pending = false;
+        }
+        self.lines[self.row][self.col] = Cell {
+            ch,
+            style: self.style,
+        };
+        if self.col + 1 < self.cols {
+            self.col += 1;
+        } else {
+            self.wrap_pending = true;
+        }
+    }
+
+    fn line_feed(&mut self) {
+        self.wrap_pending = false;
+        if self.row == self.scroll_bottom {
+            self.scroll_region_up(1, true);
+        } else if self.row + 1 < self.rows {
+            self.row += 1;
+        }
+    }
+
+    // lines leaving the top of the full main screen go to the scrollback
+    fn scroll_region_up(&mut self, n: usize, keep: bool) {
+        let n = n.min(self.scroll_bottom + 1 - self.scroll_top);
+        let keep = keep && self.scroll_top == 0 && self.main_screen.is_none();
+        for _ in 0..n {
+            let line = self.lines.remove(self.scroll_top);
+            self.lines.insert(self.scroll_bottom, vec![self.blank(); self.cols]);
+            if keep && self.max_scrollback > 0 {
+                if self.scrollback.len() == self.max_scrollback {
+                    self.scrollback.pop_front();
+                }
+                self.scrollback.push_back(line);
+            }
+        }
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        let n = n.min(self.scroll_bottom + 1 - self.scroll_top);
+        for _ in 0..n {
+            self.lines.remove(self.scroll_bottom);
+            self.lines.insert(self.scroll_top, vec![self.blank(); self.cols]);
+        }
+    }
+
+    fn alternate_screen(&mut self, on: bool) {
+        if on && self.main_screen.is_none() {
+            let blank = vec![vec![Cell::default(); self.cols]; self.rows];
+            let main = std::mem::replace(&mut self.lines, blank);
+            self.main_screen = Some((main, self.row, self.col));
+        } else if !on {
+            if let Some((main, row, col)) = self.main_screen.take() {
+                self.lines = main;
+                self.row = row;
+                self.col = col;
+            }
+        }
+    }
+
+    fn erase_line(&mut self, row: usize, from: usize, to: usize) {
+        let blank = self.blank();
+        let to = to.min(self.cols);
+        if from < to {
+            self.lines[row][from..to].fill(blank);
+        }
+    }
+
+    // erased cells keep the background colour
+    fn blank(&self) -> Cell {
+        Cell {
+            ch: ' ',
+            style: Style {
+                bg: self.style.bg,
+                ..Style::default()
+            },
+        }
+    }
+
+    fn in_scroll_region(&self) -> bool {
+        (self.scroll_top..=self.scroll_bottom).contains(&self.row)
+    }
+
+    fn top_limit(&self) -> usize {
+        if self.row >= self.scroll_top { self.scroll_top } else { 0 }
+    }
+
+    fn bottom_limit(&self) -> usize {
+        if self.row <= self.scroll_bottom { self.scroll_bottom } else { self.rows - 1 }
+    }
+}
+
+// blank cells and lines are added at the end, extra ones dropped
+fn fit_lines(lines: &mut Vec<Vec<Cell>>, cols: usize, rows: usize) {
+    lines.resize_with(rows, Vec::new);
+    for line in lines.iter_mut() {
+        line.resize(cols, Cell::default());
+    }
+}
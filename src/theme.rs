@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use egui::Color32;
+use adbx::tag_color::TagPalette;
+use regex::Regex;
+use serde::{ Deserialize, Serialize };
+
+use crate::log::RawLog;
+use crate::log_table::Column;
+
+/// Which part of a log line a highlight rule looks at.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub enum RuleField {
+    Line,
+    Tag,
+    Message,
+    Level,
+}
+
+impl RuleField {
+    pub const ALL: [RuleField; 4] = [
+        RuleField::Line,
+        RuleField::Tag,
+        RuleField::Message,
+        RuleField::Level,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RuleField::Line => "line",
+            RuleField::Tag => "tag",
+            RuleField::Message => "message",
+            RuleField::Level => "level",
+        }
+    }
+}
+
+/// e.g. "message matches /OutOfMemory/ → red background"
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct HighlightRule {
+    pub enabled: bool,
+    pub field: RuleField,
+    pub pattern: String,
+    /// plain substring match when false
+    pub is_regex: bool,
+    pub color: Option<Color32>,
+    pub background: Option<Color32>,
+    pub bold: bool,
+    pub italics: bool,
+    pub underline: bool,
+    #[serde(skip)]
+    regex: Option<Regex>,
+}
+
+impl Default for HighlightRule {
+    fn default() -> Self {
+        HighlightRule {
+            enabled: true,
+            field: RuleField::Message,
+            pattern: String::new(),
+            is_regex: false,
+            color: None,
+            background: None,
+            bold: false,
+            italics: false,
+            underline: false,
+            regex: None,
+        }
+    }
+}
+
+impl HighlightRule {
+    /// Builds the regex after the pattern changed or the rule was loaded.
+    pub fn compile(&mut self) -> Result<(), regex::Error> {
+        self.regex = None;
+        if self.is_regex && !self.pattern.is_empty() {
+            self.regex = Some(Regex::new(&self.pattern)?);
+        }
+        Ok(())
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        if !self.enabled || self.pattern.is_empty() {
+            return false;
+        }
+        match (self.is_regex, self.regex.as_ref()) {
+            (true, Some(regex)) => regex.is_match(text),
+            // an invalid regex matches nothing
+            (true, None) => false,
+            (false, _) => text.contains(&self.pattern),
+        }
+    }
+
+    fn apply(&self, format: &mut egui::TextFormat, bold: &mut bool) {
+        if let Some(color) = self.color {
+            format.color = color;
+        }
+        if let Some(background) = self.background {
+            format.background = background;
+        }
+        *bold |= self.bold;
+        format.italics |= self.italics;
+        if self.underline {
+            format.underline = egui::Stroke::new(1.0, format.color);
+        }
+    }
+}
+
+/// Colours of the log table and the user's highlight rules.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default)]
+pub struct LogTheme {
+    pub name: String,
+    pub date: Color32,
+    pub time: Color32,
+    pub pid: Color32,
+    pub tid: Color32,
+    pub verbose: Color32,
+    pub debug: Color32,
+    pub info: Color32,
+    pub warn: Color32,
+    pub error: Color32,
+    pub fatal: Color32,
+    /// lines that are not in threadtime format
+    pub plain: Color32,
+    pub retraced: Color32,
+    /// tags with a fixed colour instead of one from the palette
+    pub pinned_tags: BTreeMap<String, Color32>,
+    pub rules: Vec<HighlightRule>,
+}
+
+impl Default for LogTheme {
+    fn default() -> Self {
+        LogTheme::dark()
+    }
+}
+
+impl LogTheme {
+    pub fn dark() -> Self {
+        LogTheme {
+            name: "Dark".to_string(),
+            date: Color32::from_rgb(0x66, 0x99, 0x99),
+            time: Color32::from_rgb(0x33, 0x99, 0x99),
+            pid: Color32::from_rgb(0xcc, 0xcc, 0xcc),
+            tid: Color32::from_rgb(0x99, 0xcc, 0x99),
+            verbose: Color32::LIGHT_GRAY,
+            debug: Color32::LIGHT_BLUE,
+            info: Color32::WHITE,
+            warn: Color32::YELLOW,
+            error: Color32::LIGHT_RED,
+            fatal: Color32::from_rgb(0xff, 0x60, 0xff),
+            plain: Color32::GRAY,
+            retraced: Color32::LIGHT_GREEN,
+            pinned_tags: BTreeMap::new(),
+            rules: Vec::new(),
+        }
+    }
+
+    pub fn light() -> Self {
+        LogTheme {
+            name: "Light".to_string(),
+            date: Color32::from_rgb(0x33, 0x66, 0x66),
+            time: Color32::from_rgb(0x1a, 0x66, 0x66),
+            pid: Color32::from_rgb(0x55, 0x55, 0x55),
+            tid: Color32::from_rgb(0x33, 0x77, 0x33),
+            verbose: Color32::from_rgb(0x70, 0x70, 0x70),
+            debug: Color32::from_rgb(0x1a, 0x4d, 0xb3),
+            info: Color32::from_rgb(0x20, 0x20, 0x20),
+            warn: Color32::from_rgb(0x99, 0x66, 0x00),
+            error: Color32::from_rgb(0xc0, 0x10, 0x10),
+            fatal: Color32::from_rgb(0x99, 0x00, 0x99),
+            plain: Color32::from_rgb(0x60, 0x60, 0x60),
+            retraced: Color32::from_rgb(0x10, 0x80, 0x10),
+            pinned_tags: BTreeMap::new(),
+            rules: Vec::new(),
+        }
+    }
+
+    pub fn presets() -> Vec<LogTheme> {
+        vec![LogTheme::dark(), LogTheme::light()]
+    }
+
+    pub fn level_color(&self, level: char) -> Color32 {
+        match level {
+            'V' => self.verbose,
+            'D' => self.debug,
+            'I' => self.info,
+            'W' => self.warn,
+            'E' => self.error,
+            'F' | 'A' => self.fatal,
+            _ => self.verbose,
+        }
+    }
+
+    /// Tag colours readable on `background`, with this theme's pinned tags.
+    pub fn tag_palette(&self, background: Color32) -> TagPalette {
+        let rgb = [background.r(), background.g(), background.b()];
+        let mut palette = TagPalette::for_background(rgb);
+        for (tag, color) in self.pinned_tags.iter() {
+            palette.pin(tag, [color.r(), color.g(), color.b()]);
+        }
+        palette
+    }
+
+    /// Compiles the regexes of every rule, returning the first error.
+    pub fn compile(&mut self) -> Result<(), String> {
+        let mut result = Ok(());
+        for rule in self.rules.iter_mut() {
+            if let Err(err) = rule.compile() {
+                if result.is_ok() {
+                    result = Err(format!("/{}/: {}", rule.pattern, err));
+                }
+            }
+        }
+        result
+    }
+
+    /// Applies the matching rules to the formats of one line, in `Column::ALL`
+    /// order for parsed lines or a single format for the others. egui has no bold
+    /// text format, `bold` is set for the cells to paint bold.
+    pub fn apply_rules(
+        &self,
+        log: &RawLog,
+        formats: &mut [egui::TextFormat],
+        bold: &mut [bool]
+    ) {
+        for rule in self.rules.iter() {
+            let text = match (rule.field, log.info.as_ref()) {
+                (RuleField::Line, _) => log.origin.as_str(),
+                (RuleField::Tag, Some(info)) => &log.origin[info.tag.clone()],
+                (RuleField::Message, Some(info)) => &log.origin[info.message.clone()],
+                (RuleField::Level, Some(info)) => &log.origin[info.level.clone()],
+                // unparsed lines only have a message
+                (RuleField::Message, None) => log.origin.as_str(),
+                (_, None) => continue,
+            };
+            if !rule.matches(text) {
+                continue;
+            }
+            // tag and level rules colour their own cell, others the whole row
+            let cell = match (rule.field, log.info.is_some()) {
+                (RuleField::Tag, true) => Some(Column::Tag.index()),
+                (RuleField::Level, true) => Some(Column::Level.index()),
+                _ => None,
+            };
+            match cell.filter(|i| *i < formats.len()) {
+                Some(i) => rule.apply(&mut formats[i], &mut bold[i]),
+                None => {
+                    for (format, bold) in formats.iter_mut().zip(bold.iter_mut()) {
+                        rule.apply(format, bold);
+                    }
+                }
+            }
+        }
+    }
+}
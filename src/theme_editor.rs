@@ -0,0 +1,233 @@
+use crate::theme::{ HighlightRule, LogTheme, RuleField };
+
+pub struct ThemeEditor {
+    pub open: bool,
+    new_tag: String,
+    error: Option<String>,
+}
+
+impl ThemeEditor {
+    pub fn new() -> Self {
+        ThemeEditor {
+            open: false,
+            new_tag: String::new(),
+            error: None,
+        }
+    }
+
+    /// returns true when the selected theme or its rules changed
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        themes: &mut Vec<LogTheme>,
+        selected: &mut String
+    ) -> bool {
+        let mut changed = false;
+        let mut open = self.open;
+        egui::Window
+            ::new("Themes")
+            .open(&mut open)
+            .default_size(egui::vec2(700.0, 400.0))
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    for theme in themes.iter() {
+                        if ui.selectable_label(*selected == theme.name, &theme.name).clicked() {
+                            *selected = theme.name.clone();
+                            changed = true;
+                        }
+                    }
+                    ui.separator();
+                    if ui.button("Duplicate").clicked() {
+                        if let Some(theme) = themes.iter().find(|t| t.name == *selected) {
+                            let mut copy = theme.clone();
+                            copy.name = unique_name(themes, &theme.name);
+                            *selected = copy.name.clone();
+                            themes.push(copy);
+                            changed = true;
+                        }
+                    }
+                    if themes.len() > 1 && ui.button("Delete").clicked() {
+                        themes.retain(|t| t.name != *selected);
+                        *selected = themes[0].name.clone();
+                        changed = true;
+                    }
+                    if ui.button("Restore Presets").clicked() {
+                        for preset in LogTheme::presets() {
+                            match themes.iter_mut().find(|t| t.name == preset.name) {
+                                Some(theme) => {
+                                    *theme = preset;
+                                }
+                                None => themes.push(preset),
+                            }
+                        }
+                        changed = true;
+                    }
+                });
+                ui.separator();
+
+                let Some(theme) = themes.iter_mut().find(|t| t.name == *selected) else {
+                    ui.label("No theme selected");
+                    return;
+                };
+                ui.horizontal(|ui| {
+                    ui.label("name:");
+                    let res = ui.text_edit_singleline(&mut theme.name);
+                    if res.lost_focus() && theme.name.trim().is_empty() {
+                        theme.name = selected.clone();
+                    }
+                    // keep the selection pointing at the renamed theme
+                    *selected = theme.name.clone();
+                });
+                changed |= color_grid(ui, theme);
+                ui.separator();
+                changed |= self.pinned_tags_ui(ui, theme);
+                ui.separator();
+                changed |= self.rules_ui(ui, theme);
+            });
+        self.open = open;
+        changed
+    }
+
+    fn rules_ui(&mut self, ui: &mut egui::Ui, theme: &mut LogTheme) -> bool {
+        let mut changed = false;
+        let mut removed = None;
+        let mut moved_up = None;
+        ui.label("highlight rules, applied top to bottom:");
+        egui::Grid
+            ::new("theme_rules")
+            .striped(true)
+            .spacing(egui::vec2(8.0, 4.0))
+            .show(ui, |ui| {
+                for (i, rule) in theme.rules.iter_mut().enumerate() {
+                    changed |= ui.checkbox(&mut rule.enabled, "").changed();
+                    egui::ComboBox
+                        ::from_id_source(("rule_field", i))
+                        .selected_text(rule.field.name())
+                        .width(80.0)
+                        .show_ui(ui, |ui| {
+                            for field in RuleField::ALL {
+                                let res = ui.selectable_value(&mut rule.field, field, field.name());
+                                changed |= res.changed();
+                            }
+                        });
+                    let pattern = ui.add(
+                        egui::TextEdit::singleline(&mut rule.pattern).desired_width(160.0)
+                    );
+                    let regex = ui.checkbox(&mut rule.is_regex, "regex");
+                    if pattern.changed() || regex.changed() {
+                        changed = true;
+                    }
+                    changed |= optional_color(ui, &mut rule.color, "color");
+                    changed |= optional_color(ui, &mut rule.background, "background");
+                    changed |= ui.checkbox(&mut rule.bold, "bold").changed();
+                    changed |= ui.checkbox(&mut rule.italics, "italics").changed();
+                    changed |= ui.checkbox(&mut rule.underline, "underline").changed();
+                    if i > 0 && ui.small_button("⬆").clicked() {
+                        moved_up = Some(i);
+                    }
+                    if ui.small_button("✖").clicked() {
+                        removed = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+        if ui.button("Add Rule").clicked() {
+            theme.rules.push(HighlightRule::default());
+        }
+        if let Some(i) = moved_up {
+            theme.rules.swap(i - 1, i);
+            changed = true;
+        }
+        if let Some(i) = removed {
+            theme.rules.remove(i);
+            changed = true;
+        }
+        if changed {
+            self.error = theme.compile().err();
+        }
+        if let Some(error) = self.error.as_ref() {
+            ui.colored_label(egui::Color32::LIGHT_RED, error);
+        }
+        changed
+    }
+
+    fn pinned_tags_ui(&mut self, ui: &mut egui::Ui, theme: &mut LogTheme) -> bool {
+        let mut changed = false;
+        let mut unpinned = None;
+        ui.label("pinned tag colours:");
+        ui.horizontal_wrapped(|ui| {
+            for (tag, color) in theme.pinned_tags.iter_mut() {
+                changed |= ui.color_edit_button_srgba(color).changed();
+                ui.label(tag);
+                if ui.small_button("✖").clicked() {
+                    unpinned = Some(tag.clone());
+                }
+                ui.separator();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.new_tag);
+            let tag = self.new_tag.trim();
+            if ui.button("Pin").clicked() && !tag.is_empty() {
+                theme.pinned_tags.insert(tag.to_string(), theme.info);
+                self.new_tag.clear();
+                changed = true;
+            }
+        });
+        if let Some(tag) = unpinned {
+            theme.pinned_tags.remove(&tag);
+            changed = true;
+        }
+        changed
+    }
+}
+
+fn color_grid(ui: &mut egui::Ui, theme: &mut LogTheme) -> bool {
+    let mut changed = false;
+    egui::Grid
+        ::new("theme_colors")
+        .spacing(egui::vec2(10.0, 4.0))
+        .show(ui, |ui| {
+            let mut colors = [
+                ("date", &mut theme.date),
+                ("time", &mut theme.time),
+                ("pid", &mut theme.pid),
+                ("tid", &mut theme.tid),
+                ("plain", &mut theme.plain),
+                ("retraced", &mut theme.retraced),
+                ("verbose", &mut theme.verbose),
+                ("debug", &mut theme.debug),
+                ("info", &mut theme.info),
+                ("warn", &mut theme.warn),
+                ("error", &mut theme.error),
+                ("fatal", &mut theme.fatal),
+            ];
+            for (i, (name, color)) in colors.iter_mut().enumerate() {
+                ui.label(*name);
+                changed |= ui.color_edit_button_srgba(color).changed();
+                if i % 6 == 5 {
+                    ui.end_row();
+                }
+            }
+        });
+    changed
+}
+
+fn optional_color(ui: &mut egui::Ui, color: &mut Option<egui::Color32>, label: &str) -> bool {
+    let mut enabled = color.is_some();
+    let mut changed = ui.checkbox(&mut enabled, label).changed();
+    if changed {
+        *color = enabled.then_some(egui::Color32::LIGHT_RED);
+    }
+    if let Some(color) = color.as_mut() {
+        changed |= ui.color_edit_button_srgba(color).changed();
+    }
+    changed
+}
+
+fn unique_name(themes: &[LogTheme], base: &str) -> String {
+    (2..)
+        .map(|i| format!("{} {}", base, i))
+        .find(|name| !themes.iter().any(|t| &t.name == name))
+        .unwrap()
+}
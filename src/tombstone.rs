@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+use std::path::{ Path, PathBuf };
+
+use addr2line::gimli;
+use addr2line::object::{ self, Object };
+
+use crate::AdbError;
+
+pub const TOMBSTONE_DIR: &str = "/data/tombstones";
+
+/// A native crash dump as written by debuggerd.
+#[derive(Default)]
+pub struct Tombstone {
+    pub fingerprint: Option<String>,
+    pub abi: Option<String>,
+    pub pid: Option<u32>,
+    pub tid: Option<u32>,
+    pub thread_name: Option<String>,
+    pub process_name: Option<String>,
+    pub signal: Option<String>,
+    pub abort_message: Option<String>,
+    /// backtrace of the crashing thread
+    pub frames: Vec<BacktraceFrame>,
+}
+
+/// One `#00 pc 0000abcd /system/lib64/libfoo.so (func+12)` line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacktraceFrame {
+    pub index: usize,
+    pub pc: u64,
+    pub library: String,
+    pub function: Option<String>,
+    pub function_offset: Option<u64>,
+    pub build_id: Option<String>,
+}
+
+/// A resolved source location, innermost inline frame first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub function: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+pub fn parse_tombstone(text: &str) -> Tombstone {
+    let mut tombstone = Tombstone::default();
+    let mut in_backtrace = false;
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if in_backtrace {
+            match parse_frame(trimmed) {
+                Some(frame) => tombstone.frames.push(frame),
+                None => {
+                    // only the first backtrace belongs to the crashing thread
+                    if !tombstone.frames.is_empty() {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        if trimmed == "backtrace:" {
+            in_backtrace = true;
+        } else if let Some(value) = trimmed.strip_prefix("Build fingerprint:") {
+            tombstone.fingerprint = Some(value.trim().trim_matches('\'').to_string());
+        } else if let Some(value) = trimmed.strip_prefix("ABI:") {
+            tombstone.abi = Some(value.trim().trim_matches('\'').to_string());
+        } else if let Some(value) = trimmed.strip_prefix("Abort message:") {
+            tombstone.abort_message = Some(value.trim().trim_matches('\'').to_string());
+        } else if trimmed.starts_with("signal ") {
+            tombstone.signal = Some(trimmed.to_string());
+        } else if trimmed.starts_with("pid: ") && tombstone.pid.is_none() {
+            parse_thread_line(trimmed, &mut tombstone);
+        }
+    }
+    tombstone
+}
+
+// pid: 4312, tid: 4340, name: RenderThread  >>> com.example.crash <<<
+fn parse_thread_line(line: &str, tombstone: &mut Tombstone) {
+    let (ids, process) = match line.split_once(">>>") {
+        Some((ids, rest)) => (ids, rest.split("<<<").next()),
+        None => (line, None),
+    };
+    tombstone.process_name = process.map(|p| p.trim().to_string());
+    for part in ids.split(',') {
+        let Some((key, value)) = part.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "pid" => {
+                tombstone.pid = value.parse().ok();
+            }
+            "tid" => {
+                tombstone.tid = value.parse().ok();
+            }
+            "name" => {
+                tombstone.thread_name = Some(value.to_string());
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn parse_frame(line: &str) -> Option<BacktraceFrame> {
+    let rest = line.trim().strip_prefix('#')?;
+    let (index, rest) = rest.split_once(char::is_whitespace)?;
+    let index = index.parse().ok()?;
+    let rest = rest.trim_start().strip_prefix("pc")?.trim_start();
+    let (pc, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+    let pc = u64::from_str_radix(pc, 16).ok()?;
+
+    let rest = rest.trim_start();
+    // anonymous mappings are bracketed and may contain spaces
+    let library_end = if rest.starts_with('[') {
+        rest.find(']').map(|i| i + 1).unwrap_or(rest.len())
+    } else {
+        rest.find(" (").unwrap_or(rest.len())
+    };
+    let library = rest[..library_end].trim().to_string();
+
+    let mut frame = BacktraceFrame {
+        index,
+        pc,
+        library,
+        function: None,
+        function_offset: None,
+        build_id: None,
+    };
+    for group in paren_groups(&rest[library_end..]) {
+        if let Some(build_id) = group.strip_prefix("BuildId:") {
+            frame.build_id = Some(build_id.trim().to_string());
+        } else if group.starts_with("offset 0x") {
+            // offset of the mapping inside an apk, not useful for symbolication
+        } else {
+            match group.rsplit_once('+') {
+                Some((name, offset)) if offset.bytes().all(|b| b.is_ascii_digit()) => {
+                    frame.function = Some(name.to_string());
+                    frame.function_offset = offset.parse().ok();
+                }
+                _ => {
+                    frame.function = Some(group.to_string());
+                }
+            }
+        }
+    }
+    Some(frame)
+}
+
+// split "(a) (b(c)+1)" into top-level groups, keeping nested parentheses of C++ signatures
+fn paren_groups(text: &str) -> Vec<&str> {
+    let mut groups = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => {
+                if depth == 0 {
+                    start = i + 1;
+                }
+                depth += 1;
+            }
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    groups.push(&text[start..i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    groups
+}
+
+type DwarfContext = addr2line::Context<gimli::EndianRcSlice<gimli::RunTimeEndian>>;
+
+/// The `ABI:` of a tombstone and the folders ndk builds put its libraries in.
+const ABI_DIRS: [(&str, &[&str]); 5] = [
+    ("arm", &["armeabi-v7a", "armeabi"]),
+    ("arm64", &["arm64-v8a"]),
+    ("x86", &["x86"]),
+    ("x86_64", &["x86_64"]),
+    ("riscv64", &["riscv64"]),
+];
+
+/// Resolves frame addresses against a directory of unstripped shared libraries,
+/// like `ndk-stack -sym`.
+pub struct Symbolizer {
+    symbol_dir: PathBuf,
+    // folders of the crashed process's abi, none when the tombstone did not say
+    abi_dirs: Option<&'static [&'static str]>,
+    // by library name and build id
+    contexts: HashMap<(String, Option<String>), Option<DwarfContext>>,
+}
+
+impl Symbolizer {
+    /// `abi` is the `ABI:` of the tombstone, libraries under the folders of
+    /// another abi are skipped.
+    pub fn new(symbol_dir: impl Into<PathBuf>, abi: Option<&str>) -> Self {
+        let abi_dirs = abi.and_then(|abi| {
+            ABI_DIRS
+                .iter()
+                .find(|(name, _)| *name == abi)
+                .map(|(_, dirs)| *dirs)
+        });
+        Symbolizer {
+            symbol_dir: symbol_dir.into(),
+            abi_dirs,
+            contexts: HashMap::new(),
+        }
+    }
+
+    pub fn symbol_dir(&self) -> &Path {
+        &self.symbol_dir
+    }
+
+    pub fn symbolicate(&mut self, frame: &BacktraceFrame) -> Vec<Symbol> {
+        let name = match frame.library.rsplit('/').next() {
+            Some(name) if !name.is_empty() && !frame.library.starts_with('[') => name,
+            _ => {
+                return Vec::new();
+            }
+        };
+        let key = (name.to_string(), frame.build_id.clone());
+        if !self.contexts.contains_key(&key) {
+            let mut candidates = Vec::new();
+            find_files(&self.symbol_dir, name, &mut candidates);
+            // the first library of the right abi and build, a debug build may have no id
+            let context = candidates
+                .iter()
+                .filter(|path| self.is_abi_path(path))
+                .find_map(|path| load_context(path, frame.build_id.as_deref()));
+            self.contexts.insert(key.clone(), context);
+        }
+        let Some(context) = self.contexts.get(&key).and_then(|c| c.as_ref()) else {
+            return Vec::new();
+        };
+
+        let mut symbols = Vec::new();
+        let frames = context.find_frames(frame.pc).skip_all_loads();
+        let Ok(mut frames) = frames else {
+            return symbols;
+        };
+        while let Ok(Some(f)) = frames.next() {
+            let function = f.function
+                .as_ref()
+                .and_then(|name| name.demangle().ok())
+                .map(|name| name.to_string());
+            let (file, line) = match f.location {
+                Some(location) => (location.file.map(|f| f.to_string()), location.line),
+                None => (None, None),
+            };
+            symbols.push(Symbol { function, file, line });
+        }
+        symbols
+    }
+
+    // false under the folder of another abi, e.g. obj/local/armeabi-v7a
+    fn is_abi_path(&self, path: &Path) -> bool {
+        let Some(abi_dirs) = self.abi_dirs else {
+            return true;
+        };
+        let relative = path.strip_prefix(&self.symbol_dir).unwrap_or(path);
+        relative.components().all(|component| {
+            let component = component.as_os_str().to_string_lossy();
+            let other_abi = ABI_DIRS
+                .iter()
+                .flat_map(|(_, dirs)| dirs.iter())
+                .any(|dir| *dir == component);
+            !other_abi || abi_dirs.contains(&component.as_ref())
+        })
+    }
+}
+
+// the library in `dir` first, then in ndk build trees like obj/local/<abi>/libfoo.so
+fn find_files(dir: &Path, name: &str, found: &mut Vec<PathBuf>) {
+    let candidate = dir.join(name);
+    if candidate.is_file() {
+        found.push(candidate);
+    }
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let mut dirs: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect();
+    dirs.sort();
+    for dir in dirs {
+        find_files(&dir, name, found);
+    }
+}
+
+fn load_context(path: &Path, build_id: Option<&str>) -> Option<DwarfContext> {
+    let data = match std::fs::read(path) {
+        Ok(data) => data,
+        Err(err) => {
+            log::warn!("read symbols error: {}, {}", path.display(), err);
+            return None;
+        }
+    };
+    let file = match object::File::parse(data.as_slice()) {
+        Ok(file) => file,
+        Err(err) => {
+            log::warn!("parse elf error: {}, {}", path.display(), err);
+            return None;
+        }
+    };
+    // the tombstone prints the id as hex, a library built again has another one
+    let file_id = file.build_id().ok().flatten().map(|id| {
+        id.iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<String>()
+    });
+    if let (Some(wanted), Some(file_id)) = (build_id, file_id.as_ref()) {
+        if !wanted.eq_ignore_ascii_case(file_id) {
+            log::info!("skip {}: build id {} is not {}", path.display(), file_id, wanted);
+            return None;
+        }
+    }
+    match addr2line::Context::new(&file) {
+        Ok(context) => Some(context),
+        Err(err) => {
+            log::warn!("load dwarf error: {}, {}", path.display(), err);
+            None
+        }
+    }
+}
+
+pub fn list_tombstones(adb_path: &str, device: &str) -> Result<Vec<String>, AdbError> {
+    let output = crate::run_adb(adb_path, Some(device), &["shell", "ls", TOMBSTONE_DIR])?;
+    let output = String::from_utf8_lossy(&output);
+    Ok(
+        output
+            .split_whitespace()
+            .filter(|name| name.starts_with("tombstone_") && !name.ends_with(".pb"))
+            .map(|name| name.to_string())
+            .collect()
+    )
+}
+
+pub fn pull_tombstone(adb_path: &str, device: &str, name: &str) -> Result<String, AdbError> {
+    let path = format!("{}/{}", TOMBSTONE_DIR, name);
+    let output = crate::run_adb(adb_path, Some(device), &["exec-out", "cat", &path])?;
+    Ok(String::from_utf8_lossy(&output).to_string())
+}
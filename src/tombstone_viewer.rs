@@ -0,0 +1,326 @@
+use std::thread::JoinHandle;
+
+use adbx::tombstone::{ self, Symbol, Symbolizer, Tombstone };
+use adbx::AdbError;
+
+use crate::panel_status::PanelStatus;
+
+// an adb call on its own thread, with its command line for the error
+struct Fetch<T> {
+    command: String,
+    handle: JoinHandle<Result<T, AdbError>>,
+}
+
+pub struct TombstoneViewer {
+    pub open: bool,
+    names: Vec<String>,
+    selected: Option<String>,
+    local_path: String,
+    symbol_dir: String,
+    tombstone: Option<Tombstone>,
+    raw: String,
+    symbols: Vec<Vec<Symbol>>,
+    show_raw: bool,
+    list: Option<Fetch<Vec<String>>>,
+    pull: Option<Fetch<String>>,
+    // reading DWARF takes a while for big libraries
+    symbolicating: Option<Fetch<Vec<Vec<Symbol>>>>,
+    status: PanelStatus,
+}
+
+impl TombstoneViewer {
+    pub fn new() -> Self {
+        TombstoneViewer {
+            open: false,
+            names: Vec::new(),
+            selected: None,
+            local_path: String::new(),
+            symbol_dir: String::new(),
+            tombstone: None,
+            raw: String::new(),
+            symbols: Vec::new(),
+            show_raw: false,
+            list: None,
+            pull: None,
+            symbolicating: None,
+            status: PanelStatus::default(),
+        }
+    }
+
+    pub fn refresh(&mut self, ctx: &egui::Context, adb_path: &str, device: &str) {
+        self.status.clear_error();
+        let args = ["shell", "ls", tombstone::TOMBSTONE_DIR];
+        let command = adbx::command_line(adb_path, Some(device), &args);
+        let (adb_path, device) = (adb_path.to_string(), device.to_string());
+        let ctx = ctx.clone();
+        let handle = std::thread::spawn(move || {
+            let names = tombstone::list_tombstones(&adb_path, &device);
+            ctx.request_repaint();
+            names
+        });
+        self.list = Some(Fetch { command, handle });
+    }
+
+    /// open the viewer on a tombstone mentioned in a log line, e.g.
+    /// "Tombstone written to: /data/tombstones/tombstone_00"
+    pub fn open_from_log(
+        &mut self,
+        ctx: &egui::Context,
+        adb_path: &str,
+        device: &str,
+        line: &str
+    ) -> bool {
+        let Some(start) = line.find(tombstone::TOMBSTONE_DIR) else {
+            return false;
+        };
+        let name = line[start + tombstone::TOMBSTONE_DIR.len()..]
+            .trim_start_matches('/')
+            .split_whitespace()
+            .next()
+            .unwrap_or("");
+        if name.is_empty() {
+            return false;
+        }
+        self.open = true;
+        self.refresh(ctx, adb_path, device);
+        self.pull(ctx, adb_path, device, name);
+        true
+    }
+
+    // a pull still running is left to finish on its own, the newest one is shown
+    fn pull(&mut self, ctx: &egui::Context, adb_path: &str, device: &str, name: &str) {
+        self.selected = Some(name.to_string());
+        self.status.clear_error();
+        let path = format!("{}/{}", tombstone::TOMBSTONE_DIR, name);
+        let command = adbx::command_line(adb_path, Some(device), &["exec-out", "cat", &path]);
+        let (adb_path, device, name) = (adb_path.to_string(), device.to_string(), name.to_string());
+        let ctx = ctx.clone();
+        let handle = std::thread::spawn(move || {
+            let text = tombstone::pull_tombstone(&adb_path, &device, &name);
+            ctx.request_repaint();
+            text
+        });
+        self.pull = Some(Fetch { command, handle });
+    }
+
+    fn poll(&mut self, ctx: &egui::Context) {
+        let finished = self.list.as_ref().is_some_and(|fetch| fetch.handle.is_finished());
+        if let Some(Fetch { command, handle }) = finished.then(|| self.list.take()).flatten() {
+            match handle.join() {
+                Ok(Ok(names)) => {
+                    self.names = names;
+                }
+                Ok(Err(err)) => self.status.report(command, err.to_string()),
+                Err(_) => self.status.report(command, "list thread panicked".to_string()),
+            }
+        }
+        let finished = self.pull.as_ref().is_some_and(|fetch| fetch.handle.is_finished());
+        if let Some(Fetch { command, handle }) = finished.then(|| self.pull.take()).flatten() {
+            let text = handle
+                .join()
+                .unwrap_or_else(|_| Err(AdbError::Command("pull thread panicked".to_string())));
+            match text {
+                Ok(text) => self.load(ctx, text),
+                Err(err) => {
+                    self.tombstone = None;
+                    self.raw.clear();
+                    self.symbols.clear();
+                    self.status.report(command, err.to_string());
+                }
+            }
+        }
+        let finished = self.symbolicating
+            .as_ref()
+            .is_some_and(|fetch| fetch.handle.is_finished());
+        let symbolicated = finished.then(|| self.symbolicating.take()).flatten();
+        if let Some(Fetch { command, handle }) = symbolicated {
+            match handle.join() {
+                Ok(Ok(symbols)) => {
+                    self.symbols = symbols;
+                }
+                Ok(Err(err)) => self.status.report(command, err.to_string()),
+                Err(_) => self.status.report(command, "symbol thread panicked".to_string()),
+            }
+        }
+    }
+
+    fn load(&mut self, ctx: &egui::Context, text: String) {
+        self.tombstone = Some(tombstone::parse_tombstone(&text));
+        self.raw = text;
+        self.symbolicate(ctx);
+    }
+
+    // a run still going is left to finish on its own, the newest one is shown
+    fn symbolicate(&mut self, ctx: &egui::Context) {
+        self.symbols.clear();
+        self.symbolicating = None;
+        let Some(tombstone) = self.tombstone.as_ref() else {
+            return;
+        };
+        let dir = self.symbol_dir.trim().trim_matches('"').to_string();
+        if dir.is_empty() {
+            return;
+        }
+        let command = format!("symbolicate {}", dir);
+        let frames = tombstone.frames.clone();
+        let abi = tombstone.abi.clone();
+        let ctx = ctx.clone();
+        let handle = std::thread::spawn(move || {
+            let mut symbolizer = Symbolizer::new(dir, abi.as_deref());
+            let symbols = frames
+                .iter()
+                .map(|frame| symbolizer.symbolicate(frame))
+                .collect();
+            ctx.request_repaint();
+            Ok(symbols)
+        });
+        self.symbolicating = Some(Fetch { command, handle });
+    }
+
+    pub fn show(&mut self, ctx: &egui::Context, adb_path: &str, device: Option<&str>) {
+        self.poll(ctx);
+        let mut open = self.open;
+        egui::Window
+            ::new("Tombstones")
+            .open(&mut open)
+            .default_size(egui::vec2(900.0, 500.0))
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    if let Some(device) = device {
+                        if ui.button("Refresh").clicked() {
+                            self.refresh(ctx, adb_path, device);
+                        }
+                    }
+                    let busy = self.list.is_some() || self.pull.is_some();
+                    if busy || self.symbolicating.is_some() {
+                        ui.spinner();
+                    }
+                    ui.label("local file:");
+                    ui.text_edit_singleline(&mut self.local_path);
+                    if ui.button("Load").clicked() {
+                        let path = self.local_path.trim().trim_matches('"');
+                        match std::fs::read(path) {
+                            Ok(data) => {
+                                self.selected = None;
+                                self.load(ctx, String::from_utf8_lossy(&data).to_string());
+                            }
+                            Err(err) => {
+                                let message = err.to_string();
+                                self.status.report(format!("read {}", path), message);
+                            }
+                        }
+                    }
+                });
+                ui.horizontal(|ui| {
+                    ui.label("symbols dir:");
+                    ui.text_edit_singleline(&mut self.symbol_dir);
+                    if ui.button("Symbolicate").clicked() {
+                        self.symbolicate(ctx);
+                    }
+                    ui.checkbox(&mut self.show_raw, "Raw");
+                });
+                self.status.show_error(ui);
+                ui.separator();
+
+                egui::SidePanel
+                    ::left("tombstone_list")
+                    .resizable(true)
+                    .default_width(160.0)
+                    .show_inside(ui, |ui| {
+                        if self.names.is_empty() {
+                            ui.label("No tombstone found");
+                        }
+                        let names = self.names.clone();
+                        for name in names.iter() {
+                            let selected = self.selected.as_ref() == Some(name);
+                            if ui.selectable_label(selected, name).clicked() {
+                                if let Some(device) = device {
+                                    self.pull(ctx, adb_path, device, name);
+                                }
+                            }
+                        }
+                    });
+
+                egui::ScrollArea
+                    ::both()
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        if self.show_raw {
+                            ui.monospace(&self.raw);
+                            return;
+                        }
+                        if let Some(tombstone) = self.tombstone.as_ref() {
+                            self.show_tombstone(ui, tombstone);
+                        }
+                    });
+            });
+        self.open = open;
+    }
+
+    fn show_tombstone(&self, ui: &mut egui::Ui, tombstone: &Tombstone) {
+        let process = tombstone.process_name.as_deref().unwrap_or("?");
+        let thread = tombstone.thread_name.as_deref().unwrap_or("?");
+        ui.label(
+            format!(
+                "pid: {}, tid: {}, {} ({})",
+                tombstone.pid.map(|p| p.to_string()).unwrap_or_default(),
+                tombstone.tid.map(|t| t.to_string()).unwrap_or_default(),
+                process,
+                thread
+            )
+        );
+        if let Some(signal) = tombstone.signal.as_ref() {
+            ui.colored_label(egui::Color32::LIGHT_RED, signal);
+        }
+        if let Some(message) = tombstone.abort_message.as_ref() {
+            ui.label(format!("Abort message: {}", message));
+        }
+        if let Some(fingerprint) = tombstone.fingerprint.as_ref() {
+            ui.label(format!("Build: {}", fingerprint));
+        }
+        ui.separator();
+
+        egui::Grid
+            ::new("tombstone_frames")
+            .striped(true)
+            .spacing(egui::vec2(10.0, 4.0))
+            .show(ui, |ui| {
+                for (i, frame) in tombstone.frames.iter().enumerate() {
+                    ui.monospace(format!("#{:02}", frame.index));
+                    ui.monospace(format!("{:016x}", frame.pc));
+                    ui.monospace(&frame.library);
+                    let symbols = self.symbols.get(i).filter(|s| !s.is_empty());
+                    match symbols {
+                        Some(symbols) => {
+                            ui.vertical(|ui| {
+                                for symbol in symbols.iter() {
+                                    ui.colored_label(
+                                        egui::Color32::LIGHT_GREEN,
+                                        format_symbol(symbol)
+                                    );
+                                }
+                            });
+                        }
+                        None => {
+                            let function = match (&frame.function, frame.function_offset) {
+                                (Some(f), Some(offset)) => format!("{}+{}", f, offset),
+                                (Some(f), None) => f.clone(),
+                                _ => String::new(),
+                            };
+                            ui.monospace(function);
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+fn format_symbol(symbol: &Symbol) -> String {
+    let function = symbol.function.as_deref().unwrap_or("??");
+    match (&symbol.file, symbol.line) {
+        (Some(file), Some(line)) => format!("{} {}:{}", function, file, line),
+        (Some(file), None) => format!("{} {}", function, file),
+        _ => function.to_string(),
+    }
+}
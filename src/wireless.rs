@@ -0,0 +1,124 @@
+use crate::AdbError;
+
+/// The port `adb tcpip` switches to by default.
+pub const TCPIP_PORT: u16 = 5555;
+
+/// `host:port`, with the default port added to a bare host.
+pub fn endpoint(text: &str, default_port: u16) -> String {
+    let text = text.trim();
+    // `[fe80::1]:5555` and plain ipv4 or names
+    let has_port = match text.rsplit_once(':') {
+        Some((host, port)) => {
+            port.parse::<u16>().is_ok() && (!host.contains(':') || host.ends_with(']'))
+        }
+        None => false,
+    };
+    if has_port || text.is_empty() {
+        text.to_string()
+    } else {
+        format!("{}:{}", text, default_port)
+    }
+}
+
+fn adb_output(adb_path: &str, args: &[&str]) -> Result<String, AdbError> {
+    let output = crate::run_adb(adb_path, None, args)?;
+    Ok(String::from_utf8_lossy(&output).trim().to_string())
+}
+
+/// `adb connect`, which exits 0 on failures and says so.
+pub fn connect(adb_path: &str, endpoint: &str) -> Result<String, AdbError> {
+    let message = adb_output(adb_path, &["connect", endpoint])?;
+    if message.starts_with("connected to") || message.starts_with("already connected to") {
+        Ok(message)
+    } else {
+        Err(AdbError::Command(message))
+    }
+}
+
+pub fn disconnect(adb_path: &str, endpoint: &str) -> Result<String, AdbError> {
+    adb_output(adb_path, &["disconnect", endpoint])
+}
+
+/// `adb pair` with the six digit code of the Wireless debugging screen, Android 11 and up.
+pub fn pair(adb_path: &str, endpoint: &str, code: &str) -> Result<String, AdbError> {
+    let message = adb_output(adb_path, &["pair", endpoint, code.trim()])?;
+    if message.contains("Successfully paired") {
+        Ok(message)
+    } else {
+        Err(AdbError::Command(message))
+    }
+}
+
+/// A service of `adb mdns services`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MdnsService {
+    /// `adb-<serial>-<id>`
+    pub name: String,
+    /// `_adb-tls-connect._tcp` or `_adb-tls-pairing._tcp`
+    pub service: String,
+    pub endpoint: String,
+}
+
+impl MdnsService {
+    pub fn is_pairing(&self) -> bool {
+        self.service.starts_with("_adb-tls-pairing")
+    }
+}
+
+/// Parses `adb mdns services`, one `name service host:port` per line.
+pub fn parse_mdns_services(text: &str) -> Vec<MdnsService> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            let service = fields.next()?.trim_end_matches('.');
+            let endpoint = fields.next()?;
+            if !service.starts_with('_') || fields.next().is_some() {
+                return None;
+            }
+            Some(MdnsService {
+                name: name.to_string(),
+                service: service.to_string(),
+                endpoint: endpoint.to_string(),
+            })
+        })
+        .collect()
+}
+
+pub fn mdns_services(adb_path: &str) -> Result<Vec<MdnsService>, AdbError> {
+    Ok(parse_mdns_services(&adb_output(adb_path, &["mdns", "services"])?))
+}
+
+/// The ipv4 address of `ip -f inet addr show wlan0`.
+pub fn parse_inet_address(text: &str) -> Option<String> {
+    text.lines()
+        .filter_map(|line| line.trim().strip_prefix("inet "))
+        .filter_map(|rest| rest.split(['/', ' ']).next())
+        .find(|address| !address.starts_with("127."))
+        .map(|address| address.to_string())
+}
+
+pub fn wifi_address(adb_path: &str, device: &str) -> Result<String, AdbError> {
+    let args = ["shell", "ip", "-f", "inet", "addr", "show", "wlan0"];
+    let output = crate::run_adb(adb_path, Some(device), &args)?;
+    parse_inet_address(&String::from_utf8_lossy(&output))
+        .ok_or_else(|| AdbError::Command(format!("{} is not on a Wi-Fi network", device)))
+}
+
+/// Restarts adbd of a USB device on a TCP port and connects to it over Wi-Fi.
+/// Returns the endpoint connected to.
+pub fn switch_to_tcpip(adb_path: &str, device: &str, port: u16) -> Result<String, AdbError> {
+    let address = wifi_address(adb_path, device)?;
+    crate::run_adb(adb_path, Some(device), &["tcpip", &port.to_string()])?;
+    let endpoint = format!("{}:{}", address, port);
+    // adbd takes a moment to listen again
+    let mut result = Err(AdbError::Command(format!("cannot connect to {}", endpoint)));
+    for _ in 0..5 {
+        std::thread::sleep(std::time::Duration::from_secs(1));
+        result = connect(adb_path, &endpoint);
+        if result.is_ok() {
+            break;
+        }
+    }
+    result.map(|_| endpoint)
+}
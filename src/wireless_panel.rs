@@ -0,0 +1,294 @@
+use std::thread::JoinHandle;
+
+use adbx::wireless::{ self, MdnsService, TCPIP_PORT };
+use adbx::AdbError;
+
+use crate::panel_status::PanelStatus;
+
+const RECENT_ENDPOINT_LIMIT: usize = 10;
+
+// what a finished task did, with adb's message
+enum Done {
+    Connected {
+        endpoint: String,
+        message: String,
+    },
+    Paired(String),
+    Disconnected(String),
+    Scanned(Vec<MdnsService>),
+}
+
+// connect waits for unreachable hosts, the UI does not
+struct Task {
+    command: String,
+    handle: JoinHandle<Result<Done, AdbError>>,
+}
+
+/// `adb connect`, `adb pair`, mDNS discovery and switching a USB device to TCP/IP.
+pub struct WirelessPanel {
+    pub open: bool,
+    endpoint: String,
+    pair_endpoint: String,
+    pair_code: String,
+    services: Vec<MdnsService>,
+    task: Option<Task>,
+    status: PanelStatus,
+}
+
+impl WirelessPanel {
+    pub fn new() -> Self {
+        WirelessPanel {
+            open: false,
+            endpoint: String::new(),
+            pair_endpoint: String::new(),
+            pair_code: String::new(),
+            services: Vec::new(),
+            task: None,
+            status: PanelStatus::default(),
+        }
+    }
+
+    fn run(
+        &mut self,
+        ctx: &egui::Context,
+        command: String,
+        task: impl FnOnce() -> Result<Done, AdbError> + Send + 'static
+    ) {
+        if self.task.is_some() {
+            return;
+        }
+        ::log::info!("> {}", command);
+        self.status.clear();
+        let ctx = ctx.clone();
+        let handle = std::thread::spawn(move || {
+            let result = task();
+            ctx.request_repaint();
+            result
+        });
+        self.task = Some(Task { command, handle });
+    }
+
+    fn connect(&mut self, ctx: &egui::Context, adb_path: &str, endpoint: &str) {
+        let endpoint = wireless::endpoint(endpoint, TCPIP_PORT);
+        if endpoint.is_empty() {
+            return;
+        }
+        let command = adbx::command_line(adb_path, None, &["connect", &endpoint]);
+        let adb_path = adb_path.to_string();
+        self.run(ctx, command, move || {
+            let message = wireless::connect(&adb_path, &endpoint)?;
+            Ok(Done::Connected { endpoint, message })
+        });
+    }
+
+    fn disconnect(&mut self, ctx: &egui::Context, adb_path: &str, endpoint: &str) {
+        let command = adbx::command_line(adb_path, None, &["disconnect", endpoint]);
+        let (adb_path, endpoint) = (adb_path.to_string(), endpoint.to_string());
+        self.run(ctx, command, move || {
+            wireless::disconnect(&adb_path, &endpoint).map(Done::Disconnected)
+        });
+    }
+
+    fn scan(&mut self, ctx: &egui::Context, adb_path: &str) {
+        let command = adbx::command_line(adb_path, None, &["mdns", "services"]);
+        let adb_path = adb_path.to_string();
+        self.run(ctx, command, move || wireless::mdns_services(&adb_path).map(Done::Scanned));
+    }
+
+    fn switch_to_tcpip(&mut self, ctx: &egui::Context, adb_path: &str, device: &str) {
+        let args = ["tcpip", &TCPIP_PORT.to_string()];
+        let command = adbx::command_line(adb_path, Some(device), &args);
+        let (adb_path, device) = (adb_path.to_string(), device.to_string());
+        self.run(ctx, command, move || {
+            let endpoint = wireless::switch_to_tcpip(&adb_path, &device, TCPIP_PORT)?;
+            let message = format!("connected to {}, the USB cable can go", endpoint);
+            Ok(Done::Connected { endpoint, message })
+        });
+    }
+
+    // true when the device list changed
+    fn poll(&mut self, recent: &mut Vec<String>) -> bool {
+        let finished = self.task.as_ref().is_some_and(|task| task.handle.is_finished());
+        let Some(task) = finished.then(|| self.task.take()).flatten() else {
+            return false;
+        };
+        let result = task.handle
+            .join()
+            .unwrap_or_else(|_| Err(AdbError::Command("thread panicked".to_string())));
+        let done = match result {
+            Ok(done) => done,
+            Err(err) => {
+                self.status.report(task.command, err.to_string());
+                return false;
+            }
+        };
+        match done {
+            Done::Connected { endpoint, message } => {
+                ::log::info!("{}", message);
+                self.status.set(message);
+                recent.retain(|e| e != &endpoint);
+                recent.insert(0, endpoint);
+                recent.truncate(RECENT_ENDPOINT_LIMIT);
+                true
+            }
+            Done::Paired(message) => {
+                ::log::info!("{}", message);
+                self.status.set(message);
+                false
+            }
+            Done::Disconnected(message) => {
+                ::log::info!("{}", message);
+                self.status.set(message);
+                true
+            }
+            Done::Scanned(services) => {
+                if services.is_empty() {
+                    self.status.set("no device advertised");
+                }
+                self.services = services;
+                false
+            }
+        }
+    }
+
+    /// `recent` are the endpoints connected to, newest first. Returns true when
+    /// devices were connected or disconnected.
+    pub fn show(
+        &mut self,
+        ctx: &egui::Context,
+        adb_path: &str,
+        device: Option<&str>,
+        recent: &mut Vec<String>
+    ) -> bool {
+        let changed = self.poll(recent);
+        if !self.open {
+            return changed;
+        }
+        let mut open = self.open;
+        egui::Window
+            ::new("Wireless Debugging")
+            .open(&mut open)
+            .default_size(egui::vec2(480.0, 460.0))
+            .resizable(true)
+            .show(ctx, |ui| {
+                let idle = self.task.is_none();
+                ui.heading("Connect");
+                ui.horizontal(|ui| {
+                    let field = ui.add(
+                        egui::TextEdit
+                            ::singleline(&mut self.endpoint)
+                            .hint_text("192.168.1.23:5555")
+                            .desired_width(200.0)
+                    );
+                    let enter = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    let clicked = ui.add_enabled(idle, egui::Button::new("Connect")).clicked();
+                    if idle && (clicked || enter) {
+                        let endpoint = self.endpoint.clone();
+                        self.connect(ctx, adb_path, &endpoint);
+                    }
+                });
+                let mut remove = None;
+                for endpoint in recent.iter() {
+                    ui.horizontal(|ui| {
+                        ui.monospace(endpoint);
+                        if ui.add_enabled(idle, egui::Button::new("Connect")).clicked() {
+                            self.connect(ctx, adb_path, endpoint);
+                        }
+                        if ui.add_enabled(idle, egui::Button::new("Disconnect")).clicked() {
+                            self.disconnect(ctx, adb_path, endpoint);
+                        }
+                        if ui.button("✖").on_hover_text("Forget").clicked() {
+                            remove = Some(endpoint.clone());
+                        }
+                    });
+                }
+                if let Some(endpoint) = remove {
+                    recent.retain(|e| e != &endpoint);
+                }
+
+                ui.separator();
+                ui.heading("Pair");
+                ui.weak("Android 11 and up: Wireless debugging > Pair device with pairing code");
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit
+                            ::singleline(&mut self.pair_endpoint)
+                            .hint_text("192.168.1.23:37123")
+                            .desired_width(200.0)
+                    );
+                    ui.add(
+                        egui::TextEdit
+                            ::singleline(&mut self.pair_code)
+                            .hint_text("code")
+                            .desired_width(70.0)
+                    );
+                    let ready = idle && !self.pair_endpoint.trim().is_empty();
+                    let ready = ready && !self.pair_code.trim().is_empty();
+                    if ui.add_enabled(ready, egui::Button::new("Pair")).clicked() {
+                        let endpoint = self.pair_endpoint.trim().to_string();
+                        let code = self.pair_code.trim().to_string();
+                        // the code is a secret, it is left out of the log
+                        let command = adbx::command_line(adb_path, None, &["pair", &endpoint]);
+                        let adb_path = adb_path.to_string();
+                        self.run(ctx, command, move || {
+                            wireless::pair(&adb_path, &endpoint, &code).map(Done::Paired)
+                        });
+                    }
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.heading("Discover");
+                    if ui.add_enabled(idle, egui::Button::new("Scan mDNS")).clicked() {
+                        self.scan(ctx, adb_path);
+                    }
+                });
+                let mut clicked = None;
+                egui::Grid
+                    ::new("mdns_services")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for service in self.services.iter() {
+                            ui.label(&service.name).on_hover_text(&service.service);
+                            ui.monospace(&service.endpoint);
+                            if service.is_pairing() {
+                                let hint = "Fill in the pair endpoint";
+                                if ui.button("Pair").on_hover_text(hint).clicked() {
+                                    self.pair_endpoint = service.endpoint.clone();
+                                }
+                            } else if ui.add_enabled(idle, egui::Button::new("Connect")).clicked() {
+                                clicked = Some(service.endpoint.clone());
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if let Some(endpoint) = clicked {
+                    self.connect(ctx, adb_path, &endpoint);
+                }
+
+                ui.separator();
+                ui.heading("USB to Wi-Fi");
+                match device {
+                    // serials with a port are network devices already
+                    Some(device) if !device.contains(':') => {
+                        let text = format!("Switch {} to TCP/IP {}", device, TCPIP_PORT);
+                        if ui.add_enabled(idle, egui::Button::new(text)).clicked() {
+                            self.switch_to_tcpip(ctx, adb_path, device);
+                        }
+                    }
+                    _ => {
+                        ui.weak("Select a USB device");
+                    }
+                }
+
+                ui.separator();
+                if !idle {
+                    ui.spinner();
+                }
+                self.status.show(ui);
+            });
+        self.open = open;
+        changed
+    }
+}
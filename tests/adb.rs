@@ -0,0 +1,70 @@
+use adbx::adb::parse_adb_version;
+use adbx::{ AdbDevice, AdbError };
+
+#[test]
+fn parses_adb_version() {
+    let output = "\
+Android Debug Bridge version 1.0.41
+Version 34.0.5-10900879
+Installed as /home/dev/Android/Sdk/platform-tools/adb
+Running on Linux 6.5.0-14-generic (x86_64)
+";
+    let version = parse_adb_version(output).unwrap();
+    assert_eq!(version.version, "1.0.41");
+    assert_eq!(version.protocol(), Some(41));
+    assert_eq!(version.revision.as_deref(), Some("34.0.5-10900879"));
+    assert_eq!(
+        version.installed_as.as_deref(),
+        Some("/home/dev/Android/Sdk/platform-tools/adb")
+    );
+
+    assert_eq!(parse_adb_version("bash: adb: command not found"), None);
+}
+
+#[test]
+fn classifies_adb_client_errors() {
+    let device = Some("R58M123");
+    let error = |stderr| AdbError::from_stderr(device, stderr);
+    let unauthorized = "adb: device unauthorized.\nThis adb server's $ADB_VENDOR_KEYS is not set";
+    assert!(matches!(error(unauthorized), AdbError::Unauthorized(_)));
+    assert!(matches!(error("error: device offline"), AdbError::DeviceOffline(_)));
+    assert!(matches!(error("adb: device 'R58M123' not found"), AdbError::DeviceNotFound(_)));
+    assert!(matches!(error("adb: no devices/emulators found"), AdbError::DeviceNotFound(_)));
+    let down = "* daemon not running; starting now at tcp:5037\nerror: cannot connect to daemon";
+    assert!(matches!(error(down), AdbError::ServerDown(_)));
+    // the stderr of a remote command is not about the device
+    assert!(matches!(error("sh: pidof: not found"), AdbError::Command(_)));
+    assert!(matches!(error("Error: service is not running"), AdbError::Command(_)));
+    assert!(matches!(error("cat: /sdcard/offline: No such file"), AdbError::Command(_)));
+}
+
+#[test]
+fn parses_device_states() {
+    let output = "\
+List of devices attached
+emulator-5554\tdevice
+R58M123\tunauthorized
+192.168.1.23:5555\toffline
+
+";
+    let devices = adbx::parse_devices(output);
+    assert_eq!(devices, [
+        AdbDevice { serial: "emulator-5554".to_string(), state: "device".to_string() },
+        AdbDevice { serial: "R58M123".to_string(), state: "unauthorized".to_string() },
+        AdbDevice { serial: "192.168.1.23:5555".to_string(), state: "offline".to_string() },
+    ]);
+    assert!(devices[0].check().is_ok());
+    let unauthorized = devices[1].check();
+    assert!(matches!(unauthorized, Err(AdbError::Unauthorized(serial)) if serial == "R58M123"));
+    assert!(matches!(devices[2].check(), Err(AdbError::DeviceOffline(_))));
+}
+
+#[test]
+fn parses_process_names() {
+    assert_eq!(
+        adbx::parse_process_name("NAME\ncom.example.app:remote\n"),
+        Some("com.example.app:remote".to_string())
+    );
+    assert_eq!(adbx::parse_process_name("NAME\n"), None);
+    assert_eq!(adbx::parse_process_name(""), None);
+}
@@ -0,0 +1,46 @@
+use adbx::capture::{ self, Marker, MarkerKind };
+
+fn encode(width: u32, height: u32, color: png::ColorType, data: &[u8]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, width, height);
+    encoder.set_color(color);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(data).unwrap();
+    writer.finish().unwrap();
+    bytes
+}
+
+#[test]
+fn marker_round_trips() {
+    let marker = Marker::new(
+        MarkerKind::Screenshot,
+        1_700_000_000_000,
+        "screenshots/emulator-5554-20231114-221320.png"
+    );
+    let line = marker.line();
+    assert_eq!(
+        line,
+        "--------- adbx screenshot at 2023-11-14 22:13:20 UTC: \
+         screenshots/emulator-5554-20231114-221320.png"
+    );
+    assert_eq!(Marker::parse(&line), Some(marker));
+    assert_eq!(Marker::parse("--------- beginning of main"), None);
+    let stop = Marker::new(MarkerKind::RecordStop, 0, "videos/a b.mp4");
+    assert_eq!(Marker::parse(&stop.line()), Some(stop));
+    assert_eq!(Marker::parse("--------- adbx unknown at now: x"), None);
+}
+
+#[test]
+fn decodes_rgb_and_gray_to_rgba() {
+    let rgb = encode(2, 1, png::ColorType::Rgb, &[255, 0, 0, 0, 0, 255]);
+    let image = capture::decode_png(&rgb).unwrap();
+    assert_eq!((image.width, image.height), (2, 1));
+    assert_eq!(image.rgba, [255, 0, 0, 255, 0, 0, 255, 255]);
+
+    let gray = encode(1, 2, png::ColorType::GrayscaleAlpha, &[10, 20, 30, 40]);
+    let image = capture::decode_png(&gray).unwrap();
+    assert_eq!(image.rgba, [10, 10, 10, 20, 30, 30, 30, 40]);
+
+    assert!(capture::decode_png(b"error: no display").is_err());
+}
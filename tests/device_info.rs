@@ -0,0 +1,95 @@
+use adbx::device_info::{ self, DeviceInfo, Display, Filesystem };
+
+#[test]
+fn parses_dumpsys_battery() {
+    let text = "\
+Current Battery Service state:
+  AC powered: false
+  USB powered: true
+  Wireless powered: false
+  Max charging current: 500000
+  status: 2
+  health: 2
+  present: true
+  level: 81
+  scale: 100
+  voltage: 4123
+  temperature: 312
+  technology: Li-ion
+";
+    let battery = device_info::parse_battery(text);
+    assert_eq!(battery.percent(), Some(81));
+    assert_eq!(battery.status.as_deref(), Some("Charging"));
+    assert_eq!(battery.health.as_deref(), Some("Good"));
+    assert_eq!(battery.plugged.as_deref(), Some("USB"));
+    assert_eq!((battery.temperature, battery.voltage), (Some(312), Some(4123)));
+    assert_eq!(battery.technology.as_deref(), Some("Li-ion"));
+}
+
+#[test]
+fn parses_wm_and_meminfo() {
+    let mut display = Display::default();
+    device_info::parse_wm_size("Physical size: 1080x2400\nOverride size: 720x1600\n", &mut display);
+    device_info::parse_wm_density("Physical density: 420\n", &mut display);
+    assert_eq!(display, Display {
+        size: Some((1080, 2400)),
+        override_size: Some((720, 1600)),
+        density: Some(420),
+        override_density: None,
+    });
+
+    let memory = device_info::parse_meminfo(
+        "\
+MemTotal:        7812036 kB
+MemFree:          226408 kB
+MemAvailable:    3123456 kB
+Buffers:            4140 kB
+Cached:          2885764 kB
+SwapTotal:       3145724 kB
+SwapFree:        2322428 kB
+"
+    );
+    assert_eq!(memory.total, Some(7_812_036));
+    assert_eq!(memory.available, Some(3_123_456));
+    assert_eq!(memory.cached, Some(2_885_764));
+    assert_eq!((memory.swap_total, memory.swap_free), (Some(3_145_724), Some(2_322_428)));
+}
+
+#[test]
+fn parses_df() {
+    let text = "\
+Filesystem             1K-blocks    Used Available Use% Mounted on
+/dev/block/dm-6          1010148 1005424         0 100% /
+tmpfs                    3906016    1236   3904780   1% /dev
+/dev/fuse              115056152 9876543 105048681   9% /storage/emulated
+df: /mnt/vendor/persist: Permission denied
+";
+    let storage = device_info::parse_df(text);
+    assert_eq!(storage.len(), 3);
+    assert_eq!(storage[2], Filesystem {
+        source: "/dev/fuse".to_string(),
+        size: 115_056_152,
+        used: 9_876_543,
+        available: 105_048_681,
+        mounted_on: "/storage/emulated".to_string(),
+    });
+}
+
+#[test]
+fn summarizes_props() {
+    let getprop = "\
+[ro.product.manufacturer]: [Google]
+[ro.product.model]: [Pixel 7]
+[ro.build.version.sdk]: [34]
+[ro.product.cpu.abilist]: [arm64-v8a,armeabi-v7a,armeabi]
+[ro.boot.serialno]: []
+";
+    let info = DeviceInfo {
+        props: adbx::parse_getprop(getprop),
+        ..DeviceInfo::default()
+    };
+    assert_eq!(info.model().as_deref(), Some("Google Pixel 7"));
+    assert_eq!(info.sdk(), Some(34));
+    assert_eq!(info.abis(), ["arm64-v8a", "armeabi-v7a", "armeabi"]);
+    assert_eq!(info.prop("ro.boot.serialno"), None);
+}
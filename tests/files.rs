@@ -0,0 +1,51 @@
+use adbx::files::{ self, FileEntry, FileKind };
+
+#[test]
+fn parses_toybox_ls() {
+    let text = "\
+total 64
+drwxrwx--x  4 system sdcard_rw 4096 2024-03-01 10:02 .
+drwx--x--x  4 root   root      4096 2024-03-01 10:02 ..
+drwxrwx--x  2 u0_a1  sdcard_rw 4096 2024-03-02 11:15 Download
+-rw-rw----  1 u0_a1  sdcard_rw 1234 2024-03-03 09:00 my notes.txt
+lrw-r--r--  1 root   root        21 2009-01-01 00:00 sdcard -> /storage/self/primary
+";
+    let entries = files::parse_ls(text);
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].name, "Download");
+    assert!(entries[0].is_dir());
+    assert_eq!(entries[1], FileEntry {
+        name: "my notes.txt".to_string(),
+        kind: FileKind::File,
+        size: Some(1234),
+        mode: "-rw-rw----".to_string(),
+        modified: "2024-03-03 09:00".to_string(),
+    });
+    assert_eq!(entries[2].name, "sdcard");
+    assert_eq!(entries[2].kind, FileKind::Link("/storage/self/primary".to_string()));
+}
+
+#[test]
+fn parses_toolbox_ls() {
+    // no link count, and no size for directories
+    let text = "\
+drwxr-xr-x root     root              2016-05-10 08:30 acct
+-rw-r--r-- root     root          563 1970-01-01 00:00 default.prop
+srw-rw---- system   system            2016-05-10 08:30 property_service
+";
+    let entries = files::parse_ls(text);
+    assert_eq!(entries.len(), 3);
+    assert_eq!((entries[0].name.as_str(), entries[0].size), ("acct", None));
+    assert_eq!((entries[1].name.as_str(), entries[1].size), ("default.prop", Some(563)));
+    assert_eq!(entries[2].kind, FileKind::Other);
+}
+
+#[test]
+fn skips_errors_and_quotes_paths() {
+    assert!(files::parse_ls("ls: /data: Permission denied\n").is_empty());
+    assert_eq!(files::shell_quote("it's here"), "'it'\\''s here'");
+    assert_eq!(files::join_remote("/sdcard/", "a b"), "/sdcard/a b");
+    assert_eq!(files::join_remote("/sdcard", "a"), "/sdcard/a");
+    assert_eq!(files::parent_remote("/sdcard/Download/"), "/sdcard");
+    assert_eq!(files::parent_remote("/sdcard"), "/");
+}
@@ -0,0 +1,14 @@
+#include <stdlib.h>
+
+static int divide(int a, int b) {
+    return a / b;
+}
+
+void crash_here(int *p) {
+    *p = divide(42, 0);
+}
+
+int entry(void) {
+    crash_here(NULL);
+    return 0;
+}
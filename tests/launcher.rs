@@ -0,0 +1,114 @@
+use adbx::launcher::{ self, Extra, ExtraKind, Intent, LaunchKind };
+
+#[test]
+fn builds_am_commands() {
+    let intent = Intent::launch("com.example.app", None);
+    assert_eq!(intent.target_package(), Some("com.example.app"));
+    assert_eq!(
+        launcher::am_command(LaunchKind::Activity, &intent).unwrap(),
+        "am start -W -a android.intent.action.MAIN -c android.intent.category.LAUNCHER \
+-p com.example.app"
+    );
+
+    let intent = Intent {
+        action: "com.example.PING".to_string(),
+        component: "com.example.app/.PingReceiver".to_string(),
+        extras: vec![
+            Extra {
+                kind: ExtraKind::String,
+                key: "message".to_string(),
+                value: "it's me".to_string(),
+            },
+            Extra {
+                kind: ExtraKind::Bool,
+                key: "loud".to_string(),
+                value: "true".to_string(),
+            },
+        ],
+        flags: "RECEIVER_FOREGROUND | 0x20".to_string(),
+        ..Intent::default()
+    };
+    assert_eq!(intent.target_package(), Some("com.example.app"));
+    assert_eq!(
+        launcher::am_command(LaunchKind::Broadcast, &intent).unwrap(),
+        "am broadcast -a com.example.PING -n com.example.app/.PingReceiver \
+--es message 'it'\\''s me' --ez loud true -f 0x10000020"
+    );
+}
+
+#[test]
+fn parses_flags() {
+    assert_eq!(launcher::parse_flags("").unwrap(), 0);
+    assert_eq!(
+        launcher::parse_flags("FLAG_ACTIVITY_NEW_TASK|activity_clear_task, 32").unwrap(),
+        0x1000_8020
+    );
+    assert!(launcher::parse_flags("ACTIVITY_BOGUS").is_err());
+}
+
+#[test]
+fn parses_am_output() {
+    let output = "\
+Starting: Intent { act=android.intent.action.MAIN cat=[android.intent.category.LAUNCHER] \
+pkg=com.example.app }
+Status: ok
+LaunchState: COLD
+Activity: com.example.app/.MainActivity
+TotalTime: 812
+WaitTime: 830
+Complete
+";
+    let result = launcher::parse_am_output(output).unwrap();
+    assert_eq!(result.status.as_deref(), Some("ok"));
+    assert_eq!(result.launch_state.as_deref(), Some("COLD"));
+    assert_eq!(result.activity.as_deref(), Some("com.example.app/.MainActivity"));
+    assert_eq!((result.total_time, result.wait_time), (Some(812), Some(830)));
+    assert_eq!(result.this_time, None);
+
+    let output = "\
+Starting: Intent { cmp=com.example.app/.MainActivity }
+Warning: Activity not started, its current task has been brought to the front
+Status: ok
+";
+    let result = launcher::parse_am_output(output).unwrap();
+    assert!(result.warning.unwrap().starts_with("Activity not started"));
+
+    let output = "\
+Broadcasting: Intent { act=com.example.PING flg=0x400000 }
+Broadcast completed: result=0
+";
+    let result = launcher::parse_am_output(output).unwrap();
+    assert_eq!(result.status.as_deref(), Some("result=0"));
+
+    let output = "\
+Starting: Intent { cmp=com.example.app/.Missing }
+Error type 3
+Error: Activity class {com.example.app/com.example.app.Missing} does not exist.
+";
+    let err = launcher::parse_am_output(output).unwrap_err();
+    assert!(err.to_string().contains("does not exist"));
+}
+
+#[test]
+fn parses_activities() {
+    let dumpsys = "\
+Activity Resolver Table:
+  Non-Data Actions:
+      android.intent.action.MAIN:
+        5f1e2d3 com.example.app/.MainActivity filter 9a8b7c6
+          Action: \"android.intent.action.MAIN\"
+          Category: \"android.intent.category.LAUNCHER\"
+      com.example.OPEN:
+        5f1e2d3 com.example.app/.MainActivity filter 1b2c3d4
+        6a7b8c9 com.example.app/com.example.feature.DetailActivity filter 2c3d4e5
+
+Receiver Resolver Table:
+  Non-Data Actions:
+      com.example.PING:
+        7d8e9f0 com.example.app/.PingReceiver filter 3d4e5f6
+";
+    assert_eq!(
+        launcher::parse_activities(dumpsys, "com.example.app"),
+        ["com.example.app/.MainActivity", "com.example.app/com.example.feature.DetailActivity"]
+    );
+}
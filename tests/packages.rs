@@ -0,0 +1,235 @@
+use std::io::Write;
+
+use adbx::packages::{ self, DeviceConfig, Package, Permission };
+
+#[test]
+fn parses_package_list() {
+    let text = "\
+package:/data/app/~~a1b2==/com.example.app-c3d4==/base.apk=com.example.app versionCode:42 uid:10123
+package:/system/priv-app/Settings/Settings.apk=com.android.settings versionCode:34 uid:1000
+package:/data/app/com.shared-1/base.apk=com.shared versionCode:7 uid:10200,1010200
+package:com.bare
+";
+    let packages = packages::parse_packages(text);
+    let names: Vec<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+    assert_eq!(names, ["com.android.settings", "com.bare", "com.example.app", "com.shared"]);
+    assert_eq!(packages[2], Package {
+        name: "com.example.app".to_string(),
+        path: "/data/app/~~a1b2==/com.example.app-c3d4==/base.apk".to_string(),
+        version_code: Some(42),
+        uid: Some(10123),
+        system: false,
+        disabled: false,
+    });
+    assert_eq!(packages[1].path, "");
+    assert_eq!(packages[1].version_code, None);
+    assert_eq!(packages[3].uid, Some(10200));
+}
+
+#[test]
+fn parses_permissions() {
+    let dumpsys = "\
+Packages:
+  Package [com.example.app] (1a2b3c):
+    userId=10123
+    requested permissions:
+      android.permission.INTERNET
+      android.permission.CAMERA
+      android.permission.READ_CONTACTS, restricted=true
+    install permissions:
+      android.permission.INTERNET: granted=true
+    User 0: ceDataInode=1234 installed=true hidden=false
+      runtime permissions:
+        android.permission.CAMERA: granted=false, flags=[ USER_SET ]
+        android.permission.READ_CONTACTS: granted=true
+";
+    let permissions = packages::parse_permissions(dumpsys);
+    let expected = [
+        ("android.permission.INTERNET", None),
+        ("android.permission.CAMERA", Some(false)),
+        ("android.permission.READ_CONTACTS", Some(true)),
+    ];
+    let expected: Vec<Permission> = expected
+        .iter()
+        .map(|(name, granted)| Permission { name: name.to_string(), granted: *granted })
+        .collect();
+    assert_eq!(permissions, expected);
+}
+
+// a zip with one local header and one central record per entry
+fn zip(entries: &[(&str, &[u8], bool)]) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut central = Vec::new();
+    for (name, content, deflate) in entries {
+        let mut crc = flate2::Crc::new();
+        crc.update(content);
+        let compressed = if *deflate {
+            let mut encoder = flate2::write::DeflateEncoder::new(
+                Vec::new(),
+                flate2::Compression::default()
+            );
+            encoder.write_all(content).unwrap();
+            encoder.finish().unwrap()
+        } else {
+            content.to_vec()
+        };
+        let method: u16 = if *deflate { 8 } else { 0 };
+        let offset = data.len() as u32;
+        let mut header = Vec::new();
+        header.extend_from_slice(&20u16.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+        header.extend_from_slice(&method.to_le_bytes());
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(&crc.sum().to_le_bytes());
+        header.extend_from_slice(&(compressed.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        header.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes());
+
+        data.extend_from_slice(&0x04034b50u32.to_le_bytes());
+        data.extend_from_slice(&header);
+        data.extend_from_slice(name.as_bytes());
+        data.extend_from_slice(&compressed);
+
+        central.extend_from_slice(&0x02014b50u32.to_le_bytes());
+        central.extend_from_slice(&20u16.to_le_bytes());
+        central.extend_from_slice(&header);
+        // comment length, disk, attributes
+        central.extend_from_slice(&[0; 10]);
+        central.extend_from_slice(&offset.to_le_bytes());
+        central.extend_from_slice(name.as_bytes());
+    }
+    let central_offset = data.len() as u32;
+    data.extend_from_slice(&central);
+    data.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    data.extend_from_slice(&[0; 4]);
+    data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    data.extend_from_slice(&(entries.len() as u16).to_le_bytes());
+    data.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    data.extend_from_slice(&central_offset.to_le_bytes());
+    data.extend_from_slice(&0u16.to_le_bytes());
+    data
+}
+
+#[test]
+fn extracts_apks_from_bundle() {
+    let dir = std::env::temp_dir().join(format!("adbx-packages-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let base = b"base apk ".repeat(100);
+    let bundle = zip(&[
+        ("toc.pb", b"table of contents", false),
+        ("splits/base.apk", &base, true),
+        ("splits/config.arm64_v8a.apk", b"native libs", false),
+    ]);
+    let bundle_path = dir.join("app.apks");
+    std::fs::write(&bundle_path, bundle).unwrap();
+    assert!(packages::is_bundle(&bundle_path));
+    assert!(packages::is_installable(&bundle_path));
+
+    let out = dir.join("out");
+    let config = DeviceConfig {
+        abis: vec!["arm64-v8a".to_string()],
+        density: 420,
+    };
+    let apks = packages::extract_apks(&bundle_path, &out, &config).unwrap();
+    assert_eq!(apks, [out.join("splits_base.apk"), out.join("splits_config.arm64_v8a.apk")]);
+    assert_eq!(std::fs::read(&apks[0]).unwrap(), base);
+    assert_eq!(std::fs::read(&apks[1]).unwrap(), b"native libs");
+
+    std::fs::write(&bundle_path, b"not a zip").unwrap();
+    assert!(packages::extract_apks(&bundle_path, &out, &config).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn extracted(apks: &[std::path::PathBuf]) -> Vec<String> {
+    apks.iter()
+        .map(|apk| apk.file_name().unwrap().to_string_lossy().to_string())
+        .collect()
+}
+
+#[test]
+fn picks_splits_for_the_device() {
+    let dir = std::env::temp_dir().join(format!("adbx-splits-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    // what `bundletool build-apks` writes, a feature module with its own native libs
+    let bundle = zip(&[
+        ("toc.pb", b"table of contents", false),
+        ("splits/base-master.apk", b"dex", true),
+        ("splits/base-armeabi_v7a.apk", b"arm", false),
+        ("splits/base-arm64_v8a.apk", b"arm64", false),
+        ("splits/base-x86_64.apk", b"x86_64", false),
+        ("splits/base-mdpi.apk", b"mdpi", false),
+        ("splits/base-xhdpi.apk", b"xhdpi", false),
+        ("splits/base-xxhdpi.apk", b"xxhdpi", false),
+        ("splits/base-xxxhdpi.apk", b"xxxhdpi", false),
+        ("splits/base-en.apk", b"en", false),
+        ("splits/base-de.apk", b"de", false),
+        ("splits/camera-master.apk", b"camera", true),
+        ("splits/camera-armeabi_v7a.apk", b"camera arm", false),
+        ("splits/camera-x86_64.apk", b"camera x86_64", false),
+        ("standalones/standalone-arm64_v8a_xxhdpi.apk", b"standalone", true),
+        ("standalones/standalone-x86_64_xxhdpi.apk", b"standalone", true),
+    ]);
+    let bundle_path = dir.join("app.apks");
+    std::fs::write(&bundle_path, bundle).unwrap();
+    let out = dir.join("out");
+
+    let phone = DeviceConfig {
+        abis: vec!["arm64-v8a".to_string(), "armeabi-v7a".to_string(), "armeabi".to_string()],
+        density: 440,
+    };
+    let apks = packages::extract_apks(&bundle_path, &out, &phone).unwrap();
+    assert_eq!(extracted(&apks), [
+        "splits_base-master.apk",
+        "splits_base-arm64_v8a.apk",
+        "splits_base-xxhdpi.apk",
+        "splits_base-en.apk",
+        "splits_base-de.apk",
+        "splits_camera-master.apk",
+        "splits_camera-armeabi_v7a.apk",
+    ]);
+    assert_eq!(std::fs::read(&apks[0]).unwrap(), b"dex");
+
+    // a screen denser than every bucket takes the largest
+    let emulator = DeviceConfig {
+        abis: vec!["x86_64".to_string(), "x86".to_string()],
+        density: 800,
+    };
+    let apks = packages::extract_apks(&bundle_path, &dir.join("emulator"), &emulator).unwrap();
+    assert!(extracted(&apks).contains(&"splits_base-x86_64.apk".to_string()));
+    assert!(extracted(&apks).contains(&"splits_base-xxxhdpi.apk".to_string()));
+    assert!(!extracted(&apks).iter().any(|apk| apk.contains("standalone")));
+
+    let mips = DeviceConfig {
+        abis: vec!["mips".to_string()],
+        density: 160,
+    };
+    assert!(packages::extract_apks(&bundle_path, &dir.join("mips"), &mips).is_err());
+
+    // an xapk keeps its splits next to the base apk
+    let xapk = zip(&[
+        ("manifest.json", b"{}", false),
+        ("icon.png", b"png", false),
+        ("com.example.app.apk", b"base", true),
+        ("config.armeabi_v7a.apk", b"arm", false),
+        ("config.arm64_v8a.apk", b"arm64", false),
+        ("config.hdpi.apk", b"hdpi", false),
+        ("config.xhdpi.apk", b"xhdpi", false),
+        ("config.en.apk", b"en", false),
+        ("Android/obb/com.example.app/main.1.com.example.app.obb", b"obb", false),
+    ]);
+    let xapk_path = dir.join("app.xapk");
+    std::fs::write(&xapk_path, xapk).unwrap();
+    let tablet = DeviceConfig {
+        abis: vec!["armeabi-v7a".to_string()],
+        density: 320,
+    };
+    let apks = packages::extract_apks(&xapk_path, &dir.join("xapk"), &tablet).unwrap();
+    assert_eq!(extracted(&apks), [
+        "com.example.app.apk",
+        "config.armeabi_v7a.apk",
+        "config.xhdpi.apk",
+        "config.en.apk",
+    ]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
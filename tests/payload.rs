@@ -0,0 +1,76 @@
+use adbx::payload::{ parse_message, Node };
+
+fn value(text: &str) -> Node {
+    Node::Value(text.to_string())
+}
+
+fn entry(key: &str, node: Node) -> (String, Node) {
+    (key.to_string(), node)
+}
+
+#[test]
+fn json_after_a_prefix() {
+    let message = r#"[main] response {"id": 7, "ok": true, "tags": ["a", "b"]} took 12ms"#;
+    let node = parse_message(message).unwrap();
+    assert_eq!(
+        node,
+        Node::Map(
+            vec![
+                entry("id", value("7")),
+                entry("ok", value("true")),
+                entry("tags", Node::List(vec![value("\"a\""), value("\"b\"")]))
+            ]
+        )
+    );
+}
+
+#[test]
+fn intent_dump() {
+    let message =
+        "START u0 {act=android.intent.action.MAIN cat=[android.intent.category.LAUNCHER] \
+         flg=0x10200000 cmp=com.example/.MainActivity} from uid 10123";
+    let node = parse_message(message).unwrap();
+    assert_eq!(
+        node,
+        Node::Map(
+            vec![
+                entry("act", value("android.intent.action.MAIN")),
+                entry("cat", Node::List(vec![value("android.intent.category.LAUNCHER")])),
+                entry("flg", value("0x10200000")),
+                entry("cmp", value("com.example/.MainActivity"))
+            ]
+        )
+    );
+}
+
+#[test]
+fn bundle_pairs_with_spaces() {
+    let message = "extras: Bundle[{reason=user pressed back, count=2, nested={a=1, b=[x, y]}}]";
+    let node = parse_message(message).unwrap();
+    assert_eq!(
+        node,
+        Node::Map(
+            vec![
+                entry("reason", value("user pressed back")),
+                entry("count", value("2")),
+                entry(
+                    "nested",
+                    Node::Map(
+                        vec![
+                            entry("a", value("1")),
+                            entry("b", Node::List(vec![value("x"), value("y")]))
+                        ]
+                    )
+                )
+            ]
+        )
+    );
+}
+
+#[test]
+fn plain_text_is_not_a_tree() {
+    assert_eq!(parse_message("Displayed com.example/.Main: +412ms"), None);
+    assert_eq!(parse_message("retry=3"), None);
+    assert_eq!(parse_message("GET /items?page=2&size=20"), None);
+    assert_eq!(parse_message("array [1, 2"), None);
+}
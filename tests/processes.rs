@@ -0,0 +1,63 @@
+use adbx::processes::{ self, Process };
+
+#[test]
+fn parses_ps() {
+    let text = "\
+  PID  PPID USER           RSS NAME
+    1     0 root          9876 init
+    2     0 root             0 [kthreadd]
+ 4321   612 u0_a123     184320 com.example.app:remote
+";
+    let processes = processes::parse_ps(text);
+    assert_eq!(processes.len(), 3);
+    assert_eq!(processes[1].name, "[kthreadd]");
+    assert_eq!(processes[2], Process {
+        pid: 4321,
+        ppid: 612,
+        user: "u0_a123".to_string(),
+        rss: 184320,
+        name: "com.example.app:remote".to_string(),
+        cpu: None,
+        mem: None,
+    });
+}
+
+#[test]
+fn parses_toybox_top() {
+    let text = "\
+Tasks: 512 total,   1 running, 511 sleeping,   0 stopped,   0 zombie
+  Mem:  5790880K total,  5514676K used,   276204K free,    42912K buffers
+800%cpu  11%user   0%nice  19%sys 770%idle   0%iow   0%irq   0%sirq   0%host
+  PID USER         PR  NI VIRT  RES  SHR S[%CPU] %MEM     TIME+ ARGS
+ 4321 u0_a123      10 -10  14G 180M 110M S 12.3   3.1   0:10.53 com.example.app
+ 1234 shell        20   0  10G 3.2M 2.4M R  3.4   0.0   0:00.03 top -b -n 1
+";
+    let usage = processes::parse_top(text);
+    assert_eq!(usage.len(), 2);
+    assert_eq!(usage[&4321], (12.3, Some(3.1)));
+    assert_eq!(usage[&1234], (3.4, Some(0.0)));
+}
+
+#[test]
+fn parses_toolbox_top_and_threads() {
+    let text = "\
+User 5%, System 3%, IOW 0%, IRQ 0%
+
+  PID PR CPU% S  #THR     VSS     RSS PCY UID      Name
+ 4321  0   7% S    31 812345K  65432K  fg u0_a123  com.example.app
+";
+    let usage = processes::parse_top(text);
+    assert_eq!(usage[&4321], (7.0, None));
+
+    let threads = processes::parse_threads("\
+  TID CMD
+ 4321 example.app
+ 4330 RenderThread
+ 4331 OkHttp Dispatcher
+");
+    assert_eq!(threads, [
+        (4321, "example.app".to_string()),
+        (4330, "RenderThread".to_string()),
+        (4331, "OkHttp Dispatcher".to_string()),
+    ]);
+}
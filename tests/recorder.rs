@@ -0,0 +1,51 @@
+use std::io::Read;
+
+use adbx::recorder::{ RecordOptions, Recorder };
+
+fn options(name: &str, gzip: bool) -> RecordOptions {
+    let dir = std::env::temp_dir().join(format!("adbx-record-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    RecordOptions {
+        dir,
+        prefix: "emulator-5554".to_string(),
+        max_file_bytes: 100,
+        max_files: 2,
+        gzip,
+    }
+}
+
+#[test]
+fn rotates_and_keeps_the_newest_files() {
+    let options = options("rotate", false);
+    let mut recorder = Recorder::start(options.clone()).unwrap();
+    for i in 0..10 {
+        let line = format!("{:<39}\n", format!("line {:02}", i));
+        recorder.write_line(line.as_bytes()).unwrap();
+    }
+    let files: Vec<_> = recorder.files().cloned().collect();
+    recorder.finish().unwrap();
+
+    // 40 bytes a line, two lines a file
+    assert_eq!(files.len(), 2);
+    assert!(files[1].ends_with("emulator-5554.005.log"));
+    assert!(!options.dir.join("emulator-5554.001.log").exists());
+    let last = std::fs::read_to_string(&files[1]).unwrap();
+    assert!(last.starts_with("line 08") && last.contains("line 09"));
+    assert_eq!(last.len(), 80);
+    std::fs::remove_dir_all(&options.dir).unwrap();
+}
+
+#[test]
+fn drop_finishes_the_gzip_stream() {
+    let options = options("gzip", true);
+    let mut recorder = Recorder::start(options.clone()).unwrap();
+    recorder.write_line(b"01-02 03:04:05.678  1234  1250 I Tag: tail\n").unwrap();
+    let path = recorder.files().next().unwrap().clone();
+    drop(recorder);
+
+    let mut text = String::new();
+    let file = std::fs::File::open(&path).unwrap();
+    flate2::read::GzDecoder::new(file).read_to_string(&mut text).unwrap();
+    assert_eq!(text, "01-02 03:04:05.678  1234  1250 I Tag: tail\n");
+    std::fs::remove_dir_all(&options.dir).unwrap();
+}
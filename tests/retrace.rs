@@ -0,0 +1,53 @@
+use adbx::retrace::{ Mapping, RetracedFrame };
+
+fn mapping() -> Mapping {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/mapping.txt");
+    Mapping::load(path).unwrap()
+}
+
+#[test]
+fn retraces_line_ranges() {
+    let mapping = mapping();
+    assert_eq!(mapping.class_count(), 3);
+
+    assert_eq!(mapping.retrace_frame("a.b.c", "d", Some(2)), vec![RetracedFrame {
+        class: "com.example.app.net.Client".to_string(),
+        method: "send".to_string(),
+        file: "Client.kt".to_string(),
+        line: Some(39),
+    }]);
+    // the same obfuscated name covers two original methods
+    assert_eq!(mapping.retrace_frame("a.b.c", "d", Some(5))[0].method, "retry");
+    assert_eq!(mapping.retrace_frame("a.b.c", "d", Some(5))[0].line, Some(56));
+}
+
+#[test]
+fn expands_inlined_frames() {
+    let frames = mapping().retrace_frame("com.example.app.MainActivity", "onCreate", Some(5));
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[0].class, "com.example.app.net.Client");
+    assert_eq!(frames[0].method, "send");
+    assert_eq!(frames[0].line, Some(41));
+    assert_eq!(frames[1].method, "onCreate");
+    assert_eq!(frames[1].file, "MainActivity.kt");
+    assert_eq!(frames[1].line, Some(24));
+}
+
+#[test]
+fn retraces_log_messages() {
+    let mapping = mapping();
+    assert_eq!(
+        mapping.retrace("\tat a.b.c.d(Unknown Source:3)").as_deref(),
+        Some("\tat com.example.app.net.Client.send(Client.kt:40)")
+    );
+    assert_eq!(
+        mapping.retrace("Caused by: a.b.d: closed by a.b.c.").as_deref(),
+        Some("Caused by: com.example.app.net.ClientException: closed by com.example.app.net.Client.")
+    );
+    // unknown frames and plain words are left alone
+    assert_eq!(mapping.retrace("\tat android.os.Looper.loop(Looper.java:288)"), None);
+    assert_eq!(mapping.retrace("a b c"), None);
+    // "at " inside a sentence is not a frame
+    assert_eq!(mapping.retrace("failed at a.b.c.d(Unknown Source:3)"), None);
+    assert_eq!(mapping.retrace("that a.b.c.d(Unknown Source:3)"), None);
+}
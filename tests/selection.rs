@@ -0,0 +1,97 @@
+// ranges are compared as slices of ranges, not expanded
+#![allow(clippy::single_range_in_vec_init)]
+
+use std::collections::BTreeSet;
+
+use adbx::selection::Selection;
+use proptest::prelude::*;
+
+#[test]
+fn shift_extends_from_the_anchor() {
+    let mut selection = Selection::new();
+    selection.select(10);
+    selection.extend_to(14);
+    assert_eq!(selection.ranges(), &[10..15]);
+    // moving back past the anchor flips the range
+    selection.extend_to(7);
+    assert_eq!(selection.ranges(), &[7..11]);
+    assert_eq!(selection.anchor(), Some(10));
+    assert_eq!(selection.cursor(), Some(7));
+}
+
+#[test]
+fn toggle_splits_and_merges() {
+    let mut selection = Selection::new();
+    selection.select(3);
+    selection.extend_to(7);
+    selection.toggle(5);
+    assert_eq!(selection.ranges(), &[3..5, 6..8]);
+    assert!(!selection.contains(5));
+    selection.toggle(5);
+    assert_eq!(selection.ranges(), &[3..8]);
+    assert_eq!(selection.len(), 5);
+}
+
+#[test]
+fn select_all_keeps_only_given_ids() {
+    // the lines that pass a filter
+    let mut selection = Selection::new();
+    selection.select_all([2, 3, 4, 9, 12, 13]);
+    assert_eq!(selection.ranges(), &[2..5, 9..10, 12..14]);
+    assert_eq!(selection.cursor(), Some(13));
+    selection.clear();
+    assert!(selection.is_empty());
+}
+
+#[derive(Clone, Debug)]
+enum Op {
+    Select(usize),
+    Toggle(usize),
+    Extend(usize),
+}
+
+fn op() -> impl Strategy<Value = Op> {
+    prop_oneof![
+        (0usize..64).prop_map(Op::Select),
+        (0usize..64).prop_map(Op::Toggle),
+        (0usize..64).prop_map(Op::Extend)
+    ]
+}
+
+proptest! {
+    #[test]
+    fn matches_a_set_of_ids(ops in prop::collection::vec(op(), 0..40)) {
+        let mut selection = Selection::new();
+        let mut model = BTreeSet::new();
+        let mut anchor = None;
+        for op in ops {
+            match op {
+                Op::Select(id) => {
+                    selection.select(id);
+                    model = BTreeSet::from([id]);
+                    anchor = Some(id);
+                }
+                Op::Toggle(id) => {
+                    selection.toggle(id);
+                    if !model.remove(&id) {
+                        model.insert(id);
+                    }
+                    anchor = Some(id);
+                }
+                Op::Extend(id) => {
+                    selection.extend_to(id);
+                    let from = *anchor.get_or_insert(id);
+                    model = (from.min(id)..=from.max(id)).collect();
+                }
+            }
+        }
+        for id in 0..66 {
+            prop_assert_eq!(selection.contains(id), model.contains(&id));
+        }
+        prop_assert_eq!(selection.len(), model.len());
+        // ranges stay sorted and never touch
+        for pair in selection.ranges().windows(2) {
+            prop_assert!(pair[0].end < pair[1].start);
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use adbx::device_info::{ Battery, DeviceInfo };
+use adbx::session::{
+    DeviceProps,
+    LineFields,
+    Session,
+    SessionBookmark,
+    SessionLine,
+    SessionRecording,
+};
+
+fn session() -> Session {
+    let raw = "01-02 03:04:05.678  1234  1250 I ActivityManager: Start proc";
+    Session {
+        version: adbx::session::SESSION_VERSION,
+        device: DeviceProps {
+            serial: "emulator-5554".to_string(),
+            model: Some("Pixel 7".to_string()),
+            fingerprint: Some("google/panther/panther:14/UQ1A/1:user/release-keys".to_string()),
+            sdk: Some(34),
+        },
+        started_at: 1_700_000_000_000,
+        ended_at: 1_700_000_060_000,
+        filter: Some("ActivityManager".to_string()),
+        lines: vec![
+            SessionLine {
+                raw: raw.to_string(),
+                fields: Some(LineFields {
+                    date: 0..5,
+                    time: 6..18,
+                    pid: 20..24,
+                    tid: 26..30,
+                    level: 31..32,
+                    tag: 33..48,
+                    message: 50..raw.len(),
+                }),
+            },
+            SessionLine {
+                raw: "--------- beginning of main".to_string(),
+                fields: None,
+            }
+        ],
+        bookmarks: vec![SessionBookmark {
+            line: 0,
+            note: "bug starts".to_string(),
+        }],
+        recordings: vec![SessionRecording {
+            path: "videos/emulator-5554-20231114-221320.mp4".to_string(),
+            started_at: 1_700_000_000_000,
+            ended_at: 1_700_000_030_000,
+            start_line: 1,
+            stop_line: None,
+            device_start: Some("01-02 03:04:05".to_string()),
+        }],
+        device_info: Some(DeviceInfo {
+            serial: "emulator-5554".to_string(),
+            captured_at: 1_700_000_060_000,
+            props: [("ro.product.model".to_string(), "Pixel 7".to_string())].into(),
+            battery: Battery {
+                level: Some(80),
+                scale: Some(100),
+                ..Battery::default()
+            },
+            ..DeviceInfo::default()
+        }),
+    }
+}
+
+#[test]
+fn round_trips_compressed() {
+    let session = session();
+    let mut data = Vec::new();
+    session.write_to(&mut data).unwrap();
+    assert_eq!(&data[..4], b"ADBX");
+    assert_eq!(Session::read_from(data.as_slice()).unwrap(), session);
+}
+
+#[test]
+fn rejects_other_files_and_newer_versions() {
+    let err = Session::read_from(&b"01-02 03:04:05.678 plain text"[..]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+
+    let mut data = Vec::new();
+    session().write_to(&mut data).unwrap();
+    data[4..8].copy_from_slice(&99u32.to_le_bytes());
+    let err = Session::read_from(data.as_slice()).unwrap_err();
+    assert!(err.to_string().contains("newer"));
+}
+
+#[test]
+fn reads_device_props_from_getprop() {
+    let props = adbx::parse_getprop(
+        "[ro.build.version.sdk]: [34]\n[ro.product.model]: [Pixel 7]\n[persist.empty]: []\n"
+    );
+    assert_eq!(props.get("persist.empty").map(|v| v.as_str()), Some(""));
+    let device = DeviceProps::from_getprop("emulator-5554", &props);
+    assert_eq!(device.model.as_deref(), Some("Pixel 7"));
+    assert_eq!(device.sdk, Some(34));
+    assert_eq!(device.fingerprint, None);
+}
+
+#[test]
+fn formats_capture_times() {
+    assert_eq!(adbx::session::format_utc(0), "1970-01-01 00:00:00 UTC");
+    assert_eq!(adbx::session::format_utc(1_709_210_096_789), "2024-02-29 12:34:56 UTC");
+}
+
+#[test]
+fn matches_video_time_to_log_lines() {
+    let lines = [
+        "01-02 03:04:04.900  1234  1250 I Before: not in the video",
+        "--------- adbx screenrecord start at 2024-01-02 03:04:05 UTC: a.mp4",
+        "01-02 03:04:05.100  1234  1250 I Tap: down",
+        "01-02 03:04:07.250  1234  1250 I Tap: up",
+        "01-02 03:04:09.000  1234  1250 I Done: idle",
+    ];
+    let mut recording = SessionRecording {
+        path: "a.mp4".to_string(),
+        started_at: 0,
+        ended_at: 0,
+        start_line: 1,
+        stop_line: None,
+        device_start: Some("01-02 03:04:05".to_string()),
+    };
+    assert_eq!(recording.line_at(lines, 0), Some(2));
+    assert_eq!(recording.line_at(lines, 2_000), Some(3));
+    assert_eq!(recording.line_at(lines, 2_300), Some(4));
+    assert_eq!(recording.line_at(lines, 60_000), None);
+
+    // without the device clock the first line after the marker is the start
+    recording.device_start = None;
+    assert_eq!(recording.line_at(lines, 2_150), Some(3));
+
+    assert_eq!(adbx::session::threadtime_millis("02-01 00:00:01.5"), None);
+    assert_eq!(adbx::session::threadtime_millis("02-01 00:00:01.500"), Some(2_678_401_500));
+    assert_eq!(adbx::session::threadtime_millis("--------- beginning of main"), None);
+}
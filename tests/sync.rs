@@ -0,0 +1,360 @@
+use std::collections::BTreeMap;
+use std::io::{ Read, Write };
+use std::net::{ SocketAddr, TcpListener, TcpStream };
+use std::path::Path;
+use std::sync::{ Arc, Mutex };
+
+use adbx::files::Transfer;
+use adbx::sync::{ SyncClient, S_IFDIR, S_IFREG, SYNC_DATA_MAX };
+use adbx::AdbError;
+
+const DEVICE: &str = "emulator-5554";
+
+#[derive(Clone)]
+struct FakeFile {
+    mode: u32,
+    mtime: u32,
+    data: Vec<u8>,
+}
+
+/// What the fake adbd holds and saw, shared with the test.
+#[derive(Default)]
+struct FakeState {
+    files: BTreeMap<String, FakeFile>,
+    // sizes of the DATA chunks of every SEND
+    chunks: Vec<usize>,
+}
+
+/// A local adb server with one device whose `sync:` service keeps files in memory.
+struct FakeServer {
+    address: SocketAddr,
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl FakeServer {
+    fn start(files: &[(&str, u32, &[u8])]) -> FakeServer {
+        let mut state = FakeState::default();
+        for (path, mode, data) in files {
+            let file = FakeFile { mode: *mode, mtime: 1_700_000_000, data: data.to_vec() };
+            state.files.insert(path.to_string(), file);
+        }
+        let state = Arc::new(Mutex::new(state));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let shared = state.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let state = shared.clone();
+                std::thread::spawn(move || serve(stream.unwrap(), &state));
+            }
+        });
+        FakeServer { address, state }
+    }
+
+    fn connect(&self) -> SyncClient<TcpStream> {
+        SyncClient::connect_at(self.address, DEVICE).unwrap()
+    }
+}
+
+fn serve(mut stream: TcpStream, state: &Mutex<FakeState>) -> Option<()> {
+    // host:transport:<serial> and then sync:
+    let transport = read_host_request(&mut stream)?;
+    if transport != format!("host:transport:{}", DEVICE) {
+        let message = "device not found";
+        let reply = format!("FAIL{:04x}{}", message.len(), message);
+        stream.write_all(reply.as_bytes()).ok()?;
+        return None;
+    }
+    stream.write_all(b"OKAY").ok()?;
+    if read_host_request(&mut stream)? != "sync:" {
+        return None;
+    }
+    stream.write_all(b"OKAY").ok()?;
+    loop {
+        let (id, length) = read_header(&mut stream)?;
+        if &id == b"QUIT" {
+            return Some(());
+        }
+        let mut path = vec![0u8; length as usize];
+        stream.read_exact(&mut path).ok()?;
+        let path = String::from_utf8(path).ok()?;
+        let mut reply = Vec::new();
+        match &id {
+            b"STAT" => {
+                let state = state.lock().unwrap();
+                let (mode, size, mtime) = match stat(&state, &path) {
+                    Some(file) => (file.mode, file.data.len() as u32, file.mtime),
+                    None => (0, 0, 0),
+                };
+                put(&mut reply, b"STAT", &[mode, size, mtime]);
+            }
+            b"STA2" => {
+                let state = state.lock().unwrap();
+                reply.extend_from_slice(b"STA2");
+                stat2(&mut reply, stat(&state, &path).as_ref());
+            }
+            b"LIST" | b"LIS2" => {
+                let state = state.lock().unwrap();
+                let prefix = format!("{}/", path.trim_end_matches('/'));
+                let children = state.files.iter().filter(|(name, _)| {
+                    name.strip_prefix(&prefix).is_some_and(|rest| !rest.contains('/'))
+                });
+                for (name, file) in children {
+                    let name = &name[prefix.len()..];
+                    if &id == b"LIST" {
+                        let size = file.data.len() as u32;
+                        put(&mut reply, b"DENT", &[file.mode, size, file.mtime, name.len() as u32]);
+                    } else {
+                        reply.extend_from_slice(b"DNT2");
+                        stat2(&mut reply, Some(file));
+                        reply.extend_from_slice(&(name.len() as u32).to_le_bytes());
+                    }
+                    reply.extend_from_slice(name.as_bytes());
+                }
+                if &id == b"LIST" {
+                    put(&mut reply, b"DONE", &[0, 0, 0, 0]);
+                } else {
+                    reply.extend_from_slice(b"DONE");
+                    stat2(&mut reply, None);
+                    reply.extend_from_slice(&0u32.to_le_bytes());
+                }
+            }
+            b"SEND" => {
+                let (path, mode) = path.rsplit_once(',')?;
+                let mut data = Vec::new();
+                let mtime = loop {
+                    let (id, value) = read_header(&mut stream)?;
+                    match &id {
+                        b"DATA" => {
+                            let mut chunk = vec![0u8; value as usize];
+                            stream.read_exact(&mut chunk).ok()?;
+                            state.lock().unwrap().chunks.push(chunk.len());
+                            data.extend_from_slice(&chunk);
+                        }
+                        b"DONE" => {
+                            break value;
+                        }
+                        _ => {
+                            return None;
+                        }
+                    }
+                };
+                if path.starts_with("/system/") {
+                    fail(&mut reply, "couldn't create file: Read-only file system");
+                } else {
+                    let file = FakeFile { mode: mode.parse().ok()?, mtime, data };
+                    state.lock().unwrap().files.insert(path.to_string(), file);
+                    put(&mut reply, b"OKAY", &[0]);
+                }
+            }
+            b"RECV" => {
+                let file = state.lock().unwrap().files.get(&path).cloned();
+                match file {
+                    Some(file) => {
+                        for chunk in file.data.chunks(SYNC_DATA_MAX) {
+                            put(&mut reply, b"DATA", &[chunk.len() as u32]);
+                            reply.extend_from_slice(chunk);
+                        }
+                        put(&mut reply, b"DONE", &[0]);
+                    }
+                    None => fail(&mut reply, "No such file or directory"),
+                }
+            }
+            _ => {
+                return None;
+            }
+        }
+        stream.write_all(&reply).ok()?;
+    }
+}
+
+fn stat(state: &FakeState, path: &str) -> Option<FakeFile> {
+    let file = state.files.get(path).cloned();
+    // directories exist when something is inside them
+    let prefix = format!("{}/", path.trim_end_matches('/'));
+    let is_dir = || state.files.keys().any(|name| name.starts_with(&prefix));
+    let dir = || FakeFile { mode: S_IFDIR | 0o755, mtime: 0, data: Vec::new() };
+    file.or_else(|| is_dir().then(dir))
+}
+
+fn stat2(reply: &mut Vec<u8>, file: Option<&FakeFile>) {
+    let Some(file) = file else {
+        // ENOENT and zeros
+        reply.extend_from_slice(&2u32.to_le_bytes());
+        reply.extend_from_slice(&[0u8; 64]);
+        return;
+    };
+    reply.extend_from_slice(&0u32.to_le_bytes());
+    reply.extend_from_slice(&1u64.to_le_bytes());
+    reply.extend_from_slice(&42u64.to_le_bytes());
+    for value in [file.mode, 1, 10123, 1015] {
+        reply.extend_from_slice(&value.to_le_bytes());
+    }
+    reply.extend_from_slice(&(file.data.len() as u64).to_le_bytes());
+    for time in [file.mtime, file.mtime, file.mtime] {
+        reply.extend_from_slice(&(time as u64).to_le_bytes());
+    }
+}
+
+fn put(reply: &mut Vec<u8>, id: &[u8; 4], values: &[u32]) {
+    reply.extend_from_slice(id);
+    for value in values {
+        reply.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn fail(reply: &mut Vec<u8>, message: &str) {
+    put(reply, b"FAIL", &[message.len() as u32]);
+    reply.extend_from_slice(message.as_bytes());
+}
+
+fn read_host_request(stream: &mut TcpStream) -> Option<String> {
+    let mut length = [0u8; 4];
+    stream.read_exact(&mut length).ok()?;
+    let length = usize::from_str_radix(std::str::from_utf8(&length).ok()?, 16).ok()?;
+    let mut request = vec![0u8; length];
+    stream.read_exact(&mut request).ok()?;
+    String::from_utf8(request).ok()
+}
+
+fn read_header(stream: &mut TcpStream) -> Option<([u8; 4], u32)> {
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header).ok()?;
+    let id = header[..4].try_into().ok()?;
+    Some((id, u32::from_le_bytes(header[4..].try_into().ok()?)))
+}
+
+#[test]
+fn stats_files_and_directories() {
+    let server = FakeServer::start(&[("/sdcard/a.txt", S_IFREG | 0o660, b"hello")]);
+    let mut client = server.connect();
+
+    let stat = client.stat("/sdcard/a.txt").unwrap();
+    assert!(stat.is_file());
+    assert_eq!((stat.permissions(), stat.size, stat.mtime), (0o660, 5, 1_700_000_000));
+    assert!(client.stat("/sdcard").unwrap().is_dir());
+    // STAT has no error field, a missing file is all zeros
+    assert_eq!(client.stat("/missing").unwrap().mode, 0);
+
+    let stat = client.stat2("/sdcard/a.txt").unwrap();
+    assert_eq!((stat.ino, stat.uid, stat.gid, stat.size), (42, 10123, 1015, 5));
+    let missing = client.stat2("/missing").unwrap_err();
+    assert_eq!(missing.to_string(), "/missing: No such file or directory");
+    client.quit().unwrap();
+}
+
+#[test]
+fn lists_directories() {
+    let server = FakeServer::start(
+        &[
+            ("/sdcard/b.txt", S_IFREG | 0o644, b"bb"),
+            ("/sdcard/a.txt", S_IFREG | 0o644, b"a"),
+            ("/sdcard/Download/c.txt", S_IFREG | 0o644, b"ccc"),
+        ]
+    );
+    let mut client = server.connect();
+
+    let names: Vec<_> = client
+        .list("/sdcard")
+        .unwrap()
+        .into_iter()
+        .map(|entry| (entry.name, entry.stat.size))
+        .collect();
+    assert_eq!(names, [("a.txt".to_string(), 1), ("b.txt".to_string(), 2)]);
+
+    let entries = client.list2("/sdcard/Download/").unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].name, "c.txt");
+    assert_eq!((entries[0].stat.size, entries[0].stat.uid), (3, 10123));
+    assert!(client.list("/empty").unwrap().is_empty());
+}
+
+#[test]
+fn sends_in_chunks_with_mode_and_mtime() {
+    let server = FakeServer::start(&[]);
+    let mut client = server.connect();
+    let data: Vec<u8> = (0..SYNC_DATA_MAX * 2 + 100).map(|i| i as u8).collect();
+
+    let sent = client
+        .send(&mut data.as_slice(), "/sdcard/big.bin", S_IFREG | 0o600, 1_234_567)
+        .unwrap();
+    assert_eq!(sent, data.len() as u64);
+
+    let state = server.state.lock().unwrap();
+    assert_eq!(state.chunks, [SYNC_DATA_MAX, SYNC_DATA_MAX, 100]);
+    let file = &state.files["/sdcard/big.bin"];
+    assert_eq!((file.mode, file.mtime), (S_IFREG | 0o600, 1_234_567));
+    assert_eq!(file.data, data);
+}
+
+#[test]
+fn receives_what_was_sent() {
+    let server = FakeServer::start(&[]);
+    let mut client = server.connect();
+    let data: Vec<u8> = (0..SYNC_DATA_MAX + 7).map(|i| (i * 7) as u8).collect();
+    client.send(&mut data.as_slice(), "/data/local/tmp/x", S_IFREG | 0o644, 0).unwrap();
+
+    let mut received = Vec::new();
+    let count = client.recv("/data/local/tmp/x", &mut received).unwrap();
+    assert_eq!(count, data.len() as u64);
+    assert_eq!(received, data);
+
+    // an empty file is a DONE right away
+    client.send(&mut [].as_slice(), "/data/local/tmp/empty", S_IFREG | 0o644, 0).unwrap();
+    let mut received = Vec::new();
+    assert_eq!(client.recv("/data/local/tmp/empty", &mut received).unwrap(), 0);
+}
+
+#[test]
+fn reports_failures_and_keeps_going() {
+    let server = FakeServer::start(&[]);
+    let mut client = server.connect();
+
+    let err = client.recv("/nope", &mut Vec::new()).unwrap_err();
+    assert!(matches!(&err, AdbError::Command(message) if message == "No such file or directory"));
+    let err = client.send(&mut b"x".as_slice(), "/system/x", S_IFREG | 0o644, 0).unwrap_err();
+    assert_eq!(err.to_string(), "couldn't create file: Read-only file system");
+    // the stream is still in sync after a FAIL
+    assert_eq!(client.stat("/nope").unwrap().mode, 0);
+
+    let long = format!("/{}", "a".repeat(2000));
+    assert!(client.stat(&long).is_err());
+
+    let err = SyncClient::connect_at(server.address, "other").err().unwrap();
+    assert!(matches!(err, AdbError::DeviceNotFound(device) if device == "other"));
+}
+
+#[test]
+fn transfers_fail_with_the_device() {
+    let server = FakeServer::start(&[("/sdcard/a.txt", S_IFREG | 0o644, b"hello")]);
+    let dir = std::env::temp_dir().join(format!("adbx-transfer-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let local = dir.join("a.txt");
+    let pull = |remote: &str, local: &Path| {
+        Transfer::pull_at(server.address, "adb", DEVICE, None, remote, local, None).unwrap()
+    };
+    let push = |local: &Path, remote: &str| {
+        Transfer::push_at(server.address, "adb", DEVICE, None, local, remote).unwrap()
+    };
+
+    pull("/sdcard/a.txt", &local).join().unwrap();
+    assert_eq!(std::fs::read(&local).unwrap(), b"hello");
+
+    let missing = dir.join("missing.mp4");
+    let err = pull("/sdcard/missing.mp4", &missing).join().unwrap_err();
+    assert_eq!(err.to_string(), "/sdcard/missing.mp4: No such file or directory");
+    // no text file is left where the video should be
+    assert!(!missing.exists());
+    // a file already there is kept when the pull fails
+    std::fs::write(&missing, b"video").unwrap();
+    pull("/sdcard/missing.mp4", &missing).join().unwrap_err();
+    assert_eq!(std::fs::read(&missing).unwrap(), b"video");
+    assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+    let err = push(&local, "/system/a.txt").join().unwrap_err();
+    assert_eq!(err.to_string(), "couldn't create file: Read-only file system");
+    push(&local, "/sdcard/b.txt").join().unwrap();
+    assert_eq!(server.state.lock().unwrap().files["/sdcard/b.txt"].data, b"hello");
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,45 @@
+use adbx::tag_color::{ self, TagPalette, MIN_CONTRAST };
+use proptest::prelude::*;
+
+#[test]
+fn hash_is_stable() {
+    // FNV-1a, changing it would recolour every tag users know
+    assert_eq!(tag_color::stable_hash(""), 0xcbf29ce484222325);
+    assert_eq!(tag_color::stable_hash("a"), 0xaf63dc4c8601ec8c);
+}
+
+#[test]
+fn pinned_colors_win() {
+    let mut palette = TagPalette::for_background([0x1b, 0x1b, 0x1b]);
+    let hashed = palette.color("ActivityManager");
+    palette.pin("ActivityManager", [1, 2, 3]);
+    assert_eq!(palette.color("ActivityManager"), [1, 2, 3]);
+    palette.unpin("ActivityManager");
+    assert_eq!(palette.color("ActivityManager"), hashed);
+}
+
+#[test]
+fn short_and_unicode_tags() {
+    let palette = TagPalette::for_background([0xf8, 0xf8, 0xf8]);
+    for tag in ["", "a", "ab", "日本", "🦀", "é\u{301}"] {
+        assert!(palette.colors().contains(&palette.color(tag)));
+    }
+}
+
+proptest! {
+    #[test]
+    fn any_tag_gets_a_palette_color(tag in any::<String>(), bg in any::<[u8; 3]>()) {
+        let palette = TagPalette::for_background(bg);
+        let color = palette.color(&tag);
+        prop_assert!(palette.colors().contains(&color));
+        prop_assert_eq!(color, palette.color(&tag));
+    }
+
+    #[test]
+    fn palette_is_readable_on_any_background(bg in any::<[u8; 3]>()) {
+        let palette = TagPalette::for_background(bg);
+        for color in palette.colors() {
+            prop_assert!(tag_color::contrast_ratio(*color, bg) >= MIN_CONTRAST);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use adbx::terminal::{ indexed_rgb, Color, Terminal };
+
+#[test]
+fn prompt_colours_and_wrapping() {
+    let mut terminal = Terminal::new(10, 3, 100);
+    terminal.feed(b"\x1b[1;32mshell\x1b[0m:/ $ ");
+    let line: Vec<_> = terminal.lines().next().unwrap().to_vec();
+    assert_eq!(terminal.line_text(0), "shell:/ $");
+    assert_eq!(line[0].style.fg, Color::Indexed(2));
+    assert!(line[0].style.bold);
+    assert_eq!(line[5].style.fg, Color::Default);
+    // the prompt filled the last column, wrapping waits for the next character
+    assert_eq!(terminal.cursor(), Some((0, 9)));
+    terminal.feed(b"abc");
+    assert_eq!(terminal.line_text(1), "abc");
+    assert_eq!(terminal.cursor(), Some((1, 3)));
+}
+
+#[test]
+fn scrolled_lines_go_to_scrollback() {
+    let mut terminal = Terminal::new(20, 2, 2);
+    terminal.feed(b"one\r\ntwo\r\nthree\r\nfour\r\nfive");
+    // the oldest line was dropped past the limit
+    assert_eq!(terminal.line_count(), 4);
+    assert_eq!(terminal.text(), "two\nthree\nfour\nfive");
+}
+
+#[test]
+fn cursor_moves_and_erasing() {
+    let mut terminal = Terminal::new(10, 3, 0);
+    terminal.feed(b"hello\r\nworld");
+    terminal.feed(b"\x1b[1;3H\x1b[K");
+    assert_eq!(terminal.line_text(0), "he");
+    terminal.feed(b"\x1b[2;2H\x1b[2P");
+    assert_eq!(terminal.line_text(1), "wld");
+    terminal.feed(b"\x1b[2J\x1b[H$ ");
+    assert_eq!(terminal.text(), "$");
+    // a backspace from the shell line editor
+    terminal.feed(b"ls\x08\x1b[K");
+    assert_eq!(terminal.line_text(0), "$ l");
+}
+
+#[test]
+fn alternate_screen_restores_the_main_one() {
+    let mut terminal = Terminal::new(10, 3, 10);
+    terminal.feed(b"$ top\r\n");
+    terminal.feed(b"\x1b[?1049h\x1b[Hcpu 12%\x1b]0;title\x07");
+    assert_eq!(terminal.line_text(0), "cpu 12%");
+    terminal.feed(b"\x1b[?1049l");
+    assert_eq!(terminal.text(), "$ top");
+    assert_eq!(terminal.cursor(), Some((1, 0)));
+}
+
+#[test]
+fn utf8_split_across_reads() {
+    let mut terminal = Terminal::new(10, 1, 0);
+    let text = "日本".as_bytes();
+    terminal.feed(&text[..2]);
+    terminal.feed(&text[2..]);
+    terminal.feed(b"\xff!");
+    assert_eq!(terminal.line_text(0), "日本\u{fffd}!");
+}
+
+#[test]
+fn colour_table() {
+    assert_eq!(indexed_rgb(1), [0xcd, 0x31, 0x31]);
+    assert_eq!(indexed_rgb(16), [0, 0, 0]);
+    assert_eq!(indexed_rgb(231), [255, 255, 255]);
+    assert_eq!(indexed_rgb(232), [8, 8, 8]);
+}
+
+#[test]
+fn huge_cursor_moves_stop_at_the_edges() {
+    let mut terminal = Terminal::new(10, 3, 0);
+    terminal.feed(b"ab\x1b[18446744073709551615C\x1b[18446744073709551615B");
+    assert_eq!(terminal.cursor(), Some((2, 9)));
+    terminal.feed(b"\x1b[1;1H\x1b[18446744073709551615X\x1b[18446744073709551615E");
+    assert_eq!(terminal.line_text(0), "");
+    assert_eq!(terminal.cursor(), Some((2, 0)));
+}
+
+#[test]
+fn resizing_keeps_the_cursor_line() {
+    let mut terminal = Terminal::new(10, 3, 10);
+    terminal.feed(b"one\r\ntwo\r\n$ ls");
+    terminal.resize(6, 2);
+    assert_eq!((terminal.cols(), terminal.rows()), (6, 2));
+    // the top line went to the scrollback
+    assert_eq!(terminal.text(), "one\ntwo\n$ ls");
+    assert_eq!(terminal.cursor(), Some((2, 4)));
+    terminal.resize(12, 4);
+    terminal.feed(b"\r\n0123456789ab");
+    assert_eq!(terminal.line_text(3), "0123456789ab");
+}
@@ -0,0 +1,101 @@
+use std::path::PathBuf;
+
+use adbx::tombstone::{ parse_frame, parse_tombstone, Symbolizer };
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
+
+// libcrash.elf is tests/fixtures/crash.c built with `gcc -g -O0 -shared -fPIC`;
+// it is copied under its on-device name because *.so is gitignored
+fn symbol_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("adbx-symbols-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("x86_64")).unwrap();
+    std::fs::copy(fixture("libcrash.elf"), dir.join("x86_64").join("libcrash.so")).unwrap();
+    dir
+}
+
+#[test]
+fn parses_crashing_thread() {
+    let text = std::fs::read_to_string(fixture("tombstone_00")).unwrap();
+    let tombstone = parse_tombstone(&text);
+
+    assert_eq!(tombstone.pid, Some(4312));
+    assert_eq!(tombstone.tid, Some(4340));
+    assert_eq!(tombstone.thread_name.as_deref(), Some("RenderThread"));
+    assert_eq!(tombstone.process_name.as_deref(), Some("com.example.crash"));
+    assert_eq!(tombstone.abi.as_deref(), Some("x86_64"));
+    assert_eq!(tombstone.abort_message.as_deref(), Some("native crash in libcrash"));
+    assert!(tombstone.signal.unwrap().starts_with("signal 11 (SIGSEGV)"));
+    // the Signal Catcher backtrace belongs to another thread
+    assert_eq!(tombstone.frames.len(), 6);
+}
+
+#[test]
+fn parses_frame_variants() {
+    let frame = parse_frame(
+        "#00 pc 000000000000113b  /data/app/lib/x86_64/libcrash.so (crash_here+31) (BuildId: 6d1e)"
+    ).unwrap();
+    assert_eq!(frame.index, 0);
+    assert_eq!(frame.pc, 0x113b);
+    assert_eq!(frame.library, "/data/app/lib/x86_64/libcrash.so");
+    assert_eq!(frame.function.as_deref(), Some("crash_here"));
+    assert_eq!(frame.function_offset, Some(31));
+    assert_eq!(frame.build_id.as_deref(), Some("6d1e"));
+
+    let frame = parse_frame(
+        "#03 pc 00000000000a1b2c  /apex/libart.so (art::Invoke(art::Thread*, char const*)+732)"
+    ).unwrap();
+    assert_eq!(frame.function.as_deref(), Some("art::Invoke(art::Thread*, char const*)"));
+    assert_eq!(frame.function_offset, Some(732));
+
+    let frame = parse_frame("#05 pc 0000000000002f4c  [anon:dalvik-classes.dex extracted]");
+    let frame = frame.unwrap();
+    assert_eq!(frame.library, "[anon:dalvik-classes.dex extracted]");
+    assert_eq!(frame.function, None);
+
+    assert!(parse_frame("memory near rax:").is_none());
+}
+
+#[test]
+fn symbolicates_with_dwarf() {
+    let text = std::fs::read_to_string(fixture("tombstone_00")).unwrap();
+    let tombstone = parse_tombstone(&text);
+    let mut symbolizer = Symbolizer::new(symbol_dir(), tombstone.abi.as_deref());
+
+    let symbols = symbolizer.symbolicate(&tombstone.frames[0]);
+    assert_eq!(symbols.len(), 1);
+    assert_eq!(symbols[0].function.as_deref(), Some("crash_here"));
+    assert!(symbols[0].file.as_deref().unwrap().ends_with("crash.c"));
+    assert_eq!(symbols[0].line, Some(8));
+
+    let symbols = symbolizer.symbolicate(&tombstone.frames[1]);
+    assert_eq!(symbols[0].function.as_deref(), Some("entry"));
+    assert_eq!(symbols[0].line, Some(12));
+
+    // libraries without local symbols stay unresolved
+    assert!(symbolizer.symbolicate(&tombstone.frames[2]).is_empty());
+    assert!(symbolizer.symbolicate(&tombstone.frames[5]).is_empty());
+}
+
+#[test]
+fn symbolicates_only_the_matching_library() {
+    let text = std::fs::read_to_string(fixture("tombstone_00")).unwrap();
+    let tombstone = parse_tombstone(&text);
+    let dir = std::env::temp_dir().join(format!("adbx-symbols-abi-{}", std::process::id()));
+    // an ndk tree with the library built for another abi only
+    let other = dir.join("obj").join("local").join("arm64-v8a");
+    std::fs::create_dir_all(&other).unwrap();
+    std::fs::copy(fixture("libcrash.elf"), other.join("libcrash.so")).unwrap();
+
+    let mut symbolizer = Symbolizer::new(&dir, tombstone.abi.as_deref());
+    assert!(symbolizer.symbolicate(&tombstone.frames[0]).is_empty());
+    let mut symbolizer = Symbolizer::new(&dir, Some("arm64"));
+    assert_eq!(symbolizer.symbolicate(&tombstone.frames[0]).len(), 1);
+
+    // the same name built again has another build id
+    let mut frame = tombstone.frames[0].clone();
+    frame.build_id = Some("0123456789abcdef0123456789abcdef01234567".to_string());
+    assert!(symbolizer.symbolicate(&frame).is_empty());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
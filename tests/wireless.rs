@@ -0,0 +1,45 @@
+use adbx::wireless::{ self, MdnsService };
+
+#[test]
+fn adds_default_port() {
+    assert_eq!(wireless::endpoint(" 192.168.1.23 ", 5555), "192.168.1.23:5555");
+    assert_eq!(wireless::endpoint("192.168.1.23:40123", 5555), "192.168.1.23:40123");
+    assert_eq!(wireless::endpoint("pixel.local", 5555), "pixel.local:5555");
+    assert_eq!(wireless::endpoint("[fe80::1]:5555", 5555), "[fe80::1]:5555");
+    assert_eq!(wireless::endpoint("", 5555), "");
+}
+
+#[test]
+fn parses_mdns_services() {
+    let text = "\
+List of discovered mdns services
+adb-1A2B3C4D-xYz12a\t_adb-tls-connect._tcp\t192.168.1.23:37145
+adb-1A2B3C4D-xYz12a\t_adb-tls-pairing._tcp.\t192.168.1.23:41234
+";
+    let services = wireless::parse_mdns_services(text);
+    assert_eq!(services, [
+        MdnsService {
+            name: "adb-1A2B3C4D-xYz12a".to_string(),
+            service: "_adb-tls-connect._tcp".to_string(),
+            endpoint: "192.168.1.23:37145".to_string(),
+        },
+        MdnsService {
+            name: "adb-1A2B3C4D-xYz12a".to_string(),
+            service: "_adb-tls-pairing._tcp".to_string(),
+            endpoint: "192.168.1.23:41234".to_string(),
+        },
+    ]);
+    assert!(!services[0].is_pairing());
+    assert!(services[1].is_pairing());
+}
+
+#[test]
+fn parses_wlan_address() {
+    let text = "\
+30: wlan0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc mq state UP group default qlen 3000
+    inet 192.168.1.23/24 brd 192.168.1.255 scope global wlan0
+       valid_lft forever preferred_lft forever
+";
+    assert_eq!(wireless::parse_inet_address(text).as_deref(), Some("192.168.1.23"));
+    assert_eq!(wireless::parse_inet_address("Device \"wlan0\" does not exist.\n"), None);
+}